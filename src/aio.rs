@@ -0,0 +1,105 @@
+//! Asynchronous reading of LAS headers, for storage where latency of requests dominates (NFS, SMB).
+//! Many files are read at once on few threads, independently of the threads computing records.
+
+use crate::LasBoundsError;
+use crate::header;
+use las::Header;
+use std::fs::Metadata;
+use std::io::{Cursor, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::Semaphore;
+
+
+/// Reads up to `len` bytes from `start`.
+async fn read_range(file: &mut File, start: u64, len: u64) -> Result<Vec<u8>, LasBoundsError> {
+
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut bytes = Vec::new();
+    (&mut *file).take(len).read_to_end(&mut bytes).await?;
+    Ok(bytes)
+}
+
+/// Bytes of `len` from `start` out of the ranges read, clipped to the end of the range containing `start`.
+fn slice(ranges: &[(u64, Vec<u8>)], start: u64, len: u64) -> Result<Vec<u8>, LasBoundsError> {
+
+    ranges.iter()
+        .find(|(range_start, bytes)| start >= *range_start && start - range_start <= bytes.len() as u64)
+        .map(|(range_start, bytes)| {
+            let offset = (start - range_start) as usize;
+            let end = offset.saturating_add(len.min(usize::max_value() as u64) as usize).min(bytes.len());
+            bytes[offset..end].to_vec()
+        })
+        .ok_or_else(|| format!("Byte range at {} was not read", start).into())
+}
+
+/// Reads header with VLRs and EVLRs, see [`header::read_header_ranges`], in at most three requests:
+/// the largest header block, the rest up to point data and EVLRs if there are any.
+async fn read_header(path: &Path) -> Result<(Header, Metadata), LasBoundsError> {
+
+    let mut file = File::open(path).await?;
+    let metadata = file.metadata().await?;
+
+    let head = read_range(&mut file, 0, header::MAX_HEADER_SIZE).await?;
+    let raw = las::raw::Header::read_from(Cursor::new(&head))?;
+    let head_len = head.len() as u64;
+    let mut ranges = vec![(0, head)];
+    let vlr_end = u64::from(raw.offset_to_point_data);
+    if vlr_end > head_len {
+        ranges.push((head_len, read_range(&mut file, head_len, vlr_end - head_len).await?));
+    }
+    // EVLRs run to the end of the file, their start is left zero when there are none.
+    if let Some(evlr) = raw.evlr.filter(|evlr| evlr.number_of_evlrs > 0) {
+        let len = metadata.len().saturating_sub(evlr.start_of_first_evlr);
+        ranges.push((evlr.start_of_first_evlr, read_range(&mut file, evlr.start_of_first_evlr, len).await?));
+    }
+
+    let header = header::read_header_ranges(|start, len| slice(&ranges, start, len))?;
+    Ok((header, metadata))
+}
+
+/// Reads headers of the files with at most `concurrency` files in flight and sends what `f` makes
+/// of each result, with time spent reading the file. Results are sent in completion order.
+pub fn read_headers<T, F>(paths: Vec<PathBuf>, concurrency: usize, sender: Sender<T>, f: F)
+    where T: Send + 'static,
+          F: Fn(PathBuf, Duration, Result<(Header, Metadata), LasBoundsError>) -> T + Send + Sync + 'static {
+
+    // File operations run on the blocking pool of the runtime, sized for the requests in flight.
+    let runtime = tokio::runtime::Builder::new()
+                      .basic_scheduler()
+                      .max_threads(concurrency + 1)
+                      .build();
+    let mut runtime = match runtime {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            for p in paths {
+                let _ = sender.send(f(p, Duration::default(), Err(format!("Could not start I/O runtime: {}", e).into())));
+            }
+            return;
+        }
+    };
+
+    let f = Arc::new(f);
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    runtime.block_on(async move {
+        let tasks: Vec<_> = paths.into_iter()
+            .map(|p| {
+                let (f, semaphore, sender) = (f.clone(), semaphore.clone(), sender.clone());
+                tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await;
+                    let start = Instant::now();
+                    let result = read_header(&p).await;
+                    // Sending fails only when the writer stopped on error.
+                    let _ = sender.send(f(p, start.elapsed(), result));
+                })
+            })
+            .collect();
+        for task in tasks {
+            let _ = task.await;
+        }
+    });
+}
@@ -0,0 +1,245 @@
+//! Persistent cache of results of point scans, so unchanged files are not scanned again.
+//!
+//! Entries are kept in a JSON file keyed by path, and valid while file size, modification time
+//! and options affecting the scan are the same.
+
+use crate::LasBoundsError;
+use crate::footprint::{Footprint, FootprintOptions, Polygon, Ring};
+use crate::record::Flightline;
+use crate::scan::{PointStats, ScanOptions};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+
+const CACHE_VERSION: u64 = 2;
+
+/// Cached scan results of a single file.
+pub struct CacheEntry {
+    pub footprint: Footprint,
+    /// Statistics without collected coordinates.
+    pub stats: PointStats,
+    pub flightlines: Vec<Flightline>
+}
+
+pub struct Cache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Value>>,
+    changed: AtomicBool
+}
+
+/// Options the results depend on, entries computed with others are not used.
+pub fn options_key(footprint: &FootprintOptions, scan: &ScanOptions) -> String {
+
+    format!("{:?} alpha={} cell={} closing={} simplify={} thin={} xy={} gps_time={} classes={} returns={} bounds={} \
+             hexbin={:?} density_cell={:?} point_sources={} flightlines={} footprint_classes={:?}",
+            footprint.mode, footprint.alpha, footprint.cell, footprint.closing, footprint.simplify,
+            scan.thin, scan.xy, scan.gps_time, scan.classes, scan.returns, scan.bounds, scan.hexbin,
+            scan.density_cell, scan.point_sources, scan.flightlines, scan.footprint_classes)
+}
+
+/// Size and modification time identifying the file version, time as whole seconds and nanoseconds
+/// so it is compared exactly.
+fn identity(path: &Path) -> Option<(u64, u64, u32)> {
+
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_secs(), modified.subsec_nanos()))
+}
+
+fn ring_json(ring: &[(f64, f64)]) -> Value {
+    Value::Array(ring.iter().map(|(x, y)| json!([x, y])).collect())
+}
+
+fn parse_ring(value: &Value) -> Option<Ring> {
+    value.as_array()?.iter().map(|point| Some((point.get(0)?.as_f64()?, point.get(1)?.as_f64()?))).collect()
+}
+
+fn footprint_json(footprint: &Footprint) -> Value {
+
+    Value::Array(footprint.polygons.iter()
+        .map(|polygon| Value::Array(std::iter::once(&polygon.exterior).chain(&polygon.interiors).map(|r| ring_json(r)).collect()))
+        .collect())
+}
+
+fn parse_footprint(value: &Value) -> Option<Footprint> {
+
+    let polygons = value.as_array()?.iter()
+        .map(|rings| {
+            let mut rings = rings.as_array()?.iter().map(parse_ring).collect::<Option<Vec<Ring>>>()?.into_iter();
+            Some(Polygon { exterior: rings.next()?, interiors: rings.collect() })
+        })
+        .collect::<Option<Vec<Polygon>>>()?;
+    Some(Footprint { polygons })
+}
+
+fn parse_counts(value: &Value) -> Option<Vec<u64>> {
+    value.as_array()?.iter().map(Value::as_u64).collect()
+}
+
+/// Counts by grid position from `[[x, y, count], ...]`, missing ones are empty.
+fn parse_grid_counts(value: &Value) -> Option<HashMap<(i64, i64), u64>> {
+
+    match value {
+        Value::Null => Some(HashMap::new()),
+        counts => counts.as_array()?.iter()
+                        .map(|c| Some(((c.get(0)?.as_i64()?, c.get(1)?.as_i64()?), c.get(2)?.as_u64()?)))
+                        .collect()
+    }
+}
+
+fn grid_counts_json(counts: &HashMap<(i64, i64), u64>) -> Value {
+    counts.iter().map(|((x, y), count)| json!([x, y, count])).collect()
+}
+
+/// Flightlines from `[[point_source_id, point_count, footprint], ...]`, missing ones are empty.
+fn parse_flightlines(value: &Value) -> Option<Vec<Flightline>> {
+
+    match value {
+        Value::Null => Some(Vec::new()),
+        flightlines => flightlines.as_array()?.iter()
+            .map(|f| Some(Flightline {
+                point_source_id: f.get(0)?.as_u64()? as u16,
+                point_count: f.get(1)?.as_u64()?,
+                footprint: parse_footprint(f.get(2)?)?
+            }))
+            .collect()
+    }
+}
+
+fn flightlines_json(flightlines: &[Flightline]) -> Value {
+
+    flightlines.iter()
+        .map(|f| json!([f.point_source_id, f.point_count, footprint_json(&f.footprint)]))
+        .collect()
+}
+
+fn parse_stats(value: &Value) -> Option<PointStats> {
+
+    let gps_time = match &value["gps_time"] {
+        Value::Null => None,
+        range => Some((range.get(0)?.as_f64()?, range.get(1)?.as_f64()?))
+    };
+    let bounds = match &value["bounds"] {
+        Value::Null => None,
+        b => {
+            let v: Vec<f64> = b.as_array()?.iter().map(Value::as_f64).collect::<Option<_>>()?;
+            if v.len() != 6 {
+                return None;
+            }
+            Some(las::Bounds {
+                min: las::Vector { x: v[0], y: v[1], z: v[2] },
+                max: las::Vector { x: v[3], y: v[4], z: v[5] }
+            })
+        }
+    };
+
+    Some(PointStats {
+        xy: Vec::new(),
+        gps_time,
+        class_counts: parse_counts(&value["class_counts"])?,
+        return_counts: parse_counts(&value["return_counts"])?,
+        bounds,
+        hex_counts: parse_grid_counts(&value["hex_counts"])?,
+        cell_counts: parse_grid_counts(&value["cell_counts"])?,
+        point_sources: match &value["point_sources"] {
+            Value::Null => BTreeSet::new(),
+            ids => ids.as_array()?.iter().map(|id| id.as_u64().map(|id| id as u16)).collect::<Option<_>>()?
+        },
+        flightline_xy: BTreeMap::new()
+    })
+}
+
+fn stats_json(stats: &PointStats) -> Value {
+
+    json!({
+        "gps_time": stats.gps_time.map(|(start, end)| json!([start, end])),
+        "class_counts": stats.class_counts,
+        "return_counts": stats.return_counts,
+        "bounds": stats.bounds.map(|b| json!([b.min.x, b.min.y, b.min.z, b.max.x, b.max.y, b.max.z])),
+        "hex_counts": grid_counts_json(&stats.hex_counts),
+        "cell_counts": grid_counts_json(&stats.cell_counts),
+        "point_sources": stats.point_sources
+    })
+}
+
+impl Cache {
+
+    /// Loads the cache file, starting empty if it does not exist or was written by another version.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, LasBoundsError> {
+
+        let path = path.into();
+        let mut entries = HashMap::new();
+        if path.exists() {
+            let json: Value = serde_json::from_slice(&fs::read(&path)?)
+                                  .map_err(|e| format!("Invalid cache file {}: {}", path.to_string_lossy(), e))?;
+            if json["version"].as_u64() == Some(CACHE_VERSION) {
+                if let Some(Value::Object(map)) = json.get("entries") {
+                    entries.extend(map.iter().map(|(key, value)| (key.clone(), value.clone())));
+                }
+            } else {
+                warn!("Ignoring cache {} of another version", path.to_string_lossy());
+            }
+        }
+        debug!("Loaded {} cache entries from {}", entries.len(), path.to_string_lossy());
+
+        Ok(Cache { path, entries: Mutex::new(entries), changed: AtomicBool::new(false) })
+    }
+
+    /// Entry of the file, if it has not changed since it was cached with the same options.
+    pub fn get(&self, path: &Path, options_key: &str) -> Option<CacheEntry> {
+
+        let (size, secs, nanos) = identity(path)?;
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(path.to_string_lossy().as_ref())?;
+        if entry["size"].as_u64() != Some(size) || entry["mtime"].as_u64() != Some(secs)
+            || entry["mtime_nanos"].as_u64() != Some(u64::from(nanos)) || entry["options"].as_str() != Some(options_key) {
+            return None;
+        }
+
+        Some(CacheEntry {
+            footprint: parse_footprint(&entry["footprint"])?,
+            stats: parse_stats(&entry["stats"])?,
+            flightlines: parse_flightlines(&entry["flightlines"])?
+        })
+    }
+
+    pub fn insert(&self, path: &Path, options_key: &str, footprint: &Footprint, stats: &PointStats, flightlines: &[Flightline]) {
+
+        if let Some((size, secs, nanos)) = identity(path) {
+            let entry = json!({
+                "size": size,
+                "mtime": secs,
+                "mtime_nanos": nanos,
+                "options": options_key,
+                "footprint": footprint_json(footprint),
+                "stats": stats_json(stats),
+                "flightlines": flightlines_json(flightlines)
+            });
+            self.entries.lock().unwrap().insert(path.to_string_lossy().into_owned(), entry);
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes the cache file if there are new entries, replacing the old one only when complete.
+    pub fn save(&self) -> Result<(), LasBoundsError> {
+
+        if !self.changed.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let entries: Map<String, Value> = self.entries.lock().unwrap().clone().into_iter().collect();
+        let count = entries.len();
+        let json = json!({ "version": CACHE_VERSION, "entries": entries });
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, json.to_string())?;
+        fs::rename(&tmp, &self.path)?;
+        debug!("Saved {} cache entries to {}", count, self.path.to_string_lossy());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+//! Content hashes of files, so copies can be verified against the index after transfer.
+
+use crate::LasBoundsError;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom};
+use std::str::FromStr;
+use twox_hash::XxHash64;
+
+
+const BUFFER_SIZE: usize = 1 << 20;
+
+/// Hash function of the `checksum` attribute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Md5,
+    Sha256,
+    /// 64-bit xxHash with zero seed, much faster than the cryptographic ones.
+    Xxhash
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md5" => Ok(Algorithm::Md5),
+            "sha256" => Ok(Algorithm::Sha256),
+            "xxhash" => Ok(Algorithm::Xxhash),
+            _ => Err(format!("Unknown checksum algorithm: {}", s))
+        }
+    }
+}
+
+/// Reads the file from its start to the end, passing each chunk to `update`.
+fn read_chunks<R: Read + Seek, F: FnMut(&[u8])>(file: &mut R, mut update: F) -> Result<(), LasBoundsError> {
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            return Ok(());
+        }
+        update(&buffer[..n]);
+    }
+}
+
+/// Lowercase hex digest of the whole content of an open file, regardless of its current position.
+pub fn checksum<R: Read + Seek>(file: &mut R, algorithm: Algorithm) -> Result<String, LasBoundsError> {
+
+    match algorithm {
+        Algorithm::Md5 => {
+            let mut hasher = Md5::new();
+            read_chunks(file, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:x}", hasher.finalize()))
+        },
+        Algorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            read_chunks(file, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:x}", hasher.finalize()))
+        },
+        Algorithm::Xxhash => {
+            let mut hasher = XxHash64::with_seed(0);
+            read_chunks(file, |chunk| hasher.write(chunk))?;
+            Ok(format!("{:016x}", hasher.finish()))
+        }
+    }
+}
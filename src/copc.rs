@@ -0,0 +1,72 @@
+//! Cloud Optimized Point Cloud detection from the COPC info VLR.
+//!
+//! COPC files are LAZ 1.4 files, so their bounds are read from the header like for any other file;
+//! the octree root cube of the info VLR encloses them but is not tight, so it is taken only when the
+//! writer left header bounds zeroed. The info VLR lets the GPS time range be taken without scanning
+//! points.
+
+use las::{Bounds, Header, Vector, Vlr};
+use std::convert::TryInto;
+
+
+const COPC_USER_ID: &str = "copc";
+const COPC_INFO_RECORD_ID: u16 = 1;
+const COPC_INFO_LEN: usize = 160;
+
+/// Contents of the COPC info VLR, which COPC requires to be the first VLR of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopcInfo {
+    /// Center of the octree root cube.
+    pub center: (f64, f64, f64),
+    /// Half of the root cube side.
+    pub halfsize: f64,
+    /// Point spacing at the root level.
+    pub spacing: f64,
+    /// Minimum and maximum GPS time, `None` when both are zero (not computed by the writer).
+    pub gps_time: Option<(f64, f64)>
+}
+
+impl CopcInfo {
+
+    fn parse(data: &[u8]) -> Option<Self> {
+
+        if data.len() < COPC_INFO_LEN {
+            return None;
+        }
+
+        let f64_at = |i: usize| f64::from_le_bytes(data[i * 8..i * 8 + 8].try_into().unwrap());
+        // Fields 5 and 6 are offset and size of the root hierarchy page.
+        let gps_time = (f64_at(7), f64_at(8));
+        Some(CopcInfo {
+            center: (f64_at(0), f64_at(1), f64_at(2)),
+            halfsize: f64_at(3),
+            spacing: f64_at(4),
+            gps_time: Some(gps_time).filter(|&(min, max)| (min != 0.0 || max != 0.0) && min <= max)
+        })
+    }
+
+    /// Octree root cube, which encloses all points of the file.
+    pub fn cube(&self) -> Bounds {
+
+        let (x, y, z) = self.center;
+        let halfsize = self.halfsize;
+        Bounds {
+            min: Vector { x: x - halfsize, y: y - halfsize, z: z - halfsize },
+            max: Vector { x: x + halfsize, y: y + halfsize, z: z + halfsize }
+        }
+    }
+}
+
+/// Reads COPC info of the file, `None` for files that are not COPC.
+pub fn copc_info(header: &Header) -> Option<CopcInfo> {
+
+    vlrs_copc_info(header.vlrs())
+}
+
+/// Reads COPC info from VLRs of a header not built yet, see [`copc_info`].
+pub fn vlrs_copc_info(vlrs: &[Vlr]) -> Option<CopcInfo> {
+
+    vlrs.iter()
+        .find(|vlr| vlr.user_id == COPC_USER_ID && vlr.record_id == COPC_INFO_RECORD_ID)
+        .and_then(|vlr| CopcInfo::parse(&vlr.data))
+}
@@ -0,0 +1,126 @@
+//! Coordinate reference system detection from LAS header VLRs.
+
+use crate::LasBoundsError;
+use crate::geokeys::{self, GeoKeys};
+use crate::ogr;
+use gdal::spatial_ref::SpatialRef;
+use las::{Header, Vlr};
+use std::convert::TryFrom;
+use std::fmt;
+
+
+const PROJECTION_USER_ID: &str = "LASF_Projection";
+const OGC_WKT_RECORD_ID: u16 = 2112;
+
+/// CRS detected in a LAS file, kept in plain form so it can be compared and sent between threads.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Crs {
+    Epsg(u32),
+    Wkt(String)
+}
+
+impl Crs {
+
+    /// Spatial reference taking x as easting or longitude, as LAS coordinates and OGR layers do.
+    pub fn to_spatial_ref(&self) -> Result<SpatialRef, LasBoundsError> {
+
+        let srs = match self {
+            Self::Epsg(code) => SpatialRef::from_epsg(*code)?,
+            Self::Wkt(wkt) => SpatialRef::from_wkt(wkt)?
+        };
+        ogr::set_traditional_axis_order(&srs);
+        Ok(srs)
+    }
+
+    /// Whether it is the same CRS as `srs`, also when defined by WKT instead of EPSG code.
+    pub fn is_same(&self, srs: &SpatialRef) -> Result<bool, LasBoundsError> {
+        Ok(ogr::is_same_srs(&self.to_spatial_ref()?, srs))
+    }
+}
+
+impl fmt::Display for Crs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Epsg(code) => write!(f, "EPSG:{}", code),
+            Self::Wkt(wkt) => write!(f, "{}", wkt_name(wkt).unwrap_or("WKT"))
+        }
+    }
+}
+
+/// Name of the root WKT node, e.g. `ETRS89 / Poland CS92` for `PROJCS["ETRS89 / Poland CS92", ...]`.
+fn wkt_name(wkt: &str) -> Option<&str> {
+
+    let start = wkt.find("[\"")? + 2;
+    let len = wkt[start..].find('"')?;
+    Some(&wkt[start..start + len])
+}
+
+fn projection_vlrs<'a>(header: &'a Header) -> impl Iterator<Item = &'a Vlr> {
+    header.vlrs().iter()
+          .chain(header.evlrs().iter())
+          .filter(|vlr| vlr.user_id == PROJECTION_USER_ID)
+}
+
+/// Whether the VLR declares CRS, as OGC WKT or GeoKey directory.
+pub fn is_crs_vlr(vlr: &Vlr) -> bool {
+    vlr.user_id == PROJECTION_USER_ID &&
+        (vlr.record_id == OGC_WKT_RECORD_ID || vlr.record_id == geokeys::GEO_KEY_DIRECTORY_RECORD_ID)
+}
+
+fn read_wkt(header: &Header) -> Option<String> {
+
+    projection_vlrs(header)
+        .find(|vlr| vlr.record_id == OGC_WKT_RECORD_ID)
+        .map(|vlr| String::from_utf8_lossy(&vlr.data).trim_end_matches('\0').trim().to_string())
+        .filter(|wkt| !wkt.is_empty())
+}
+
+fn read_geokeys(header: &Header) -> Option<GeoKeys> {
+
+    let record = |record_id: u16| projection_vlrs(header)
+                                      .find(|vlr| vlr.record_id == record_id)
+                                      .map(|vlr| vlr.data.as_slice());
+
+    GeoKeys::parse(record(geokeys::GEO_KEY_DIRECTORY_RECORD_ID)?,
+                   record(geokeys::GEO_DOUBLE_PARAMS_RECORD_ID),
+                   record(geokeys::GEO_ASCII_PARAMS_RECORD_ID))
+}
+
+/// Detects CRS from the OGC WKT VLR or, for legacy files, from GeoTIFF GeoKeys.
+/// Returns `None` when the header carries no usable CRS information.
+pub fn detect_crs(header: &Header) -> Option<Crs> {
+    read_wkt(header).map(Crs::Wkt)
+                    .or_else(|| read_geokeys(header).and_then(|keys| keys.epsg()).map(Crs::Epsg))
+}
+
+/// Projection VLR declaring the CRS, used to carry CRS of non-LAS inputs in their headers.
+/// Returns `None` for EPSG codes that do not fit a GeoKey.
+pub fn projection_vlr(crs: &Crs) -> Option<Vlr> {
+
+    let (record_id, data) = match crs {
+        Crs::Epsg(code) => (geokeys::GEO_KEY_DIRECTORY_RECORD_ID, geokeys::epsg_directory(u16::try_from(*code).ok()?)),
+        Crs::Wkt(wkt) => (OGC_WKT_RECORD_ID, wkt.as_bytes().to_vec())
+    };
+    Some(Vlr { user_id: PROJECTION_USER_ID.to_string(), record_id, description: String::new(), data })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use gdal::spatial_ref::CoordTransform;
+
+    #[test]
+    fn reprojects_into_longitude_latitude_order() {
+
+        let source = Crs::Epsg(32633).to_spatial_ref().unwrap();
+        let target = Crs::Epsg(4326).to_spatial_ref().unwrap();
+        let transform = CoordTransform::new(&source, &target).unwrap();
+
+        // Central meridian of UTM zone 33N at 50 degrees north.
+        let (mut xs, mut ys, mut zs) = (vec![500_000.0], vec![5_538_630.7], vec![0.0]);
+        transform.transform_coords(&mut xs, &mut ys, &mut zs).unwrap();
+        assert!((xs[0] - 15.0).abs() < 1e-6, "longitude {}", xs[0]);
+        assert!((ys[0] - 50.0).abs() < 1e-3, "latitude {}", ys[0]);
+    }
+}
@@ -0,0 +1,190 @@
+//! Plain-text CSV output with WKT geometry column, written without OGR drivers.
+
+use crate::{BoundsRecord, BoundsWriter, LasBoundsError};
+use crate::fields::{self, Field, Value};
+use crate::writer::{self, Elevation};
+use gdal::spatial_ref::CoordTransform;
+use gdal::vector::Geometry;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+
+/// Name of the geometry column, the last one.
+const WKT_COLUMN: &str = "wkt";
+
+pub struct CsvOptions {
+    pub path: PathBuf,
+    /// Transformation of footprints into the output CRS.
+    pub transform: Option<CoordTransform>,
+    /// Write MultiPolygon instead of Polygon geometries.
+    pub multi: bool,
+    /// Write 3D polygons with Z of vertices from Z range of the file.
+    pub elevation: Option<Elevation>,
+    /// Distance footprints are expanded by after reprojection, in output CRS units.
+    pub buffer: Option<f64>,
+    /// Append rows to existing file, its columns have to match the fields.
+    pub append: bool,
+    /// Replace existing file.
+    pub overwrite: bool
+}
+
+/// Writes records as CSV rows with schema fields and WKT geometry.
+pub struct CsvWriter {
+    out: Option<BufWriter<File>>,
+    fields: Vec<Field>,
+    indexed: HashSet<String>,
+    transform: Option<CoordTransform>,
+    multi: bool,
+    elevation: Option<Elevation>,
+    buffer: Option<f64>
+}
+
+/// Quotes the value when it contains separator, quotes or line breaks.
+fn escape(value: &str) -> String {
+
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits CSV line into values, handling quoted ones.
+fn split_line(line: &str) -> Vec<String> {
+
+    let mut values = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                value.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => values.push(std::mem::replace(&mut value, String::new())),
+            c => value.push(c)
+        }
+    }
+    values.push(value);
+    values
+}
+
+/// Values of the path column of rows already in the file.
+fn read_indexed(path: &Path, fields: &[Field]) -> Result<HashSet<String>, LasBoundsError> {
+
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let columns = match lines.next() {
+        Some(line) => split_line(&line?),
+        None => return Ok(HashSet::new())
+    };
+    let expected: Vec<&str> = fields.iter().map(|field| field.name.as_ref()).chain(Some(WKT_COLUMN)).collect();
+    if columns != expected {
+        return Err(format!("Columns of {} do not match the fields: {}", path.to_string_lossy(), columns.join(",")).into());
+    }
+
+    let mut indexed = HashSet::new();
+    if let Some(index) = fields.iter().position(|field| field.key == fields::PATH.key) {
+        for line in lines {
+            if let Some(path) = split_line(&line?).into_iter().nth(index) {
+                indexed.insert(path);
+            }
+        }
+    }
+
+    Ok(indexed)
+}
+
+impl CsvWriter {
+
+    /// Creates the file with header row, or opens it when appending. Fails when the file
+    /// exists and neither append nor overwrite was requested.
+    pub fn new(options: CsvOptions, fields: &[Field]) -> Result<Self, LasBoundsError> {
+
+        let path = &options.path;
+        let exists = path.exists();
+        let append = options.append && exists;
+        if exists && !append && !options.overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite or --append", path.to_string_lossy()).into());
+        }
+
+        let (out, indexed) = if append {
+            debug!("Appending to {}", path.to_string_lossy());
+            let indexed = read_indexed(path, fields)?;
+            (BufWriter::new(OpenOptions::new().append(true).open(path)?), indexed)
+        } else {
+            debug!("Creating {}", path.to_string_lossy());
+            let mut out = BufWriter::new(File::create(path)?);
+            let columns: Vec<String> = fields.iter().map(|field| escape(&field.name)).collect();
+            writeln!(out, "{},{}", columns.join(","), WKT_COLUMN)?;
+            (out, HashSet::new())
+        };
+
+        Ok(CsvWriter {
+            out: Some(out),
+            fields: fields.to_vec(),
+            indexed,
+            transform: options.transform,
+            multi: options.multi,
+            elevation: options.elevation,
+            buffer: options.buffer
+        })
+    }
+
+    fn wkt(&self, record: &BoundsRecord) -> Result<String, LasBoundsError> {
+
+        let footprint = match &self.transform {
+            Some(_) => writer::densified_footprint(record),
+            None => record.footprint.clone()
+        };
+        let wkt = footprint.wkt(self.multi, self.elevation.map(|elevation| elevation.z(record)));
+
+        if self.transform.is_none() && self.buffer.is_none() {
+            return Ok(wkt);
+        }
+        let mut geometry = Geometry::from_wkt(&wkt)?;
+        if let Some(transform) = &self.transform {
+            geometry = geometry.transform(transform)?;
+        }
+        if let Some(distance) = self.buffer {
+            geometry = writer::buffered(&geometry, distance, self.multi)?;
+        }
+        Ok(geometry.wkt()?)
+    }
+}
+
+impl BoundsWriter for CsvWriter {
+
+    fn contains(&self, path: &str) -> bool {
+        self.indexed.contains(path)
+    }
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        // Unknown values are left empty.
+        let mut row: Vec<String> = self.fields.iter()
+            .map(|field| match fields::value(record, field) {
+                Some(Value::Integer(i)) => i.to_string(),
+                Some(Value::Real(r)) => r.to_string(),
+                Some(Value::String(s)) => escape(&s),
+                None => String::new()
+            })
+            .collect();
+        row.push(escape(&self.wkt(record)?));
+
+        let out = self.out.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        writeln!(out, "{}", row.join(","))?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        if let Some(mut out) = self.out.take() {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+//! Point density raster, counts of points per square cell across all files as GeoTIFF.
+
+use crate::{BoundsRecord, BoundsWriter, LasBoundsError};
+use crate::hexbin;
+use crate::ogr;
+use gdal::spatial_ref::SpatialRef;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+
+/// Rasters with more cells are refused, as they come from too small cell size for the extent
+/// or garbage coordinates. The limit is 400 MB of counts.
+const MAX_CELLS: i64 = 100_000_000;
+
+/// Column and row of a cell from the origin in cell size units.
+pub type Cell = (i64, i64);
+
+pub type CellCounts = HashMap<Cell, u64>;
+
+/// Cell containing the point.
+pub fn cell_of(x: f64, y: f64, size: f64) -> Cell {
+    ((x / size).floor() as i64, (y / size).floor() as i64)
+}
+
+/// Accumulates cell counts of all records and writes them as `UInt32` GeoTIFF in CRS of the input files
+/// on finish. Cells without points are 0.
+pub struct DensityRasterWriter {
+    path: PathBuf,
+    cell: f64,
+    srs: Option<SpatialRef>,
+    counts: CellCounts
+}
+
+impl DensityRasterWriter {
+
+    /// Requires `ScanOptions::density_cell` of the same `cell` size.
+    pub fn new<P: Into<PathBuf>>(path: P, cell: f64, srs: Option<SpatialRef>, overwrite: bool) -> Result<Self, LasBoundsError> {
+
+        let path = path.into();
+        if path.exists() && !overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite", path.to_string_lossy()).into());
+        }
+
+        Ok(DensityRasterWriter { path, cell, srs, counts: CellCounts::new() })
+    }
+}
+
+impl BoundsWriter for DensityRasterWriter {
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        hexbin::merge(&mut self.counts, &record.stats.cell_counts);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        if self.counts.is_empty() {
+            warn!("No points for density raster, {} is not written", self.path.to_string_lossy());
+            return Ok(());
+        }
+
+        let column_min = self.counts.keys().map(|c| c.0).min().unwrap();
+        let column_max = self.counts.keys().map(|c| c.0).max().unwrap();
+        let row_min = self.counts.keys().map(|c| c.1).min().unwrap();
+        let row_max = self.counts.keys().map(|c| c.1).max().unwrap();
+        // Cells of garbage coordinates may be far enough apart to overflow.
+        let width = column_max.checked_sub(column_min).and_then(|width| width.checked_add(1));
+        let height = row_max.checked_sub(row_min).and_then(|height| height.checked_add(1));
+        let (width, height, cells) = match (width, height) {
+            (Some(width), Some(height)) => match width.checked_mul(height) {
+                Some(cells) if cells <= MAX_CELLS => (width, height, cells),
+                Some(cells) => return Err(format!("Density raster would have {} cells, use larger --cell", cells).into()),
+                None => return Err(format!("Density raster of {}x{} cells is too large, use larger --cell", width, height).into())
+            },
+            _ => return Err("Density raster extent is too large, check coordinates of the files".to_string().into())
+        };
+
+        // Rows go from north to south.
+        let mut data = vec![0u32; cells as usize];
+        for (&(column, row), &count) in &self.counts {
+            let index = (row_max - row) * width + (column - column_min);
+            data[index as usize] = count.min(u32::MAX as u64) as u32;
+        }
+        let geo_transform = [column_min as f64 * self.cell, self.cell, 0.0, (row_max + 1) as f64 * self.cell, 0.0, -self.cell];
+        debug!("Writing density raster of {}x{} cells", width, height);
+
+        ogr::write_raster(&self.path, width as usize, height as usize, geo_transform, self.srs.as_ref(), &data)
+    }
+}
@@ -0,0 +1,117 @@
+//! Comparison of two bounds indexes, e.g. runs before and after a delivery update.
+
+use crate::LasBoundsError;
+use crate::ogr;
+use crate::writer;
+use gdal::vector::{Dataset, FieldValue};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+
+/// Feature of an indexed file.
+pub struct IndexEntry {
+    /// Envelope of the footprint as `(min_x, min_y, max_x, max_y)`.
+    pub bounds: (f64, f64, f64, f64),
+    /// `None` when the layer has no `point_count` field.
+    pub point_count: Option<i64>
+}
+
+/// Reads the bounds layer, see [`writer::find_bounds_layer`], by file path.
+pub fn read_index(path: &Path, layer_name: Option<&str>) -> Result<BTreeMap<String, IndexEntry>, LasBoundsError> {
+
+    let mut dataset = Dataset::open(path)?;
+    let index = writer::find_bounds_layer(&mut dataset, layer_name, path)?;
+    let layer = dataset.layer(index)?;
+    let path_field = writer::path_field_name(layer).ok_or_else(|| format!("No path field in {}", path.to_string_lossy()))?;
+
+    let mut entries = BTreeMap::new();
+    for feature in layer.features() {
+        let file = match feature.field(&path_field)? {
+            FieldValue::StringValue(file) => file,
+            _ => continue
+        };
+        let point_count = match feature.field("point_count") {
+            Ok(FieldValue::Integer64Value(count)) => Some(count),
+            Ok(FieldValue::IntegerValue(count)) => Some(count as i64),
+            Ok(FieldValue::RealValue(count)) => Some(count as i64),
+            _ => None
+        };
+        entries.insert(file, IndexEntry { bounds: ogr::envelope(feature.geometry()), point_count });
+    }
+    Ok(entries)
+}
+
+/// Files added, removed and changed from the old to the new index.
+#[derive(Default)]
+pub struct IndexDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Files with what changed, `bounds` and/or `point_count`.
+    pub changed: Vec<(String, Vec<&'static str>)>,
+    pub unchanged: usize
+}
+
+/// Compares the indexes by path, bounds differing by at most `tolerance` are the same. Point counts are
+/// compared when both indexes have them.
+pub fn diff(old: &BTreeMap<String, IndexEntry>, new: &BTreeMap<String, IndexEntry>, tolerance: f64) -> IndexDiff {
+
+    let mut diff = IndexDiff::default();
+    for (file, entry) in new {
+        let old_entry = match old.get(file) {
+            Some(old_entry) => old_entry,
+            None => {
+                diff.added.push(file.clone());
+                continue;
+            }
+        };
+
+        let (a, b) = (old_entry.bounds, entry.bounds);
+        let mut changes = Vec::new();
+        if [(a.0, b.0), (a.1, b.1), (a.2, b.2), (a.3, b.3)].iter().any(|(a, b)| (a - b).abs() > tolerance) {
+            changes.push("bounds");
+        }
+        if let (Some(old_count), Some(count)) = (old_entry.point_count, entry.point_count) {
+            if old_count != count {
+                changes.push("point_count");
+            }
+        }
+        if changes.is_empty() {
+            diff.unchanged += 1;
+        } else {
+            diff.changed.push((file.clone(), changes));
+        }
+    }
+    diff.removed = old.keys().filter(|file| !new.contains_key(*file)).cloned().collect();
+    diff
+}
+
+impl IndexDiff {
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+
+        json!({
+            "added": self.added,
+            "removed": self.removed,
+            "changed": self.changed.iter()
+                .map(|(file, changes)| json!({ "path": file, "changes": changes }))
+                .collect::<Vec<_>>(),
+            "unchanged": self.unchanged
+        })
+    }
+
+    /// Line per file, `+` added, `-` removed and `~` changed, followed by totals.
+    pub fn report(&self) -> String {
+
+        let mut lines: Vec<String> = self.added.iter().map(|file| format!("+ {}", file)).collect();
+        lines.extend(self.removed.iter().map(|file| format!("- {}", file)));
+        lines.extend(self.changed.iter().map(|(file, changes)| format!("~ {} ({})", file, changes.join(", "))));
+        lines.push(format!("{} added, {} removed, {} changed, {} unchanged",
+                           self.added.len(), self.removed.len(), self.changed.len(), self.unchanged));
+        lines.join("\n")
+    }
+}
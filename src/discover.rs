@@ -0,0 +1,196 @@
+//! Discovery of LAS/LAZ files.
+
+use crate::LasBoundsError;
+use crate::input::{EPT_FILE_NAME, InputFormat};
+use glob::Pattern;
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+
+/// Handling of symbolic links found while scanning directories.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symlinks {
+    /// Links to files are indexed, links to directories are not descended into.
+    Files,
+    /// Links to files and directories are followed, directory cycles are skipped
+    /// and files reachable through several links are indexed once.
+    Follow,
+    /// Links are ignored.
+    Skip
+}
+
+/// How input directories are scanned.
+#[derive(Clone)]
+pub struct DiscoverOptions {
+    /// Maximum depth of the scan, 1 scans only the directory itself.
+    pub max_depth: usize,
+    /// Glob patterns of skipped files and directories, matched against their name
+    /// and their path relative to the scanned directory, e.g. `*.tmp.las` or `_old`.
+    pub exclude: Vec<String>,
+    pub symlinks: Symlinks,
+    /// List ASCII point files (`.xyz`, `.txt`, `.csv`) too.
+    pub xyz: bool,
+    /// List PLY point files too.
+    pub ply: bool
+}
+
+impl Default for DiscoverOptions {
+
+    fn default() -> Self {
+        DiscoverOptions { max_depth: 1, exclude: Vec::new(), symlinks: Symlinks::Files, xyz: false, ply: false }
+    }
+}
+
+/// Compiled exclude patterns.
+struct Exclude(Vec<Pattern>);
+
+impl Exclude {
+
+    fn new(patterns: &[String]) -> Result<Self, LasBoundsError> {
+
+        let patterns = patterns.iter()
+                               .map(|p| Pattern::new(p).map_err(|e| format!("Invalid exclude pattern {}: {}", p, e)))
+                               .collect::<Result<Vec<_>, _>>()?;
+        Ok(Exclude(patterns))
+    }
+
+    fn is_excluded(&self, root: &Path, path: &Path) -> bool {
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let name = path.file_name().map(Path::new).unwrap_or(relative);
+        self.0.iter().any(|pattern| pattern.matches_path(relative) || pattern.matches_path(name))
+    }
+}
+
+/// Whether the file has LAS or LAZ extension in any case, e.g. `.LAS` written by Windows software.
+fn is_las(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str())
+        .filter(|ext| ext.eq_ignore_ascii_case("las") || ext.eq_ignore_ascii_case("laz")).is_some()
+}
+
+/// Whether the file is indexed, LAS/LAZ or other point file format enabled by options.
+pub(crate) fn is_input(path: &Path, options: &DiscoverOptions) -> bool {
+
+    match InputFormat::of(path) {
+        InputFormat::Xyz => options.xyz,
+        InputFormat::Ply => options.ply,
+        _ => is_las(path)
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata().map(|metadata| metadata.file_type().is_symlink()).unwrap_or(false)
+}
+
+/// Drops files already listed under another path, keeping the first one, when links are followed.
+fn dedup_links(paths: Vec<PathBuf>, symlinks: Symlinks) -> Vec<PathBuf> {
+
+    if symlinks != Symlinks::Follow {
+        return paths;
+    }
+
+    let mut seen = HashSet::new();
+    paths.into_iter()
+         .filter(|path| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())))
+         .collect()
+}
+
+/// `ept.json` of the directory when it is root of an EPT dataset.
+fn ept_metadata(dir: &Path) -> Option<PathBuf> {
+    Some(dir.join(EPT_FILE_NAME)).filter(|path| path.is_file())
+}
+
+/// Lists LAS/LAZ files, and other enabled point files, in the directory, sorted by path. EPT datasets are listed by their `ept.json`,
+/// the directory itself may be an EPT dataset too.
+pub fn scan_dir(dir: &Path, options: &DiscoverOptions) -> Result<Vec<PathBuf>, LasBoundsError> {
+
+    if let Some(ept) = ept_metadata(dir) {
+        return Ok(vec![ept]);
+    }
+
+    let exclude = Exclude::new(&options.exclude)?;
+
+    let walk = WalkDir::new(dir).min_depth(1)
+                                .max_depth(options.max_depth)
+                                .follow_links(options.symlinks == Symlinks::Follow);
+
+    // Excluded directories are not descended into, with links skipped neither are linked ones.
+    let mut vec = Vec::new();
+    let mut entries = walk.into_iter()
+                          .filter_entry(|entry| !exclude.is_excluded(dir, entry.path())
+                                                && !(options.symlinks == Symlinks::Skip && entry.path_is_symlink()));
+    while let Some(entry) = entries.next() {
+
+        let path = match entry {
+            Ok(entry) => entry.into_path(),
+            Err(e) => {
+                if let Some(ancestor) = e.loop_ancestor() {
+                    warn!("Skipped symlink cycle to {}", ancestor.to_string_lossy());
+                }
+                continue;
+            }
+        };
+        // Tiles in `ept-data` are part of the dataset, not separate inputs.
+        if path.is_dir() {
+            if let Some(ept) = ept_metadata(&path) {
+                vec.push(ept);
+                entries.skip_current_dir();
+            }
+        } else if path.is_file() && is_input(&path, options) {
+            vec.push(path);
+        }
+    }
+
+    vec.sort();
+    Ok(dedup_links(vec, options.symlinks))
+}
+
+/// Whether the string contains glob wildcards (`*`, `?` or `[`).
+pub fn is_glob(s: &str) -> bool {
+    s.contains(|c| c == '*' || c == '?' || c == '[')
+}
+
+/// Lists LAS/LAZ files matching glob pattern, e.g. `data/**/tile_1?_*.las`, sorted by path.
+/// Exclude patterns are matched relative to the base directory of the pattern, also against parent directories.
+pub fn scan_glob(pattern: &str, options: &DiscoverOptions) -> Result<Vec<PathBuf>, LasBoundsError> {
+
+    let exclude = Exclude::new(&options.exclude)?;
+    let base = glob_base(pattern);
+    let paths = glob::glob(pattern).map_err(|e| format!("Invalid glob pattern {}: {}", pattern, e))?;
+
+    // Like directory scan, unreadable entries are skipped.
+    let mut vec: Vec<PathBuf> = paths.filter_map(Result::ok)
+                                     .filter(|path| path.is_file())
+                                     .filter(|path| is_input(path, options) || InputFormat::of(path) == InputFormat::Ept)
+                                     .filter(|path| !(options.symlinks == Symlinks::Skip && is_symlink(path)))
+                                     .filter(|path| !path.ancestors()
+                                                         .take_while(|&p| p != base && p.starts_with(&base))
+                                                         .any(|p| exclude.is_excluded(&base, p)))
+                                     .collect();
+
+    vec.sort();
+    Ok(dedup_links(vec, options.symlinks))
+}
+
+/// Leading part of glob pattern without wildcards, e.g. `data` for `data/**/*.las`.
+pub fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern).components().take_while(|c| !is_glob(&c.as_os_str().to_string_lossy())).collect()
+}
+
+/// Reads newline separated list of paths, e.g. output of `find`. Blank lines are ignored,
+/// listed files are kept in the given order regardless of extension.
+pub fn read_file_list<R: BufRead>(reader: R) -> Result<Vec<PathBuf>, LasBoundsError> {
+
+    let mut vec = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            vec.push(PathBuf::from(line));
+        }
+    }
+
+    Ok(vec)
+}
@@ -0,0 +1,52 @@
+//! Entwine Point Tile datasets, indexed from the `ept.json` metadata without reading point data.
+
+use crate::LasBoundsError;
+use crate::crs::{self, Crs};
+use crate::input;
+use las::{Bounds, Header, Vector};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+
+fn read_bounds(value: &Value) -> Option<Bounds> {
+
+    let values: Vec<f64> = value.as_array()?.iter().filter_map(Value::as_f64).collect();
+    if values.len() != 6 {
+        return None;
+    }
+
+    Some(Bounds {
+        min: Vector { x: values[0], y: values[1], z: values[2] },
+        max: Vector { x: values[3], y: values[4], z: values[5] }
+    })
+}
+
+/// CRS from the `srs` object, WKT preferred over EPSG code as for LAS files.
+fn read_crs(srs: &Value) -> Option<Crs> {
+
+    if let Some(wkt) = srs.get("wkt").and_then(Value::as_str).filter(|wkt| !wkt.is_empty()) {
+        return Some(Crs::Wkt(wkt.to_string()));
+    }
+
+    let authority = srs.get("authority").and_then(Value::as_str)?;
+    let code = srs.get("horizontal").and_then(Value::as_str)?.parse().ok()?;
+    Some(Crs::Epsg(code)).filter(|_| authority.eq_ignore_ascii_case("EPSG"))
+}
+
+/// Reads `ept.json` into a header with bounds conforming to the points, total point count and SRS.
+pub fn read_header(path: &Path) -> Result<Header, LasBoundsError> {
+
+    let content = fs::read_to_string(path)?;
+    let metadata: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid EPT metadata {}: {}", path.to_string_lossy(), e))?;
+
+    // Cubic `bounds` of the octree are larger than the data.
+    let bounds = metadata.get("boundsConforming").and_then(read_bounds)
+                         .or_else(|| metadata.get("bounds").and_then(read_bounds))
+                         .ok_or_else(|| format!("EPT metadata without bounds: {}", path.to_string_lossy()))?;
+    let point_count = metadata.get("points").and_then(Value::as_u64).unwrap_or(0);
+    let vlrs = metadata.get("srs").and_then(read_crs).and_then(|crs| crs::projection_vlr(&crs)).into_iter().collect();
+
+    input::synthetic_header(&bounds, point_count, vlrs)
+}
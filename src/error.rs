@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt;
+
+
+pub enum LasBoundsError {
+    GdalError(gdal::errors::Error),
+    IOError(std::io::Error),
+    LASError(las::Error),
+    Custom(String)
+}
+
+impl fmt::Debug for LasBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for LasBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GdalError(e) => write!(f, "GdalError: {}", e),
+            Self::IOError(e) => write!(f, "IOError: {}", e),
+            Self::LASError(e) => write!(f, "LASError: {}", e),
+            Self::Custom(s) => write!(f, "Custom: {}", s)
+        }
+    }
+}
+
+
+impl Error for LasBoundsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::GdalError(_) => None,
+            Self::IOError(e) => Some(e),
+            Self::LASError(e) => Some(e),
+            Self::Custom(_) => None
+        }
+    }
+}
+
+impl From<std::io::Error> for LasBoundsError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IOError(error)
+    }
+}
+
+impl From<gdal::errors::Error> for LasBoundsError {
+    fn from(error: gdal::errors::Error) -> Self {
+        Self::GdalError(error)
+    }
+}
+
+impl From<las::Error> for LasBoundsError {
+    fn from(error: las::Error) -> Self {
+        Self::LASError(error)
+    }
+}
+
+impl From<String> for LasBoundsError {
+    fn from(s: String) -> Self {
+        Self::Custom(s)
+    }
+}
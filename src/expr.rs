@@ -0,0 +1,352 @@
+//! Computed fields, `name = expression` evaluated for each file from values of other fields.
+//!
+//! Expressions have numbers, `'strings'`, field names, arithmetic `+ - * / %`, comparisons
+//! `< <= > >= == !=`, logical `&& || !`, conditional `c ? a : b` and parentheses. Operators on
+//! unknown values give unknown values, written as null.
+
+use crate::BoundsRecord;
+use crate::fields::{self, Field, FieldType, Value};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Field(Field),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Value),
+    Str(String),
+    Ident(String),
+    Op(&'static str)
+}
+
+/// Operators, longer first so `<=` is not read as `<`.
+const OPS: &[&str] = &["<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "?", ":", "(", ")"];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        let len = if c.is_ascii_digit() || c == '.' {
+            let len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E')).unwrap_or(rest.len());
+            let text = &rest[..len];
+            tokens.push(Token::Number(match text.parse::<i64>() {
+                Ok(i) => Value::Integer(i),
+                Err(_) => Value::Real(text.parse().map_err(|_| format!("Invalid number: {}", text))?)
+            }));
+            len
+        } else if c == '\'' {
+            let end = rest[1..].find('\'').ok_or_else(|| format!("Unterminated string: {}", rest))?;
+            tokens.push(Token::Str(rest[1..=end].to_string()));
+            end + 2
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if let Some(op) = OPS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op(*op));
+            op.len()
+        } else {
+            return Err(format!("Unexpected character: {}", c));
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, one method per precedence level.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize
+}
+
+impl Parser {
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(*op),
+            _ => None
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(format!("Expected {}", op))
+        }
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+
+        let condition = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+        let then = self.conditional()?;
+        self.expect(":")?;
+        let otherwise = self.conditional()?;
+        Ok(Expr::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)))
+    }
+
+    /// Binary operators of the `level` precedence and higher, left associative.
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+
+        const LEVELS: &[&[(&str, BinaryOp)]] = &[
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+            &[("<", BinaryOp::Lt), ("<=", BinaryOp::Le), (">", BinaryOp::Gt), (">=", BinaryOp::Ge)],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(&(_, op)) = LEVELS[level].iter().find(|(s, _)| self.peek_op() == Some(*s)) {
+            self.pos += 1;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+
+        if self.eat("-") {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+
+        let token = self.tokens.get(self.pos).cloned().ok_or("Unexpected end of expression")?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(Expr::Literal(value)),
+            Token::Str(s) => Ok(Expr::Literal(Value::String(s))),
+            Token::Ident(name) => Ok(Expr::Field(fields::select_fields(&[name])?.remove(0))),
+            Token::Op("(") => {
+                let expr = self.conditional()?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Token::Op(op) => Err(format!("Unexpected {}", op))
+        }
+    }
+}
+
+impl Expr {
+
+    pub fn parse(s: &str) -> Result<Expr, String> {
+
+        let mut parser = Parser { tokens: tokenize(s)?, pos: 0 };
+        let expr = parser.conditional()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(format!("Unexpected {:?}", parser.tokens[parser.pos]));
+        }
+        Ok(expr)
+    }
+
+    /// Type of the values, Real for arithmetic with a real operand or division, Integer 0 or 1 for conditions.
+    pub fn field_type(&self) -> FieldType {
+
+        match self {
+            Expr::Literal(Value::Integer(_)) => FieldType::Integer,
+            Expr::Literal(Value::Real(_)) => FieldType::Real,
+            Expr::Literal(Value::String(_)) => FieldType::String,
+            Expr::Field(field) => field.field_type,
+            Expr::Neg(expr) => expr.field_type(),
+            Expr::Not(_) => FieldType::Integer,
+            Expr::Binary(left, op, right) => match (op, left.field_type(), right.field_type()) {
+                (BinaryOp::Add, FieldType::String, _) | (BinaryOp::Add, _, FieldType::String) => FieldType::String,
+                (BinaryOp::Div, _, _) => FieldType::Real,
+                (BinaryOp::Add, FieldType::Integer, FieldType::Integer) | (BinaryOp::Sub, FieldType::Integer, FieldType::Integer)
+                    | (BinaryOp::Mul, FieldType::Integer, FieldType::Integer)
+                    | (BinaryOp::Rem, FieldType::Integer, FieldType::Integer) => FieldType::Integer,
+                (BinaryOp::Add, _, _) | (BinaryOp::Sub, _, _) | (BinaryOp::Mul, _, _) | (BinaryOp::Rem, _, _) => FieldType::Real,
+                _ => FieldType::Integer
+            },
+            Expr::Conditional(_, then, otherwise) => match (then.field_type(), otherwise.field_type()) {
+                (a, b) if a == b => a,
+                (FieldType::String, _) | (_, FieldType::String) => FieldType::String,
+                _ => FieldType::Real
+            }
+        }
+    }
+
+    /// Value for the record, converted to the type of [`Expr::field_type`]. `None` when a used field
+    /// is unknown or the operation is undefined, such as division by zero or comparing text with number.
+    pub fn eval(&self, record: &BoundsRecord) -> Option<Value> {
+
+        let value = match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Field(field) => fields::value(record, field)?,
+            Expr::Neg(expr) => match expr.eval(record)? {
+                Value::Integer(i) => Value::Integer(i.checked_neg()?),
+                Value::Real(r) => Value::Real(-r),
+                Value::String(_) => return None
+            },
+            Expr::Not(expr) => Value::Integer(!truth(&expr.eval(record)?) as i64),
+            Expr::Binary(left, BinaryOp::And, right) =>
+                Value::Integer((truth(&left.eval(record)?) && truth(&right.eval(record)?)) as i64),
+            Expr::Binary(left, BinaryOp::Or, right) =>
+                Value::Integer((truth(&left.eval(record)?) || truth(&right.eval(record)?)) as i64),
+            Expr::Binary(left, op, right) => binary(*op, left.eval(record)?, right.eval(record)?)?,
+            Expr::Conditional(condition, then, otherwise) =>
+                if truth(&condition.eval(record)?) { then.eval(record)? } else { otherwise.eval(record)? }
+        };
+
+        convert(value, self.field_type())
+    }
+}
+
+fn truth(value: &Value) -> bool {
+
+    match value {
+        Value::Integer(i) => *i != 0,
+        Value::Real(r) => *r != 0.0,
+        Value::String(s) => !s.is_empty()
+    }
+}
+
+fn as_real(value: &Value) -> Option<f64> {
+
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Real(r) => Some(*r),
+        Value::String(_) => None
+    }
+}
+
+fn to_text(value: Value) -> String {
+
+    match value {
+        Value::Integer(i) => i.to_string(),
+        Value::Real(r) => r.to_string(),
+        Value::String(s) => s
+    }
+}
+
+fn convert(value: Value, field_type: FieldType) -> Option<Value> {
+
+    Some(match (field_type, value) {
+        (FieldType::Real, value) => Value::Real(as_real(&value)?),
+        (FieldType::String, value) => Value::String(to_text(value)),
+        (FieldType::Integer, Value::Real(r)) => Value::Integer(r as i64),
+        (_, value) => value
+    })
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value) -> Option<Value> {
+
+    let ordering = |left: &Value, right: &Value| match (left, right) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (a, b) => as_real(a)?.partial_cmp(&as_real(b)?)
+    };
+
+    let value = match op {
+        BinaryOp::Add => match (left, right) {
+            (Value::Integer(a), Value::Integer(b)) => Value::Integer(a.checked_add(b)?),
+            (left @ Value::String(_), right) | (left, right @ Value::String(_)) =>
+                Value::String(to_text(left) + &to_text(right)),
+            (a, b) => Value::Real(as_real(&a)? + as_real(&b)?)
+        },
+        BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Rem => match (left, right) {
+            (Value::Integer(a), Value::Integer(b)) => Value::Integer(match op {
+                BinaryOp::Sub => a.checked_sub(b)?,
+                BinaryOp::Mul => a.checked_mul(b)?,
+                _ => a.checked_rem(b)?
+            }),
+            (a, b) => {
+                let (a, b) = (as_real(&a)?, as_real(&b)?);
+                Value::Real(match op {
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    _ if b == 0.0 => return None,
+                    _ => a % b
+                })
+            }
+        },
+        BinaryOp::Div => {
+            let (a, b) = (as_real(&left)?, as_real(&right)?);
+            if b == 0.0 {
+                return None;
+            }
+            Value::Real(a / b)
+        },
+        BinaryOp::Eq => Value::Integer((ordering(&left, &right)? == Ordering::Equal) as i64),
+        BinaryOp::Ne => Value::Integer((ordering(&left, &right)? != Ordering::Equal) as i64),
+        BinaryOp::Lt => Value::Integer((ordering(&left, &right)? == Ordering::Less) as i64),
+        BinaryOp::Le => Value::Integer((ordering(&left, &right)? != Ordering::Greater) as i64),
+        BinaryOp::Gt => Value::Integer((ordering(&left, &right)? == Ordering::Greater) as i64),
+        BinaryOp::Ge => Value::Integer((ordering(&left, &right)? != Ordering::Less) as i64),
+        BinaryOp::And | BinaryOp::Or => unreachable!("Logical operators are evaluated lazily")
+    };
+
+    Some(value)
+}
+
+/// Field computed by `name = expression`.
+pub fn computed_field(definition: &str) -> Result<Field, String> {
+
+    let mut parts = definition.splitn(2, '=');
+    let name = parts.next().unwrap().trim();
+    let expression = parts.next().ok_or_else(|| format!("Expected name = expression: {}", definition))?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid field name: {}", name));
+    }
+
+    let expr = Expr::parse(expression).map_err(|e| format!("Invalid expression of {}: {}", name, e))?;
+    Ok(Field {
+        key: "expr",
+        name: name.to_string().into(),
+        field_type: expr.field_type(),
+        expr: Some(Arc::new(expr))
+    })
+}
@@ -0,0 +1,53 @@
+//! Extra dimensions of point records, described by the Extra Bytes VLR (LAS 1.4, used in earlier
+//! versions too), e.g. Amplitude or Deviation of full waveform scanners.
+
+use las::Header;
+
+
+const EXTRA_BYTES_USER_ID: &str = "LASF_Spec";
+const EXTRA_BYTES_RECORD_ID: u16 = 4;
+const DESCRIPTOR_LEN: usize = 192;
+const NAME_OFFSET: usize = 4;
+const NAME_LEN: usize = 32;
+
+/// Extra dimension of the point records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraDim {
+    pub name: String,
+    /// Type name, e.g. `uint16`, `float64[3]` for deprecated array types or `bytes[4]` for undocumented bytes.
+    pub data_type: String
+}
+
+/// Name of the data type, types 1 to 10 are scalars and 11 to 30 their deprecated arrays of 2 and 3.
+fn type_name(data_type: u8, options: u8) -> String {
+
+    const TYPES: [&str; 10] = ["uint8", "int8", "uint16", "int16", "uint32", "int32", "uint64", "int64", "float32", "float64"];
+    match data_type {
+        // Options of undocumented extra bytes hold their number.
+        0 => format!("bytes[{}]", options),
+        1..=10 => TYPES[data_type as usize - 1].to_string(),
+        11..=30 => format!("{}[{}]", TYPES[(data_type as usize - 11) % 10], (data_type - 11) / 10 + 2),
+        _ => format!("unknown({})", data_type)
+    }
+}
+
+fn parse(data: &[u8]) -> Vec<ExtraDim> {
+
+    data.chunks_exact(DESCRIPTOR_LEN)
+        .map(|descriptor| {
+            let name = &descriptor[NAME_OFFSET..NAME_OFFSET + NAME_LEN];
+            let name = String::from_utf8_lossy(name).trim_end_matches('\0').trim().to_string();
+            ExtraDim { name, data_type: type_name(descriptor[2], descriptor[3]) }
+        })
+        .collect()
+}
+
+/// Extra dimensions declared in VLRs or EVLRs of the file, empty when it has none.
+pub fn extra_dims(header: &Header) -> Vec<ExtraDim> {
+
+    header.vlrs().iter()
+          .chain(header.evlrs())
+          .filter(|vlr| vlr.user_id == EXTRA_BYTES_USER_ID && vlr.record_id == EXTRA_BYTES_RECORD_ID)
+          .flat_map(|vlr| parse(&vlr.data))
+          .collect()
+}
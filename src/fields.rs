@@ -0,0 +1,601 @@
+//! Attributes written for each LAS file.
+
+use crate::{BoundsRecord, ReadOptions};
+use crate::copc;
+use crate::crs::{self, Crs};
+use crate::expr::Expr;
+use crate::extra_bytes;
+use crate::input::InputFormat;
+use crate::remote;
+use crate::scan;
+use chrono::{DateTime, SecondsFormat, Utc};
+use las::GpsTimeType;
+use std::borrow::Cow;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldType {
+    Integer,
+    Real,
+    String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    String(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// Name of the attribute the value is computed for.
+    pub key: &'static str,
+    /// Name of the layer field, the key unless renamed.
+    pub name: Cow<'static, str>,
+    pub field_type: FieldType,
+    /// Expression of computed fields, see [`crate::expr::computed_field`].
+    pub expr: Option<Arc<Expr>>
+}
+
+const fn field(key: &'static str, field_type: FieldType) -> Field {
+    Field { key, name: Cow::Borrowed(key), field_type, expr: None }
+}
+
+impl Field {
+
+    /// Same attribute written under another field name.
+    pub fn renamed<S: Into<String>>(&self, name: S) -> Field {
+        Field { name: Cow::Owned(name.into()), ..self.clone() }
+    }
+}
+
+pub const NAME: Field = field("name", FieldType::String);
+pub const PATH: Field = field("path", FieldType::String);
+pub const POINT_COUNT: Field = field("point_count", FieldType::Integer);
+pub const Z_MIN: Field = field("z_min", FieldType::Real);
+pub const Z_MAX: Field = field("z_max", FieldType::Real);
+pub const LAS_VERSION: Field = field("las_version", FieldType::String);
+pub const POINT_FORMAT: Field = field("point_format", FieldType::Integer);
+/// Footprint area in layer units, before any reprojection.
+pub const AREA: Field = field("area", FieldType::Real);
+/// Points per unit of area.
+pub const DENSITY: Field = field("density", FieldType::Real);
+pub const SIZE_BYTES: Field = field("size_bytes", FieldType::Integer);
+/// File modification time, ISO 8601 in UTC.
+pub const MTIME: Field = field("mtime", FieldType::String);
+/// Whether the file is Cloud Optimized Point Cloud.
+pub const COPC: Field = field("copc", FieldType::Integer);
+pub const RETURN_FIELDS: &[(u8, Field)] = &[
+    (1, field("returns_1", FieldType::Integer)),
+    (2, field("returns_2", FieldType::Integer)),
+    (3, field("returns_3", FieldType::Integer)),
+    (4, field("returns_4", FieldType::Integer)),
+    (5, field("returns_5", FieldType::Integer)),
+];
+/// Whether header counts by return match scanned points.
+pub const RETURNS_OK: Field = field("returns_ok", FieldType::Integer);
+/// Whether header bounds match bounds of points within the scale factor.
+pub const BOUNDS_OK: Field = field("bounds_ok", FieldType::Integer);
+/// Whether header bounds were rewritten with bounds of points.
+pub const HEADER_FIXED: Field = field("hdr_fixed", FieldType::Integer);
+/// Whether density is within the limits of the specification.
+pub const DENSITY_OK: Field = field("density_ok", FieldType::Integer);
+/// Whether the file has suspiciously few points, often a failed export.
+pub const SUSPECT: Field = field("suspect", FieldType::Integer);
+/// Bounds of points, may differ from stale header bounds.
+pub const TRUE_BOUNDS_FIELDS: &[Field] = &[
+    field("true_xmin", FieldType::Real),
+    field("true_ymin", FieldType::Real),
+    field("true_zmin", FieldType::Real),
+    field("true_xmax", FieldType::Real),
+    field("true_ymax", FieldType::Real),
+    field("true_zmax", FieldType::Real),
+];
+/// Input directory the file was found in, when indexing several directories.
+pub const SOURCE_DIR: Field = field("source_dir", FieldType::String);
+/// Path of the file this one is a copy of, by header bounds, point count and project GUID.
+pub const DUPLICATE_OF: Field = field("duplicate_of", FieldType::String);
+pub const GPS_START: Field = field("gps_start", FieldType::Real);
+pub const GPS_END: Field = field("gps_end", FieldType::Real);
+pub const GPS_TIME_TYPE: Field = field("gps_type", FieldType::String);
+/// Minimum and maximum point source ID and number of distinct IDs, i.e. flightlines.
+pub const POINT_SOURCE_FIELDS: &[Field] = &[
+    field("psid_min", FieldType::Integer),
+    field("psid_max", FieldType::Integer),
+    field("psid_count", FieldType::Integer),
+];
+
+/// Per class point count fields for ASPRS standard classes, remaining classes are summed in `cls_other`.
+pub const CLASS_FIELDS: &[(u8, Field)] = &[
+    (0, field("cls_never", FieldType::Integer)),
+    (1, field("cls_unclas", FieldType::Integer)),
+    (2, field("cls_ground", FieldType::Integer)),
+    (3, field("cls_lowveg", FieldType::Integer)),
+    (4, field("cls_medveg", FieldType::Integer)),
+    (5, field("cls_highveg", FieldType::Integer)),
+    (6, field("cls_build", FieldType::Integer)),
+    (7, field("cls_lnoise", FieldType::Integer)),
+    (9, field("cls_water", FieldType::Integer)),
+    (10, field("cls_rail", FieldType::Integer)),
+    (11, field("cls_road", FieldType::Integer)),
+    (13, field("cls_wguard", FieldType::Integer)),
+    (14, field("cls_wcond", FieldType::Integer)),
+    (15, field("cls_tower", FieldType::Integer)),
+    (16, field("cls_wconn", FieldType::Integer)),
+    (17, field("cls_bridge", FieldType::Integer)),
+    (18, field("cls_hnoise", FieldType::Integer)),
+];
+pub const CLASS_OTHER: Field = field("cls_other", FieldType::Integer);
+/// Per class point counts as JSON object keyed by class code.
+pub const CLASSES: Field = field("classes", FieldType::String);
+pub const VLR_COUNT: Field = field("vlr_count", FieldType::Integer);
+pub const EVLR_COUNT: Field = field("evlr_count", FieldType::Integer);
+/// JSON array of VLRs and EVLRs with their user ID, record ID, description and whether they are extended.
+pub const VLRS: Field = field("vlrs", FieldType::String);
+
+/// How per class point counts are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassStats {
+    /// Field per ASPRS standard class.
+    Fields,
+    /// Single JSON encoded field.
+    Json
+}
+
+impl FromStr for ClassStats {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fields" => Ok(ClassStats::Fields),
+            "json" => Ok(ClassStats::Json),
+            _ => Err(format!("Unknown class stats mode: {}", s))
+        }
+    }
+}
+
+/// How VLRs and EVLRs of files are inventoried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VlrInventory {
+    /// Numbers of VLRs and EVLRs.
+    Counts,
+    /// Numbers and JSON encoded list of user ID, record ID and description of each.
+    Json
+}
+
+impl FromStr for VlrInventory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "counts" => Ok(VlrInventory::Counts),
+            "json" => Ok(VlrInventory::Json),
+            _ => Err(format!("Unknown VLR inventory mode: {}", s))
+        }
+    }
+}
+
+/// What is written into the `path` field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathMode {
+    /// Path as found, relative when the input was given by relative path.
+    AsGiven,
+    Absolute,
+    /// Relative to the directory of the output, so the index can be moved together with the files.
+    Relative,
+    /// File name only.
+    Filename,
+    /// `file://` URI of the absolute path.
+    Uri
+}
+
+impl FromStr for PathMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(PathMode::Absolute),
+            "relative" => Ok(PathMode::Relative),
+            "filename" => Ok(PathMode::Filename),
+            "uri" => Ok(PathMode::Uri),
+            _ => Err(format!("Unknown path mode: {}", s))
+        }
+    }
+}
+
+/// Formatting of `path` field values.
+#[derive(Debug, Clone)]
+pub struct PathFormat {
+    pub mode: PathMode,
+    /// Directory `Relative` paths start from.
+    pub base: PathBuf
+}
+
+impl Default for PathFormat {
+    fn default() -> Self {
+        PathFormat { mode: PathMode::AsGiven, base: PathBuf::new() }
+    }
+}
+
+/// Absolute path with `.` and `..` components resolved lexically, symbolic links are kept.
+fn absolute_path(path: &Path) -> PathBuf {
+
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => { normalized.pop(); },
+            component => normalized.push(component)
+        }
+    }
+    normalized
+}
+
+/// `path` relative to `base`, both absolute. Paths on different Windows drives stay absolute.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path.iter().collect();
+    }
+
+    let mut relative: PathBuf = base[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&path[common..]);
+    relative
+}
+
+/// `file://` URI, with characters outside unreserved ones percent encoded.
+fn file_uri(path: &Path) -> String {
+
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    uri
+}
+
+impl PathFormat {
+
+    /// Value of the `path` field for the file, URLs of remote files are kept except for `Filename`.
+    pub fn format(&self, path: &Path) -> String {
+
+        if remote::is_url(path) && self.mode != PathMode::Filename {
+            return path.to_string_lossy().into_owned();
+        }
+
+        match self.mode {
+            PathMode::AsGiven => path.to_string_lossy().into_owned(),
+            PathMode::Absolute => absolute_path(path).to_string_lossy().into_owned(),
+            PathMode::Relative => relative_path(&absolute_path(path), &absolute_path(&self.base)).to_string_lossy().into_owned(),
+            PathMode::Filename => path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+            PathMode::Uri => file_uri(&absolute_path(path))
+        }
+    }
+}
+
+/// Fields written by default, in layer order.
+pub const DEFAULT_FIELDS: &[Field] = &[
+    NAME,
+    PATH,
+    POINT_COUNT,
+    Z_MIN,
+    Z_MAX,
+    LAS_VERSION,
+    POINT_FORMAT,
+    AREA,
+    DENSITY,
+    SIZE_BYTES,
+    MTIME,
+    COPC,
+];
+
+/// Fields written with the given options, in layer order.
+pub fn schema(options: &ReadOptions) -> Vec<Field> {
+
+    let mut fields = DEFAULT_FIELDS.to_vec();
+    if options.source_dir {
+        fields.push(SOURCE_DIR);
+    }
+    if options.duplicates {
+        fields.push(DUPLICATE_OF);
+    }
+    if options.provenance {
+        fields.extend_from_slice(&[SYSTEM_ID, SOFTWARE]);
+    }
+    if options.project_id {
+        fields.push(PROJECT_ID);
+    }
+    if options.waveform {
+        fields.push(HAS_WAVEFORM);
+    }
+    if options.src_crs {
+        fields.push(SRC_CRS);
+    }
+    if options.file_date {
+        fields.push(FILE_DATE);
+    }
+    if options.extra_dims {
+        fields.push(EXTRA_DIMS);
+    }
+    if options.checksum.is_some() {
+        fields.push(CHECKSUM);
+    }
+    fields.extend(RETURN_FIELDS.iter().map(|(_, field)| field.clone()));
+    if options.scan.returns {
+        fields.push(RETURNS_OK);
+    }
+    if options.scan.bounds {
+        fields.push(BOUNDS_OK);
+        fields.extend_from_slice(TRUE_BOUNDS_FIELDS);
+    }
+    if options.fix_headers {
+        fields.push(HEADER_FIXED);
+    }
+    if options.min_density.is_some() || options.max_density.is_some() {
+        fields.push(DENSITY_OK);
+    }
+    if options.min_points.is_some() {
+        fields.push(SUSPECT);
+    }
+    if options.scan.gps_time {
+        fields.extend_from_slice(&[GPS_START, GPS_END, GPS_TIME_TYPE]);
+    }
+    if options.scan.point_sources {
+        fields.extend_from_slice(POINT_SOURCE_FIELDS);
+    }
+    match options.class_stats {
+        Some(ClassStats::Fields) => {
+            fields.extend(CLASS_FIELDS.iter().map(|(_, field)| field.clone()));
+            fields.push(CLASS_OTHER);
+        },
+        Some(ClassStats::Json) => fields.push(CLASSES),
+        None => ()
+    }
+    match options.vlr_inventory {
+        Some(VlrInventory::Counts) => fields.extend_from_slice(&[VLR_COUNT, EVLR_COUNT]),
+        Some(VlrInventory::Json) => fields.extend_from_slice(&[VLR_COUNT, EVLR_COUNT, VLRS]),
+        None => ()
+    }
+
+    fields
+}
+
+/// CRS detected in the file, as WKT or `EPSG:code`.
+pub const SRS: Field = field("srs", FieldType::String);
+/// CRS detected in the file as `EPSG:code` or name of WKT, unlike [`SRS`] short enough to compare
+/// files at a glance.
+pub const SRC_CRS: Field = field("src_crs", FieldType::String);
+/// File creation time, ISO 8601 in UTC.
+pub const CREATED: Field = field("created", FieldType::String);
+/// System identifier of the header, the sensor or processing system.
+pub const SYSTEM_ID: Field = field("system_id", FieldType::String);
+/// Generating software of the header.
+pub const SOFTWARE: Field = field("software", FieldType::String);
+/// Project ID GUID of the header, hyphenated lowercase hex.
+pub const PROJECT_ID: Field = field("project_id", FieldType::String);
+/// Whether points carry waveform packets, by point format or waveform VLRs and EVLRs.
+pub const HAS_WAVEFORM: Field = field("has_waveform", FieldType::Integer);
+/// Creation date of the header (day of year and year), ISO 8601.
+pub const FILE_DATE: Field = field("file_date", FieldType::String);
+/// Comma separated extra dimensions of the Extra Bytes VLR as `name:type`, e.g. `Amplitude:uint16`.
+pub const EXTRA_DIMS: Field = field("extra_dims", FieldType::String);
+/// Hex digest of the file content, see [`crate::checksum`].
+pub const CHECKSUM: Field = field("checksum", FieldType::String);
+
+/// Every field that can be selected, in default layer order.
+pub fn all_fields() -> Vec<Field> {
+
+    let mut fields = DEFAULT_FIELDS.to_vec();
+    fields.extend(vec![SRS, SRC_CRS, CREATED, SYSTEM_ID, SOFTWARE, PROJECT_ID, HAS_WAVEFORM, FILE_DATE, EXTRA_DIMS, CHECKSUM, SOURCE_DIR, DUPLICATE_OF]);
+    fields.extend(RETURN_FIELDS.iter().map(|(_, field)| field.clone()));
+    fields.extend(vec![RETURNS_OK, BOUNDS_OK]);
+    fields.extend_from_slice(TRUE_BOUNDS_FIELDS);
+    fields.extend(vec![HEADER_FIXED, DENSITY_OK, SUSPECT, GPS_START, GPS_END, GPS_TIME_TYPE]);
+    fields.extend_from_slice(POINT_SOURCE_FIELDS);
+    fields.extend(CLASS_FIELDS.iter().map(|(_, field)| field.clone()));
+    fields.extend(vec![CLASS_OTHER, CLASSES, VLR_COUNT, EVLR_COUNT, VLRS]);
+    fields
+}
+
+/// Fields of given names in the given order. Values of fields computed from points are known only
+/// when the corresponding scan is enabled.
+pub fn select_fields<S: AsRef<str>>(names: &[S]) -> Result<Vec<Field>, String> {
+
+    let all = all_fields();
+    names.iter()
+         .map(|name| {
+             let name = name.as_ref().trim();
+             all.iter().find(|field| field.key == name).cloned().ok_or_else(|| {
+                 let keys: Vec<&str> = all.iter().map(|field| field.key).collect();
+                 format!("Unknown field: {}, available fields: {}", name, keys.join(", "))
+             })
+         })
+         .collect()
+}
+
+/// Fields written by PDAL `tindex create`, so the index can be used by `filters.tindex`.
+pub fn pdal_tindex_schema() -> Vec<Field> {
+    vec![PATH.renamed("location"), SRS, MTIME.renamed("modified"), CREATED]
+}
+
+/// Single field with file path, the layout of GDAL (`gdaltindex`) and MapServer tile indexes.
+pub fn tileindex_schema(field_name: &str) -> Vec<Field> {
+    vec![PATH.renamed(field_name)]
+}
+
+fn class_counts_json(counts: &[u64]) -> String {
+
+    let counts: serde_json::Map<String, serde_json::Value> = counts.iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .map(|(class, &count)| (class.to_string(), count.into()))
+        .collect();
+    serde_json::Value::Object(counts).to_string()
+}
+
+fn vlrs_json(header: &las::Header) -> String {
+
+    let vlr_json = |vlr: &las::Vlr, extended: bool| serde_json::json!({
+        "user_id": vlr.user_id,
+        "record_id": vlr.record_id,
+        "description": vlr.description,
+        "extended": extended
+    });
+    let vlrs: Vec<serde_json::Value> = header.vlrs().iter().map(|vlr| vlr_json(vlr, false))
+        .chain(header.evlrs().iter().map(|vlr| vlr_json(vlr, true)))
+        .collect();
+    serde_json::Value::Array(vlrs).to_string()
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Header of LAS files, `None` for synthetic headers of other formats.
+fn las_header(record: &BoundsRecord) -> Option<&las::Header> {
+    Some(&record.header).filter(|_| record.format == InputFormat::Las)
+}
+
+const WAVEFORM_USER_ID: &str = "LASF_Spec";
+/// Record IDs of Waveform Packet Descriptor VLRs.
+const WAVEFORM_DESCRIPTOR_RECORD_IDS: std::ops::RangeInclusive<u16> = 100..=354;
+/// Record ID of the EVLR (or VLR in LAS 1.3) holding waveform data packets.
+const WAVEFORM_DATA_RECORD_ID: u16 = 65535;
+
+/// Whether the point format has waveform packets or any VLR or EVLR describes or holds them.
+fn has_waveform(header: &las::Header) -> bool {
+
+    header.point_format().has_waveform ||
+        header.vlrs().iter().chain(header.evlrs()).any(|vlr| {
+            vlr.user_id == WAVEFORM_USER_ID &&
+                (WAVEFORM_DESCRIPTOR_RECORD_IDS.contains(&vlr.record_id) || vlr.record_id == WAVEFORM_DATA_RECORD_ID)
+        })
+}
+
+/// File name, or name of the dataset directory for EPT.
+fn record_name(record: &BoundsRecord) -> String {
+
+    let path = match record.format {
+        InputFormat::Ept => record.path.parent().unwrap_or(&record.path),
+        _ => &record.path
+    };
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Value of the field for the record, `None` when it is unknown.
+pub fn value(record: &BoundsRecord, field: &Field) -> Option<Value> {
+
+    if let Some(expr) = &field.expr {
+        return expr.eval(record);
+    }
+
+    let value = match field.key {
+        "name" => Value::String(record_name(record)),
+        "path" => Value::String(record.indexed_path.clone()),
+        "point_count" => Value::Integer(record.header.number_of_points() as i64),
+        "z_min" => Value::Real(record.header.bounds().min.z),
+        "z_max" => Value::Real(record.header.bounds().max.z),
+        "area" => Value::Real(record.area),
+        "density" => {
+            let area = record.area;
+            if area <= 0.0 {
+                return None;
+            }
+            Value::Real(record.header.number_of_points() as f64 / area)
+        },
+        "size_bytes" => Value::Integer(record.size? as i64),
+        "mtime" => Value::String(format_time(record.modified?)),
+        "created" => Value::String(format_time(record.created?)),
+        "srs" => Value::String(match crs::detect_crs(&record.header)? {
+            Crs::Epsg(code) => format!("EPSG:{}", code),
+            Crs::Wkt(wkt) => wkt
+        }),
+        "src_crs" => Value::String(crs::detect_crs(&record.header)?.to_string()),
+        "copc" => Value::Integer(copc::copc_info(las_header(record)?).is_some() as i64),
+        "las_version" => {
+            let version = las_header(record)?.version();
+            Value::String(format!("{}.{}", version.major, version.minor))
+        },
+        "point_format" => Value::Integer(las_header(record)?.point_format().to_u8().map_or(-1, i64::from)),
+        "system_id" => Value::String(las_header(record)?.system_identifier().trim_end_matches('\0').trim().to_string()),
+        "software" => Value::String(las_header(record)?.generating_software().trim_end_matches('\0').trim().to_string()),
+        // Most software leaves the GUID zero, it does not identify any project then.
+        "project_id" => Value::String(Some(las_header(record)?.guid()).filter(|guid| !guid.is_nil())?.to_string()),
+        "has_waveform" => Value::Integer(has_waveform(las_header(record)?) as i64),
+        "file_date" => Value::String(record.file_date?.format("%Y-%m-%d").to_string()),
+        "checksum" => Value::String(record.checksum.clone()?),
+        "extra_dims" => Value::String(extra_bytes::extra_dims(las_header(record)?).iter()
+                                          .map(|dim| format!("{}:{}", dim.name, dim.data_type))
+                                          .collect::<Vec<_>>()
+                                          .join(",")),
+        "source_dir" => Value::String(record.source_dir.as_ref()?.to_string_lossy().into_owned()),
+        "duplicate_of" => Value::String(record.duplicate_of.as_ref()?.to_string_lossy().into_owned()),
+        "gps_start" => Value::Real(record.stats.gps_time?.0),
+        "gps_end" => Value::Real(record.stats.gps_time?.1),
+        // Global encoding bit 0 tells whether GPS time is seconds of week or adjusted standard time
+        // (standard GPS time minus 1e9).
+        "gps_type" => Value::String(match las_header(record)?.gps_time_type() {
+            GpsTimeType::Week => "week".to_string(),
+            GpsTimeType::Standard => "standard".to_string()
+        }),
+        // Counts are empty when points were not scanned for them, the values are unknown then.
+        "cls_other" | "classes" if record.stats.class_counts.is_empty() => return None,
+        "returns_ok" if record.stats.return_counts.is_empty() => return None,
+        "cls_other" => {
+            let counts = &record.stats.class_counts;
+            let known: u64 = CLASS_FIELDS.iter().map(|(class, _)| counts.get(*class as usize).cloned().unwrap_or(0)).sum();
+            Value::Integer((counts.iter().sum::<u64>() - known) as i64)
+        },
+        "returns_ok" => {
+            let header = las_header(record)?;
+            let ok = (1..16u8).all(|n| header.number_of_points_by_return(n).unwrap_or(0)
+                                        == record.stats.return_counts.get(n as usize).cloned().unwrap_or(0));
+            Value::Integer(ok as i64)
+        },
+        "bounds_ok" => Value::Integer(scan::header_bounds_match(las_header(record)?, record.stats.bounds.as_ref()?) as i64),
+        "hdr_fixed" => Value::Integer(record.header_fixed as i64),
+        "density_ok" => Value::Integer(record.density_ok? as i64),
+        "suspect" => Value::Integer(record.suspect? as i64),
+        "psid_min" => Value::Integer(i64::from(*record.stats.point_sources.iter().next()?)),
+        "psid_max" => Value::Integer(i64::from(*record.stats.point_sources.iter().next_back()?)),
+        "psid_count" if record.stats.point_sources.is_empty() => return None,
+        "psid_count" => Value::Integer(record.stats.point_sources.len() as i64),
+        "true_xmin" => Value::Real(record.stats.bounds?.min.x),
+        "true_ymin" => Value::Real(record.stats.bounds?.min.y),
+        "true_zmin" => Value::Real(record.stats.bounds?.min.z),
+        "true_xmax" => Value::Real(record.stats.bounds?.max.x),
+        "true_ymax" => Value::Real(record.stats.bounds?.max.y),
+        "true_zmax" => Value::Real(record.stats.bounds?.max.z),
+        "classes" => Value::String(class_counts_json(&record.stats.class_counts)),
+        "vlr_count" => Value::Integer(las_header(record)?.vlrs().len() as i64),
+        "evlr_count" => Value::Integer(las_header(record)?.evlrs().len() as i64),
+        "vlrs" => Value::String(vlrs_json(las_header(record)?)),
+        key => if let Some((class, _)) = CLASS_FIELDS.iter().find(|(_, field)| field.key == key) {
+            Value::Integer(*record.stats.class_counts.get(*class as usize)? as i64)
+        } else if let Some((n, _)) = RETURN_FIELDS.iter().find(|(_, field)| field.key == key) {
+            Value::Integer(las_header(record)?.number_of_points_by_return(*n).unwrap_or(0) as i64)
+        } else {
+            unreachable!("Unknown field: {}", key)
+        }
+    };
+
+    Some(value)
+}
@@ -0,0 +1,563 @@
+//! Footprint polygons of LAS files, either from header bounds or computed from points.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+
+pub type Point2 = (f64, f64);
+pub type Ring = Vec<Point2>;
+
+/// How footprint of a file is computed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Header bounding box.
+    Bbox,
+    /// Convex hull of points.
+    Hull,
+    /// Alpha shape of points.
+    Concave,
+    /// Polygonized occupancy grid of points.
+    Grid
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bbox" => Ok(Mode::Bbox),
+            "hull" => Ok(Mode::Hull),
+            "concave" => Ok(Mode::Concave),
+            "grid" => Ok(Mode::Grid),
+            _ => Err(format!("Unknown footprint mode: {}", s))
+        }
+    }
+}
+
+impl Mode {
+
+    /// Whether points have to be read to compute the footprint.
+    pub fn needs_points(self) -> bool {
+        self != Mode::Bbox
+    }
+
+    /// Whether footprint may consist of several polygons.
+    pub fn is_multi(self) -> bool {
+        self == Mode::Concave || self == Mode::Grid
+    }
+}
+
+#[derive(Clone)]
+pub struct FootprintOptions {
+    pub mode: Mode,
+    /// Maximum circumradius of Delaunay triangles kept in concave footprint.
+    pub alpha: f64,
+    /// Cell size of occupancy grid.
+    pub cell: f64,
+    /// Number of dilation/erosion steps closing small gaps in occupancy grid.
+    pub closing: usize,
+    /// Douglas-Peucker tolerance concave and grid footprints are simplified with, 0 to keep all vertices.
+    pub simplify: f64
+}
+
+impl Default for FootprintOptions {
+    fn default() -> Self {
+        FootprintOptions { mode: Mode::Bbox, alpha: std::f64::INFINITY, cell: 1.0, closing: 0, simplify: 0.0 }
+    }
+}
+
+/// Polygon with closed exterior ring and optional holes.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    pub exterior: Ring,
+    pub interiors: Vec<Ring>
+}
+
+/// Footprint of a file, one or more polygons.
+#[derive(Debug, Clone)]
+pub struct Footprint {
+    pub polygons: Vec<Polygon>
+}
+
+fn ring_wkt(ring: &[Point2], z: Option<f64>) -> String {
+
+    let coords: Vec<String> = ring.iter()
+        .map(|(x, y)| match z {
+            Some(z) => format!("{} {} {}", x, y, z),
+            None => format!("{} {}", x, y)
+        })
+        .collect();
+    format!("({})", coords.join(","))
+}
+
+fn ring_area(ring: &[Point2]) -> f64 {
+
+    let sum: f64 = ring.windows(2)
+                       .map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1)
+                       .sum();
+    sum / 2.0
+}
+
+/// Splits ring edges longer than `max_length`.
+fn densify_ring(ring: &[Point2], max_length: f64) -> Ring {
+
+    let mut out = Vec::with_capacity(ring.len());
+    for edge in ring.windows(2) {
+        let ((ax, ay), (bx, by)) = (edge[0], edge[1]);
+        let length = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+        let segments = (length / max_length).ceil().max(1.0) as usize;
+        for i in 0..segments {
+            let t = i as f64 / segments as f64;
+            out.push((ax + (bx - ax) * t, ay + (by - ay) * t));
+        }
+    }
+    out.extend(ring.last());
+    out
+}
+
+/// Distance of `p` from segment `a`-`b`.
+fn segment_distance(p: Point2, a: Point2, b: Point2) -> f64 {
+
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length2 = dx * dx + dy * dy;
+    let t = if length2 > 0.0 { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length2).max(0.0).min(1.0) } else { 0.0 };
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+/// Marks vertices between `first` and `last` farther than `tolerance` from the simplified line.
+fn douglas_peucker(points: &[Point2], first: usize, last: usize, tolerance: f64, keep: &mut [bool]) {
+
+    let farthest = (first + 1..last)
+        .map(|i| (i, segment_distance(points[i], points[first], points[last])))
+        .fold(None, |max: Option<(usize, f64)>, (i, d)| match max {
+            Some((_, max_d)) if max_d >= d => max,
+            _ => Some((i, d))
+        });
+    if let Some((i, d)) = farthest {
+        if d > tolerance {
+            keep[i] = true;
+            douglas_peucker(points, first, i, tolerance, keep);
+            douglas_peucker(points, i, last, tolerance, keep);
+        }
+    }
+}
+
+/// Closed ring simplified by Douglas-Peucker, `None` when it degenerates below a triangle.
+/// The ring is split at its vertex farthest from the start, so both halves have distinct endpoints.
+fn simplify_ring(ring: &[Point2], tolerance: f64) -> Option<Ring> {
+
+    if ring.len() < 4 {
+        return None;
+    }
+    let from_start = |p: Point2| (p.0 - ring[0].0).hypot(p.1 - ring[0].1);
+    let split = (1..ring.len() - 1)
+        .max_by(|&a, &b| from_start(ring[a]).partial_cmp(&from_start(ring[b])).unwrap_or(std::cmp::Ordering::Equal))?;
+
+    let mut keep = vec![false; ring.len()];
+    keep[0] = true;
+    keep[split] = true;
+    keep[ring.len() - 1] = true;
+    douglas_peucker(ring, 0, split, tolerance, &mut keep);
+    douglas_peucker(ring, split, ring.len() - 1, tolerance, &mut keep);
+
+    let simplified: Ring = ring.iter().zip(&keep).filter(|(_, &k)| k).map(|(&p, _)| p).collect();
+    if simplified.len() < 4 { None } else { Some(simplified) }
+}
+
+impl Polygon {
+
+    fn wkt_body(&self, z: Option<f64>) -> String {
+
+        let rings: Vec<String> = std::iter::once(&self.exterior)
+                                     .chain(self.interiors.iter())
+                                     .map(|ring| ring_wkt(ring, z))
+                                     .collect();
+        format!("({})", rings.join(","))
+    }
+
+    pub fn area(&self) -> f64 {
+        ring_area(&self.exterior).abs() - self.interiors.iter().map(|ring| ring_area(ring).abs()).sum::<f64>()
+    }
+}
+
+impl Footprint {
+
+    /// Rectangle footprint from header bounds.
+    pub fn bbox(bounds: &las::Bounds) -> Footprint {
+
+        let (x0, y0, x1, y1) = (bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y);
+        let exterior = vec![(x0, y0), (x1, y0), (x1, y1), (x0, y1), (x0, y0)];
+        Footprint { polygons: vec![Polygon { exterior, interiors: Vec::new() }] }
+    }
+
+    pub fn to_wkt(&self) -> String {
+        self.wkt(false, None)
+    }
+
+    /// WKT always of MULTIPOLYGON type, regardless of the number of polygons.
+    pub fn to_multipolygon_wkt(&self) -> String {
+        self.wkt(true, None)
+    }
+
+    /// WKT of POLYGON type, or MULTIPOLYGON when `multi` or there are several polygons. Vertices have
+    /// the `z` coordinate when given.
+    pub fn wkt(&self, multi: bool, z: Option<f64>) -> String {
+
+        let z_tag = if z.is_some() { " Z" } else { "" };
+        match self.polygons.as_slice() {
+            [] if multi => "MULTIPOLYGON EMPTY".to_string(),
+            [] => "POLYGON EMPTY".to_string(),
+            [polygon] if !multi => format!("POLYGON{} {}", z_tag, polygon.wkt_body(z)),
+            polygons => {
+                let bodies: Vec<String> = polygons.iter().map(|p| p.wkt_body(z)).collect();
+                format!("MULTIPOLYGON{} ({})", z_tag, bodies.join(","))
+            }
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.polygons.iter().map(|p| p.area()).sum()
+    }
+
+    /// Removes vertices closer than `tolerance` to the simplified outline, dropping polygons and holes
+    /// that collapse. Rings are simplified independently, so they may touch where they were close.
+    pub fn simplify(&self, tolerance: f64) -> Footprint {
+
+        let polygons = self.polygons.iter()
+            .filter_map(|p| Some(Polygon {
+                exterior: simplify_ring(&p.exterior, tolerance)?,
+                interiors: p.interiors.iter().filter_map(|ring| simplify_ring(ring, tolerance)).collect()
+            }))
+            .collect();
+
+        Footprint { polygons }
+    }
+
+    /// Bounding box of exterior rings as `(min_x, min_y, max_x, max_y)`, `None` when empty.
+    pub fn envelope(&self) -> Option<(f64, f64, f64, f64)> {
+
+        self.polygons.iter().flat_map(|p| p.exterior.iter()).fold(None, |envelope, &(x, y)| Some(match envelope {
+            Some((x0, y0, x1, y1)) => (x.min(x0), y.min(y0), x.max(x1), y.max(y1)),
+            None => (x, y, x, y)
+        }))
+    }
+
+    /// Splits edges longer than `max_length`, so the footprint keeps its shape when reprojected.
+    pub fn densify(&self, max_length: f64) -> Footprint {
+
+        let polygons = self.polygons.iter().map(|p| Polygon {
+            exterior: densify_ring(&p.exterior, max_length),
+            interiors: p.interiors.iter().map(|ring| densify_ring(ring, max_length)).collect()
+        }).collect();
+
+        Footprint { polygons }
+    }
+}
+
+fn cross(o: Point2, a: Point2, b: Point2) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Convex hull of points (Andrew's monotone chain), `None` for fewer than three distinct points.
+pub fn convex_hull(points: Vec<Point2>) -> Option<Footprint> {
+
+    let ring = hull_ring(points);
+    if ring.len() < 4 {
+        return None;
+    }
+
+    Some(Footprint { polygons: vec![Polygon { exterior: ring, interiors: Vec::new() }] })
+}
+
+/// Closed ring of convex hull vertices, shorter than four points (distinct points or extremes of
+/// collinear ones) when the points span no area. Hull of the ring and more points equals hull of
+/// all of them.
+pub fn hull_ring(mut points: Vec<Point2>) -> Ring {
+
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut lower: Ring = Vec::new();
+    for &p in points.iter() {
+        while lower.len() >= 2 && cross(lower[lower.len() - 2], lower[lower.len() - 1], p) <= 0.0 {
+            lower.pop();
+        }
+        lower.push(p);
+    }
+
+    let mut upper: Ring = Vec::new();
+    for &p in points.iter().rev() {
+        while upper.len() >= 2 && cross(upper[upper.len() - 2], upper[upper.len() - 1], p) <= 0.0 {
+            upper.pop();
+        }
+        upper.push(p);
+    }
+
+    // Last point of each chain is the first point of the other one.
+    lower.pop();
+    lower.extend(upper);
+    lower
+}
+
+/// Chains directed boundary edges into closed rings of vertex ids.
+fn trace_rings(edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(a, b) in edges {
+        outgoing.entry(a).or_default().push(b);
+    }
+
+    let mut next = |from: usize| outgoing.get_mut(&from).and_then(|ends| ends.pop());
+
+    let mut rings = Vec::new();
+    for &(start, _) in edges {
+        while let Some(mut current) = next(start) {
+            let mut ring = vec![start];
+            while current != start {
+                ring.push(current);
+                current = match next(current) {
+                    Some(vertex) => vertex,
+                    None => break
+                };
+            }
+            ring.push(start);
+            rings.push(ring);
+        }
+    }
+
+    rings
+}
+
+fn contains(ring: &[Point2], p: Point2) -> bool {
+
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((ax, ay), (bx, by)) = (w[0], w[1]);
+        if (ay > p.1) != (by > p.1) && p.0 < (bx - ax) * (p.1 - ay) / (by - ay) + ax {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Builds polygons from closed rings, nesting them by containment: rings inside an exterior become
+/// its holes, rings inside a hole become new exteriors. Exteriors are oriented counter-clockwise.
+fn assemble_polygons(rings: Vec<Ring>) -> Vec<Polygon> {
+
+    let mut rings: Vec<(f64, Ring)> = rings.into_iter()
+                                           .filter(|ring| ring.len() >= 4)
+                                           .map(|ring| (ring_area(&ring), ring))
+                                           .filter(|(area, _)| *area != 0.0)
+                                           .collect();
+    rings.sort_by(|a, b| b.0.abs().partial_cmp(&a.0.abs()).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut polygons: Vec<Polygon> = Vec::new();
+    // Already placed rings as (ring, polygon index, is exterior), from the largest.
+    let mut placed: Vec<(Ring, usize, bool)> = Vec::new();
+
+    for (area, mut ring) in rings {
+        let probe = ((ring[0].0 + ring[1].0) / 2.0, (ring[0].1 + ring[1].1) / 2.0);
+        let container = placed.iter().rev().find(|(other, _, _)| contains(other, probe));
+
+        match container {
+            Some(&(_, index, true)) => {
+                if area > 0.0 { ring.reverse(); }
+                polygons[index].interiors.push(ring.clone());
+                placed.push((ring, index, false));
+            },
+            _ => {
+                if area < 0.0 { ring.reverse(); }
+                polygons.push(Polygon { exterior: ring.clone(), interiors: Vec::new() });
+                placed.push((ring, polygons.len() - 1, true));
+            }
+        }
+    }
+
+    polygons
+}
+
+fn circumradius(a: Point2, b: Point2, c: Point2) -> f64 {
+
+    let ab = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+    let bc = ((c.0 - b.0).powi(2) + (c.1 - b.1).powi(2)).sqrt();
+    let ca = ((a.0 - c.0).powi(2) + (a.1 - c.1).powi(2)).sqrt();
+    let area = cross(a, b, c).abs() / 2.0;
+    if area == 0.0 {
+        return std::f64::INFINITY;
+    }
+
+    ab * bc * ca / (4.0 * area)
+}
+
+/// Alpha shape of points: union of Delaunay triangles with circumradius not greater than `alpha`.
+/// Returns `None` when no triangle passes the criterion.
+pub fn concave_hull(points: &[Point2], alpha: f64) -> Option<Footprint> {
+
+    let vertices: Vec<delaunator::Point> = points.iter().map(|&(x, y)| delaunator::Point { x, y }).collect();
+    let triangulation = delaunator::triangulate(&vertices)?;
+    let triangles = &triangulation.triangles;
+    let position = |i: usize| (vertices[i].x, vertices[i].y);
+
+    let kept: Vec<bool> = triangles.chunks(3)
+                                  .map(|t| circumradius(position(t[0]), position(t[1]), position(t[2])) <= alpha)
+                                  .collect();
+
+    // Half-edges of kept triangles whose twin is missing or belongs to a removed triangle.
+    let mut edges = Vec::new();
+    for e in 0..triangles.len() {
+        if !kept[e / 3] {
+            continue;
+        }
+
+        let twin = triangulation.halfedges[e];
+        if twin == delaunator::EMPTY || !kept[twin / 3] {
+            let next = if e % 3 == 2 { e - 2 } else { e + 1 };
+            edges.push((triangles[e], triangles[next]));
+        }
+    }
+
+    let rings = trace_rings(&edges).into_iter()
+                                   .map(|ring| ring.into_iter().map(position).collect())
+                                   .collect();
+    let polygons = assemble_polygons(rings);
+    if polygons.is_empty() {
+        return None;
+    }
+
+    Some(Footprint { polygons })
+}
+
+/// Drops vertices lying on a straight line between their neighbours.
+fn remove_collinear(ring: Ring) -> Ring {
+
+    if ring.len() < 4 {
+        return ring;
+    }
+
+    let n = ring.len() - 1;
+    let mut out: Ring = (0..n).filter(|&i| cross(ring[(i + n - 1) % n], ring[i], ring[i + 1]) != 0.0)
+                              .map(|i| ring[i])
+                              .collect();
+    out.extend(out.first().cloned());
+    out
+}
+
+/// Grids with more cells are refused, the limit is 100 MB of occupancy.
+const MAX_GRID_CELLS: usize = 100_000_000;
+
+/// Occupancy grid of points, cell (row, col) covers `origin + (col, row) * cell`.
+struct Grid {
+    origin: Point2,
+    cell: f64,
+    cols: usize,
+    rows: usize,
+    occupied: Vec<bool>
+}
+
+impl Grid {
+
+    fn new(points: &[Point2], cell: f64, padding: usize) -> Option<Grid> {
+
+        let first = points.first()?;
+        let (mut x0, mut y0, mut x1, mut y1) = (first.0, first.1, first.0, first.1);
+        for &(x, y) in points {
+            x0 = x0.min(x); y0 = y0.min(y);
+            x1 = x1.max(x); y1 = y1.max(y);
+        }
+
+        // Small cell or garbage coordinates would need more memory than there is.
+        let size = |min: f64, max: f64| Some(((max - min) / cell).floor())
+            .filter(|&cells| cells >= 0.0 && cells < MAX_GRID_CELLS as f64)
+            .and_then(|cells| (cells as usize).checked_add(1)?.checked_add(padding.checked_mul(2)?));
+        let (cols, rows) = match (size(x0, x1), size(y0, y1)) {
+            (Some(cols), Some(rows)) if cols.checked_mul(rows).map_or(false, |cells| cells <= MAX_GRID_CELLS) => (cols, rows),
+            _ => {
+                warn!("Footprint grid with cell {} over {}x{} extent is too large, header bounds are used", cell, x1 - x0, y1 - y0);
+                return None;
+            }
+        };
+        let pad = padding as f64 * cell;
+        let origin = (x0 - pad, y0 - pad);
+
+        let mut grid = Grid { origin, cell, cols, rows, occupied: vec![false; cols * rows] };
+        for &(x, y) in points {
+            let col = ((x - origin.0) / cell).floor() as usize;
+            let row = ((y - origin.1) / cell).floor() as usize;
+            grid.occupied[row * cols + col] = true;
+        }
+
+        Some(grid)
+    }
+
+    fn get(&self, row: isize, col: isize) -> bool {
+        row >= 0 && col >= 0 && (row as usize) < self.rows && (col as usize) < self.cols
+            && self.occupied[row as usize * self.cols + col as usize]
+    }
+
+    /// One step of dilation (`value == true`) or erosion (`value == false`) with 3x3 element.
+    fn morph(&mut self, value: bool) {
+
+        let mut out = self.occupied.clone();
+        for row in 0..self.rows as isize {
+            for col in 0..self.cols as isize {
+                let hit = (-1..=1).any(|dr| (-1..=1).any(|dc| self.get(row + dr, col + dc) == value));
+                if hit {
+                    out[row as usize * self.cols + col as usize] = value;
+                }
+            }
+        }
+        self.occupied = out;
+    }
+
+    /// Counter-clockwise boundary edges of occupied cells, between corner ids `row * (cols + 1) + col`.
+    fn boundary_edges(&self) -> Vec<(usize, usize)> {
+
+        let corner = |row: usize, col: usize| row * (self.cols + 1) + col;
+        let mut edges = Vec::new();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let (r, c) = (row as isize, col as isize);
+                if !self.get(r, c) {
+                    continue;
+                }
+                if !self.get(r - 1, c) { edges.push((corner(row, col), corner(row, col + 1))); }
+                if !self.get(r, c + 1) { edges.push((corner(row, col + 1), corner(row + 1, col + 1))); }
+                if !self.get(r + 1, c) { edges.push((corner(row + 1, col + 1), corner(row + 1, col))); }
+                if !self.get(r, c - 1) { edges.push((corner(row + 1, col), corner(row, col))); }
+            }
+        }
+        edges
+    }
+
+    fn corner_position(&self, id: usize) -> Point2 {
+
+        let (row, col) = (id / (self.cols + 1), id % (self.cols + 1));
+        (self.origin.0 + col as f64 * self.cell, self.origin.1 + row as f64 * self.cell)
+    }
+}
+
+/// Footprint made of grid cells containing points, with gaps up to `closing` cells filled in.
+/// Interior areas without points become holes. `None` also when the grid would be too large.
+pub fn grid_footprint(points: &[Point2], cell: f64, closing: usize) -> Option<Footprint> {
+
+    let mut grid = Grid::new(points, cell, closing)?;
+    for _ in 0..closing {
+        grid.morph(true);
+    }
+    for _ in 0..closing {
+        grid.morph(false);
+    }
+
+    let rings = trace_rings(&grid.boundary_edges()).into_iter()
+                    .map(|ring| remove_collinear(ring.into_iter().map(|id| grid.corner_position(id)).collect()))
+                    .collect();
+    let polygons = assemble_polygons(rings);
+    if polygons.is_empty() {
+        return None;
+    }
+
+    Some(Footprint { polygons })
+}
@@ -0,0 +1,101 @@
+//! Parser of GeoTIFF GeoKey VLRs used by legacy LAS files to describe their CRS.
+
+use std::collections::HashMap;
+
+
+pub const GEO_KEY_DIRECTORY_RECORD_ID: u16 = 34735;
+pub const GEO_DOUBLE_PARAMS_RECORD_ID: u16 = 34736;
+pub const GEO_ASCII_PARAMS_RECORD_ID: u16 = 34737;
+
+const GEOGRAPHIC_TYPE_GEO_KEY: u16 = 2048;
+const PROJECTED_CS_TYPE_GEO_KEY: u16 = 3072;
+const USER_DEFINED: u16 = 32767;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoKeyValue {
+    Short(u16),
+    Double(Vec<f64>),
+    Ascii(String)
+}
+
+/// GeoKeys by key id.
+pub struct GeoKeys {
+    keys: HashMap<u16, GeoKeyValue>
+}
+
+fn read_u16(data: &[u8], index: usize) -> Option<u16> {
+    data.get(index * 2..index * 2 + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_f64(data: &[u8], index: usize) -> Option<f64> {
+
+    let bytes = data.get(index * 8..index * 8 + 8)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Some(f64::from_le_bytes(buf))
+}
+
+impl GeoKeys {
+
+    /// Parses GeoKeyDirectoryTag record with its optional double and ASCII parameter records.
+    /// Returns `None` when the directory is truncated.
+    pub fn parse(directory: &[u8], doubles: Option<&[u8]>, ascii: Option<&[u8]>) -> Option<GeoKeys> {
+
+        let number_of_keys = read_u16(directory, 3)? as usize;
+        let mut keys = HashMap::with_capacity(number_of_keys);
+
+        for i in 0..number_of_keys {
+            let entry = 4 + i * 4;
+            let key_id = read_u16(directory, entry)?;
+            let location = read_u16(directory, entry + 1)?;
+            let count = read_u16(directory, entry + 2)? as usize;
+            let value_offset = read_u16(directory, entry + 3)? as usize;
+
+            let value = match location {
+                0 => Some(GeoKeyValue::Short(value_offset as u16)),
+                GEO_DOUBLE_PARAMS_RECORD_ID => doubles.and_then(|data| {
+                    (value_offset..value_offset + count).map(|j| read_f64(data, j))
+                                                        .collect::<Option<Vec<_>>>()
+                                                        .map(GeoKeyValue::Double)
+                }),
+                GEO_ASCII_PARAMS_RECORD_ID => ascii.and_then(|data| data.get(value_offset..value_offset + count))
+                                                   .map(|bytes| String::from_utf8_lossy(bytes)
+                                                                       .trim_end_matches(|c| c == '|' || c == '\0')
+                                                                       .to_string())
+                                                   .map(GeoKeyValue::Ascii),
+                _ => None
+            };
+
+            if let Some(value) = value {
+                keys.insert(key_id, value);
+            }
+        }
+
+        Some(GeoKeys { keys })
+    }
+
+    pub fn get(&self, key_id: u16) -> Option<&GeoKeyValue> {
+        self.keys.get(&key_id)
+    }
+
+    fn code(&self, key_id: u16) -> Option<u32> {
+        match self.get(key_id) {
+            Some(GeoKeyValue::Short(code)) if *code != 0 && *code != USER_DEFINED => Some(u32::from(*code)),
+            _ => None
+        }
+    }
+
+    /// EPSG code of the projected CRS, or of the geographic CRS for unprojected data.
+    pub fn epsg(&self) -> Option<u32> {
+        self.code(PROJECTED_CS_TYPE_GEO_KEY)
+            .or_else(|| self.code(GEOGRAPHIC_TYPE_GEO_KEY))
+    }
+}
+
+/// GeoKeyDirectoryTag record with single key declaring the EPSG code, for CRS of non-LAS inputs.
+pub fn epsg_directory(code: u16) -> Vec<u8> {
+
+    // Directory version 1, revision 1.0, one key; the key value is stored inline (location 0).
+    let shorts = [1, 1, 0, 1, PROJECTED_CS_TYPE_GEO_KEY, 0, 1, code];
+    shorts.iter().flat_map(|short| short.to_le_bytes().to_vec()).collect()
+}
@@ -0,0 +1,280 @@
+//! Reading of LAS headers from byte ranges, without setting up point reading.
+
+use crate::LasBoundsError;
+use crate::copc;
+use las::{Builder, Header, Vlr};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+
+/// Size of the largest (LAS 1.4) public header block.
+pub const MAX_HEADER_SIZE: u64 = 375;
+
+/// Size of the header of an EVLR, before its payload.
+pub const EVLR_HEADER_SIZE: u64 = 60;
+
+/// Makes the 64-bit point counts of LAS 1.4 take precedence over the legacy 32-bit ones, which
+/// are zero in files of more than 4.2 billion points but may be stale or truncated in others.
+/// Legacy counts are used by the header builder when they are not zero.
+fn prefer_extended_counts(raw: &mut las::raw::Header) {
+
+    if let Some(large_file) = &raw.large_file {
+        if large_file.number_of_point_records > 0 {
+            raw.number_of_point_records = 0;
+        }
+        if large_file.number_of_points_by_return.iter().any(|&count| count > 0) {
+            raw.number_of_points_by_return = [0; 5];
+        }
+    }
+}
+
+/// Takes bounds of COPC files whose writer left header bounds zeroed from the octree root cube of
+/// the info VLR, which encloses the points.
+fn fill_copc_bounds(raw: &mut las::raw::Header, vlrs: &[Vlr]) {
+
+    let zeroed = [raw.min_x, raw.min_y, raw.min_z, raw.max_x, raw.max_y, raw.max_z].iter().all(|&v| v == 0.0);
+    if let (true, Some(info)) = (zeroed, copc::vlrs_copc_info(vlrs)) {
+        if info.halfsize > 0.0 && info.halfsize.is_finite() {
+            let cube = info.cube();
+            raw.min_x = cube.min.x;
+            raw.min_y = cube.min.y;
+            raw.min_z = cube.min.z;
+            raw.max_x = cube.max.x;
+            raw.max_y = cube.max.y;
+            raw.max_z = cube.max.z;
+        }
+    }
+}
+
+/// Reads EVLR at `start` with its header and payload only, returns it with its length in the file.
+fn read_evlr<F>(fetch: &mut F, start: u64) -> Result<(Vlr, u64), LasBoundsError>
+    where F: FnMut(u64, u64) -> Result<Vec<u8>, LasBoundsError> {
+
+    let mut bytes = fetch(start, EVLR_HEADER_SIZE)?;
+    if bytes.len() < EVLR_HEADER_SIZE as usize {
+        return Err(format!("EVLR at {} is truncated", start).into());
+    }
+    // Payload length follows reserved bytes, user ID and record ID.
+    let payload_len = u64::from_le_bytes(bytes[20..28].try_into().unwrap());
+    bytes.extend(fetch(start.saturating_add(EVLR_HEADER_SIZE), payload_len)?);
+    let vlr = Vlr::new(las::raw::Vlr::read_from(Cursor::new(bytes), true)?);
+    Ok((vlr, EVLR_HEADER_SIZE.saturating_add(payload_len)))
+}
+
+/// Reads header with VLRs and EVLRs using only the byte ranges it needs. `fetch(start, len)`
+/// returns up to `len` bytes of the file starting at `start`.
+pub fn read_header_ranges<F>(mut fetch: F) -> Result<Header, LasBoundsError>
+    where F: FnMut(u64, u64) -> Result<Vec<u8>, LasBoundsError> {
+
+    let mut raw = las::raw::Header::read_from(Cursor::new(fetch(0, MAX_HEADER_SIZE)?))?;
+    prefer_extended_counts(&mut raw);
+
+    let mut vlrs = Vec::new();
+    let vlr_start = u64::from(raw.header_size);
+    let vlr_len = u64::from(raw.offset_to_point_data).saturating_sub(vlr_start);
+    if raw.number_of_variable_length_records > 0 && vlr_len > 0 {
+        let mut cursor = Cursor::new(fetch(vlr_start, vlr_len)?);
+        for _ in 0..raw.number_of_variable_length_records {
+            vlrs.push(Vlr::new(las::raw::Vlr::read_from(&mut cursor, false)?));
+        }
+    }
+
+    // Writers leave the EVLR start zero when there are none, so it is not fetched then.
+    let mut evlrs = Vec::new();
+    if let Some(evlr) = raw.evlr.filter(|evlr| evlr.number_of_evlrs > 0) {
+        let mut start = evlr.start_of_first_evlr;
+        for _ in 0..evlr.number_of_evlrs {
+            let (vlr, len) = read_evlr(&mut fetch, start)?;
+            evlrs.push(vlr);
+            start = start.saturating_add(len);
+        }
+    }
+
+    fill_copc_bounds(&mut raw, &vlrs);
+    let mut builder = Builder::new(raw)?;
+    builder.vlrs = vlrs;
+    builder.evlrs = evlrs;
+    Ok(builder.into_header()?)
+}
+
+/// Reads header of a local file from just the bytes of the header block, VLRs and EVLRs, without
+/// setting up point reading (and LAZ decompression) as [`las::Reader`] does.
+pub fn read_file_header(path: &Path) -> Result<Header, LasBoundsError> {
+
+    read_open_header(&mut File::open(path)?)
+}
+
+/// Reads header as [`read_file_header`] from a file already open, e.g. to hash it afterwards.
+pub fn read_open_header(file: &mut File) -> Result<Header, LasBoundsError> {
+
+    read_header_ranges(|start, len| {
+        file.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::new();
+        (&mut file).take(len).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use las::raw::header::{Evlr, LargeFile};
+
+    /// Bytes of LAS 1.4 header block without VLRs and points, with given legacy and extended counts.
+    fn header_bytes(legacy: u32, legacy_by_return: [u32; 5], extended: u64, extended_by_return: [u64; 15]) -> Vec<u8> {
+
+        let raw = las::raw::Header {
+            version: las::Version::new(1, 4),
+            header_size: MAX_HEADER_SIZE as u16,
+            offset_to_point_data: MAX_HEADER_SIZE as u32,
+            point_data_record_format: 6,
+            point_data_record_length: 30,
+            number_of_point_records: legacy,
+            number_of_points_by_return: legacy_by_return,
+            evlr: Some(Evlr { start_of_first_evlr: MAX_HEADER_SIZE, number_of_evlrs: 0 }),
+            large_file: Some(LargeFile { number_of_point_records: extended, number_of_points_by_return: extended_by_return }),
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        raw.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn read(bytes: &[u8]) -> Header {
+
+        read_fetched(bytes).0
+    }
+
+    /// Reads the header, also returning the ranges fetched.
+    fn read_fetched(bytes: &[u8]) -> (Header, Vec<(u64, u64)>) {
+
+        let mut fetched = Vec::new();
+        let header = read_header_ranges(|start, len| {
+            fetched.push((start, len));
+            let start = (start as usize).min(bytes.len());
+            let end = start.saturating_add(len.min(bytes.len() as u64) as usize).min(bytes.len());
+            Ok(bytes[start..end].to_vec())
+        }).unwrap();
+        (header, fetched)
+    }
+
+    /// Bytes of LAS 1.4 header block without VLRs and points, followed by the EVLRs.
+    fn evlr_header_bytes(start_of_first_evlr: u64, evlrs: Vec<Vlr>) -> Vec<u8> {
+
+        let raw = las::raw::Header {
+            version: las::Version::new(1, 4),
+            header_size: MAX_HEADER_SIZE as u16,
+            offset_to_point_data: MAX_HEADER_SIZE as u32,
+            point_data_record_format: 6,
+            point_data_record_length: 30,
+            evlr: Some(Evlr { start_of_first_evlr, number_of_evlrs: evlrs.len() as u32 }),
+            large_file: Some(LargeFile { number_of_point_records: 0, number_of_points_by_return: [0; 15] }),
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        raw.write_to(&mut bytes).unwrap();
+        for evlr in evlrs {
+            evlr.into_raw(true).unwrap().write_to(&mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    const BILLIONS: u64 = 5_000_000_000;
+
+    fn by_return() -> [u64; 15] {
+
+        let mut counts = [0; 15];
+        counts[0] = 3_000_000_000;
+        counts[1] = 2_000_000_000;
+        counts
+    }
+
+    #[test]
+    fn reads_extended_counts_above_u32() {
+
+        let header = read(&header_bytes(0, [0; 5], BILLIONS, by_return()));
+        assert!(BILLIONS > u64::from(u32::max_value()));
+        assert_eq!(header.number_of_points(), BILLIONS);
+        assert_eq!(header.number_of_points_by_return(1), Some(3_000_000_000));
+        assert_eq!(header.number_of_points_by_return(2), Some(2_000_000_000));
+    }
+
+    #[test]
+    fn prefers_extended_counts_over_disagreeing_legacy_ones() {
+
+        let header = read(&header_bytes(1_000, [600, 400, 0, 0, 0], BILLIONS, by_return()));
+        assert_eq!(header.number_of_points(), BILLIONS);
+        assert_eq!(header.number_of_points_by_return(1), Some(3_000_000_000));
+        assert_eq!(header.number_of_points_by_return(2), Some(2_000_000_000));
+    }
+
+    #[test]
+    fn keeps_legacy_counts_when_extended_ones_are_zero() {
+
+        let header = read(&header_bytes(1_000, [600, 400, 0, 0, 0], 0, [0; 15]));
+        assert_eq!(header.number_of_points(), 1_000);
+        assert_eq!(header.number_of_points_by_return(1), Some(600));
+        assert_eq!(header.number_of_points_by_return(2), Some(400));
+    }
+
+    #[test]
+    fn fetches_only_header_block_without_evlrs() {
+
+        let (header, fetched) = read_fetched(&evlr_header_bytes(0, Vec::new()));
+        assert!(header.evlrs().is_empty());
+        assert_eq!(fetched, vec![(0, MAX_HEADER_SIZE)]);
+    }
+
+    #[test]
+    fn fetches_evlrs_by_their_lengths() {
+
+        let evlr = |record_id, len| Vlr { user_id: "test".to_string(), record_id, description: String::new(), data: vec![7; len] };
+        let (header, fetched) = read_fetched(&evlr_header_bytes(MAX_HEADER_SIZE, vec![evlr(1, 10), evlr(2, 20)]));
+        assert_eq!(header.evlrs().iter().map(|vlr| (vlr.record_id, vlr.data.len())).collect::<Vec<_>>(), vec![(1, 10), (2, 20)]);
+        let second = MAX_HEADER_SIZE + EVLR_HEADER_SIZE + 10;
+        assert_eq!(fetched, vec![(0, MAX_HEADER_SIZE), (MAX_HEADER_SIZE, EVLR_HEADER_SIZE), (MAX_HEADER_SIZE + EVLR_HEADER_SIZE, 10),
+                                 (second, EVLR_HEADER_SIZE), (second + EVLR_HEADER_SIZE, 20)]);
+    }
+
+    /// Bytes of LAS 1.4 header block with zeroed bounds and COPC info VLR of given root cube.
+    fn copc_header_bytes(center: (f64, f64, f64), halfsize: f64) -> Vec<u8> {
+
+        let mut data = Vec::new();
+        for value in &[center.0, center.1, center.2, halfsize] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.resize(160, 0);
+        let vlr = Vlr { user_id: "copc".to_string(), record_id: 1, description: String::new(), data };
+        let mut vlr_bytes = Vec::new();
+        vlr.into_raw(false).unwrap().write_to(&mut vlr_bytes).unwrap();
+
+        let offset_to_point_data = MAX_HEADER_SIZE + vlr_bytes.len() as u64;
+        let raw = las::raw::Header {
+            version: las::Version::new(1, 4),
+            header_size: MAX_HEADER_SIZE as u16,
+            offset_to_point_data: offset_to_point_data as u32,
+            number_of_variable_length_records: 1,
+            point_data_record_format: 6,
+            point_data_record_length: 30,
+            evlr: Some(Evlr { start_of_first_evlr: offset_to_point_data, number_of_evlrs: 0 }),
+            large_file: Some(LargeFile { number_of_point_records: 1_000, number_of_points_by_return: [0; 15] }),
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        raw.write_to(&mut bytes).unwrap();
+        bytes.extend_from_slice(&vlr_bytes);
+        bytes
+    }
+
+    #[test]
+    fn takes_zeroed_copc_bounds_from_info_cube() {
+
+        let header = read(&copc_header_bytes((500_000.0, 5_500_000.0, 100.0), 50.0));
+        let bounds = header.bounds();
+        assert_eq!((bounds.min.x, bounds.min.y, bounds.min.z), (499_950.0, 5_499_950.0, 50.0));
+        assert_eq!((bounds.max.x, bounds.max.y, bounds.max.z), (500_050.0, 5_500_050.0, 150.0));
+    }
+}
@@ -0,0 +1,57 @@
+//! Hexagonal grid point counts, a density surface across all files.
+//!
+//! Hexagons are pointy-top with the given side length, addressed by axial coordinates.
+
+use crate::footprint::Ring;
+use std::collections::HashMap;
+
+
+pub type Hex = (i64, i64);
+
+pub type HexCounts = HashMap<Hex, u64>;
+
+/// Hexagon containing the point.
+pub fn hex_of(x: f64, y: f64, size: f64) -> Hex {
+
+    let q = (3f64.sqrt() / 3.0 * x - y / 3.0) / size;
+    let r = (2.0 / 3.0 * y) / size;
+
+    // Rounding in cube coordinates, fixing the component with the largest rounding error.
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i64, rr as i64)
+}
+
+/// Closed ring of the hexagon vertices.
+pub fn hex_polygon(hex: Hex, size: f64) -> Ring {
+
+    let (q, r) = (hex.0 as f64, hex.1 as f64);
+    let cx = size * 3f64.sqrt() * (q + r / 2.0);
+    let cy = size * 1.5 * r;
+    let mut ring: Ring = (0..6)
+        .map(|i| {
+            let angle = (60.0 * i as f64 - 30.0).to_radians();
+            (cx + size * angle.cos(), cy + size * angle.sin())
+        })
+        .collect();
+    ring.push(ring[0]);
+    ring
+}
+
+pub fn hex_area(size: f64) -> f64 {
+    3.0 * 3f64.sqrt() / 2.0 * size * size
+}
+
+/// Adds counts of `other` to `counts`.
+pub fn merge(counts: &mut HexCounts, other: &HexCounts) {
+
+    for (hex, count) in other {
+        *counts.entry(*hex).or_insert(0) += count;
+    }
+}
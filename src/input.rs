@@ -0,0 +1,80 @@
+//! Input formats other than LAS/LAZ files, described by synthetic LAS headers so they are
+//! indexed with the same attributes.
+
+use crate::LasBoundsError;
+use las::{Builder, Bounds, Header, Version, Vlr};
+use las::raw::header::LargeFile;
+use std::convert::TryFrom;
+use std::path::Path;
+
+
+pub const EPT_FILE_NAME: &str = "ept.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    Las,
+    /// Entwine Point Tile dataset, given by its `ept.json`.
+    Ept,
+    /// ASCII file with delimited coordinates.
+    Xyz,
+    /// PLY point file.
+    Ply
+}
+
+impl InputFormat {
+
+    /// Format of the input by its file name, LAS for anything not recognized.
+    pub fn of(path: &Path) -> Self {
+
+        if path.file_name().and_then(|name| name.to_str()) == Some(EPT_FILE_NAME) {
+            return InputFormat::Ept;
+        }
+
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+        match ext.as_str() {
+            "xyz" | "txt" | "csv" => InputFormat::Xyz,
+            "ply" => InputFormat::Ply,
+            _ => InputFormat::Las
+        }
+    }
+
+    /// Whether the header can be read without reading all points, e.g. to detect CRS.
+    pub fn has_header(self) -> bool {
+        self == InputFormat::Las || self == InputFormat::Ept
+    }
+}
+
+/// Extends bounds by the point.
+pub fn grow(bounds: &mut Bounds, x: f64, y: f64, z: f64) {
+
+    bounds.min.x = bounds.min.x.min(x);
+    bounds.min.y = bounds.min.y.min(y);
+    bounds.min.z = bounds.min.z.min(z);
+    bounds.max.x = bounds.max.x.max(x);
+    bounds.max.y = bounds.max.y.max(y);
+    bounds.max.z = bounds.max.z.max(z);
+}
+
+/// Builds LAS header with given bounds, point count and VLRs.
+pub fn synthetic_header(bounds: &Bounds, point_count: u64, vlrs: Vec<Vlr>) -> Result<Header, LasBoundsError> {
+
+    // LAS 1.4 is needed for 64 bit point counts.
+    let version = Version::new(1, 4);
+    let raw = las::raw::Header {
+        version,
+        header_size: version.header_size(),
+        min_x: bounds.min.x,
+        min_y: bounds.min.y,
+        min_z: bounds.min.z,
+        max_x: bounds.max.x,
+        max_y: bounds.max.y,
+        max_z: bounds.max.z,
+        number_of_point_records: u32::try_from(point_count).unwrap_or(0),
+        large_file: Some(LargeFile { number_of_point_records: point_count, number_of_points_by_return: [0; 15] }),
+        ..Default::default()
+    };
+
+    let mut builder = Builder::new(raw)?;
+    builder.vlrs = vlrs;
+    Ok(builder.into_header()?)
+}
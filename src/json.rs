@@ -0,0 +1,116 @@
+//! JSON representation of per file results, used for structured logs, and of the outcome of a run.
+
+use crate::{LasBoundsError, RecordResult};
+use crate::input;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+
+
+/// Processing status of a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    Skipped,
+    Failed
+}
+
+impl Status {
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Skipped => "skipped",
+            Status::Failed => "failed"
+        }
+    }
+}
+
+/// JSON object describing processed file: path, status, duration, bounds and error, if any.
+/// `error` overrides the error of the result, e.g. when reading succeeded but writing failed.
+pub fn result_json(result: &RecordResult, status: Status, error: Option<&LasBoundsError>) -> Value {
+
+    let mut value = json!({
+        "path": result.path.to_string_lossy(),
+        "status": status.as_str(),
+        "duration_ms": result.duration.as_secs_f64() * 1000.0
+    });
+
+    match &result.result {
+        Ok(record) => {
+            let bounds = record.header.bounds();
+            value["bounds"] = json!({
+                "min": [bounds.min.x, bounds.min.y, bounds.min.z],
+                "max": [bounds.max.x, bounds.max.y, bounds.max.z]
+            });
+            value["point_count"] = json!(record.header.number_of_points());
+        },
+        Err(e) => value["error"] = json!(e.to_string())
+    }
+
+    if let Some(e) = error {
+        value["error"] = json!(e.to_string());
+    }
+
+    value
+}
+
+/// Outcome of an indexing run.
+#[derive(Default)]
+pub struct RunSummary {
+    pub written: usize,
+    pub skipped: usize,
+    pub failures: Vec<(PathBuf, String)>,
+    /// Main output, `None` when only STAC is written.
+    pub output: Option<String>,
+    /// Header bounds of written files, in their CRS.
+    pub bounds: Option<las::Bounds>,
+    pub duration: Duration,
+    /// Error that stopped the run.
+    pub error: Option<String>
+}
+
+impl RunSummary {
+
+    /// Counts the result by its status, bounds of written files are added to the union.
+    pub fn add(&mut self, result: &RecordResult, status: Status, error: Option<&LasBoundsError>) {
+
+        match (status, &result.result) {
+            (Status::Ok, Ok(record)) => {
+                self.written += 1;
+                let bounds = record.header.bounds();
+                let union = self.bounds.get_or_insert(bounds);
+                input::grow(union, bounds.min.x, bounds.min.y, bounds.min.z);
+                input::grow(union, bounds.max.x, bounds.max.y, bounds.max.z);
+            },
+            (Status::Skipped, _) => self.skipped += 1,
+            (_, read) => {
+                let message = match (error, read) {
+                    (Some(e), _) | (None, Err(e)) => e.to_string(),
+                    (None, Ok(_)) => "failed".to_string()
+                };
+                self.failures.push((result.path.clone(), message));
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+
+        json!({
+            "files": self.written + self.skipped + self.failures.len(),
+            "written": self.written,
+            "skipped": self.skipped,
+            "failed": self.failures.len(),
+            "failures": self.failures.iter()
+                .map(|(path, error)| json!({ "path": path.to_string_lossy(), "error": error }))
+                .collect::<Vec<_>>(),
+            "output": self.output,
+            "bounds": self.bounds.map(|b| json!({
+                "min": [b.min.x, b.min.y, b.min.z],
+                "max": [b.max.x, b.max.y, b.max.z]
+            })),
+            "duration_ms": self.duration.as_secs_f64() * 1000.0,
+            "error": self.error
+        })
+    }
+}
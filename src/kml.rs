@@ -0,0 +1,220 @@
+//! KML and KMZ output with a placemark per file, for viewing coverage in Google Earth.
+
+use crate::{BoundsRecord, BoundsWriter, LasBoundsError};
+use crate::fields::{self, Field, Value};
+use crate::footprint::Ring;
+use crate::ogr;
+use crate::writer;
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+
+const STYLE_ID: &str = "bounds";
+/// Style of files failing density limits, yellow with thicker outline.
+const DENSITY_FAILED_STYLE_ID: &str = "density_failed";
+const DENSITY_FAILED_LINE_COLOR: &str = "ff00ffff";
+const DENSITY_FAILED_FILL_COLOR: &str = "4000ffff";
+
+/// How Google Earth places polygon vertices vertically.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Altitude {
+    /// Draped on the terrain.
+    Clamp,
+    /// At maximum Z of the file, which has to be height above sea level in meters.
+    Absolute
+}
+
+impl FromStr for Altitude {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(Altitude::Clamp),
+            "absolute" => Ok(Altitude::Absolute),
+            _ => Err(format!("Unknown altitude mode: {}", s))
+        }
+    }
+}
+
+/// Color as KML `aabbggrr` from `RRGGBB` or `RRGGBBAA`, optionally prefixed with `#`.
+pub fn parse_color(s: &str) -> Result<String, LasBoundsError> {
+
+    let hex = s.trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid color, expected RRGGBB or RRGGBBAA: {}", s).into());
+    }
+    let alpha = if hex.len() == 8 { &hex[6..8] } else { "ff" };
+    Ok(format!("{}{}{}{}", alpha, &hex[4..6], &hex[2..4], &hex[0..2]).to_ascii_lowercase())
+}
+
+pub struct KmlOptions {
+    /// Output path, compressed as KMZ when it has `.kmz` extension.
+    pub path: PathBuf,
+    pub altitude: Altitude,
+    /// Outline color, `aabbggrr`.
+    pub line_color: String,
+    /// Fill color, `aabbggrr`.
+    pub fill_color: String,
+    pub line_width: f64,
+    pub overwrite: bool
+}
+
+impl Default for KmlOptions {
+
+    fn default() -> Self {
+        KmlOptions {
+            path: PathBuf::new(),
+            altitude: Altitude::Clamp,
+            line_color: "ff0000ff".to_string(),
+            fill_color: "400000ff".to_string(),
+            line_width: 2.0,
+            overwrite: false
+        }
+    }
+}
+
+/// Collects placemarks and writes the document on finish.
+pub struct KmlWriter {
+    options: KmlOptions,
+    fields: Vec<Field>,
+    /// Transformation of footprints into WGS84.
+    transform: CoordTransform,
+    placemarks: String
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn format_value(value: Option<Value>) -> String {
+
+    match value {
+        Some(Value::Integer(i)) => i.to_string(),
+        Some(Value::Real(r)) => format!("{:.3}", r),
+        Some(Value::String(s)) => s,
+        None => String::new()
+    }
+}
+
+impl KmlWriter {
+
+    /// `srs` is CRS of the input files. Fails when the output exists and overwrite was not requested.
+    pub fn new(options: KmlOptions, srs: &SpatialRef, fields: &[Field]) -> Result<Self, LasBoundsError> {
+
+        if options.path.exists() && !options.overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite", options.path.to_string_lossy()).into());
+        }
+
+        let wgs84 = SpatialRef::from_epsg(4326)?;
+        ogr::set_traditional_axis_order(&wgs84);
+        let transform = CoordTransform::new(srs, &wgs84)?;
+
+        Ok(KmlWriter { options, fields: fields.to_vec(), transform, placemarks: String::new() })
+    }
+
+    /// `lon,lat[,alt]` tuples of the ring.
+    fn coordinates(&self, ring: &Ring, altitude: f64) -> Result<String, LasBoundsError> {
+
+        let mut xs: Vec<f64> = ring.iter().map(|p| p.0).collect();
+        let mut ys: Vec<f64> = ring.iter().map(|p| p.1).collect();
+        let mut zs = vec![0.0; ring.len()];
+        self.transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
+
+        let tuples: Vec<String> = xs.iter().zip(&ys)
+            .map(|(x, y)| match self.options.altitude {
+                Altitude::Clamp => format!("{},{}", x, y),
+                Altitude::Absolute => format!("{},{},{}", x, y, altitude)
+            })
+            .collect();
+        Ok(tuples.join(" "))
+    }
+
+    fn polygons(&self, record: &BoundsRecord) -> Result<String, LasBoundsError> {
+
+        let altitude_mode = match self.options.altitude {
+            Altitude::Clamp => "clampToGround",
+            Altitude::Absolute => "absolute"
+        };
+        let altitude = record.header.bounds().max.z;
+
+        let mut kml = String::new();
+        for polygon in &writer::densified_footprint(record).polygons {
+            write!(kml, "<Polygon><altitudeMode>{}</altitudeMode>", altitude_mode).unwrap();
+            write!(kml, "<outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs>",
+                   self.coordinates(&polygon.exterior, altitude)?).unwrap();
+            for interior in &polygon.interiors {
+                write!(kml, "<innerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></innerBoundaryIs>",
+                       self.coordinates(interior, altitude)?).unwrap();
+            }
+            kml.push_str("</Polygon>");
+        }
+        Ok(kml)
+    }
+
+    /// Balloon text, table of attributes.
+    fn description(&self, record: &BoundsRecord) -> String {
+
+        let rows: String = self.fields.iter()
+            .map(|field| format!("<tr><td>{}</td><td>{}</td></tr>", field.name, format_value(fields::value(record, field))))
+            .collect();
+        escape(&format!("<table>{}</table>", rows))
+    }
+
+    fn document(&self) -> String {
+
+        let options = &self.options;
+        format!(concat!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                    "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n",
+                    "<name>{}</name>\n",
+                    "<Style id=\"{}\"><LineStyle><color>{}</color><width>{}</width></LineStyle>",
+                    "<PolyStyle><color>{}</color></PolyStyle></Style>\n",
+                    "<Style id=\"{}\"><LineStyle><color>{}</color><width>{}</width></LineStyle>",
+                    "<PolyStyle><color>{}</color></PolyStyle></Style>\n",
+                    "{}</Document>\n</kml>\n"),
+                escape(&options.path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()),
+                STYLE_ID, options.line_color, options.line_width, options.fill_color,
+                DENSITY_FAILED_STYLE_ID, DENSITY_FAILED_LINE_COLOR, options.line_width * 2.0, DENSITY_FAILED_FILL_COLOR,
+                self.placemarks)
+    }
+}
+
+/// Writes KMZ archive with the document as `doc.kml`.
+fn write_kmz(path: &Path, document: &str) -> Result<(), LasBoundsError> {
+
+    let mut zip = zip::ZipWriter::new(File::create(path)?);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("doc.kml", options).map_err(|e| format!("Could not write {}: {}", path.to_string_lossy(), e))?;
+    zip.write_all(document.as_bytes())?;
+    zip.finish().map_err(|e| format!("Could not write {}: {}", path.to_string_lossy(), e))?;
+    Ok(())
+}
+
+impl BoundsWriter for KmlWriter {
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        let name = escape(&format_value(fields::value(record, &fields::NAME)));
+        let style_id = if record.density_ok == Some(false) { DENSITY_FAILED_STYLE_ID } else { STYLE_ID };
+        let placemark = format!("<Placemark><name>{}</name><description>{}</description><styleUrl>#{}</styleUrl>\
+                                 <MultiGeometry>{}</MultiGeometry></Placemark>\n",
+                                name, self.description(record), style_id, self.polygons(record)?);
+        self.placemarks.push_str(&placemark);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        let path = &self.options.path;
+        let kmz = path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("kmz"));
+        if kmz {
+            write_kmz(path, &self.document())
+        } else {
+            Ok(fs::write(path, self.document())?)
+        }
+    }
+}
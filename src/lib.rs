@@ -0,0 +1,84 @@
+//! Indexing of LAS/LAZ files: discovers files, reads their bounds and writes them as polygons
+//! with attributes to OGR vector datasets, CSV or KML files.
+//!
+//! ```no_run
+//! use las_bounds::{scan_dir, read_bounds, BoundsWriter, DiscoverOptions, OgrWriter, OgrOptions, ReadOptions};
+//! use std::path::Path;
+//!
+//! # fn main() -> Result<(), las_bounds::LasBoundsError> {
+//! let options = ReadOptions::default();
+//! let fields = las_bounds::fields::schema(&options);
+//! let mut writer = OgrWriter::new(OgrOptions::new("GPKG", "tiles.gpkg"), &fields)?;
+//! for path in scan_dir(Path::new("tiles"), &DiscoverOptions::default())? {
+//!     writer.write(&read_bounds(&path, &options)?)?;
+//! }
+//! writer.finish()?;
+//! # Ok(())
+//! # }
+//! ```
+
+extern crate chrono;
+extern crate delaunator;
+extern crate gdal;
+extern crate gdal_sys;
+extern crate glob;
+extern crate las;
+#[macro_use]
+extern crate log;
+extern crate md5;
+extern crate notify;
+extern crate rayon;
+extern crate rusoto_core;
+extern crate rusoto_s3;
+extern crate serde_json;
+extern crate sha2;
+extern crate tokio;
+extern crate twox_hash;
+extern crate ureq;
+extern crate walkdir;
+extern crate zip;
+
+pub mod cache;
+pub mod checksum;
+pub mod copc;
+pub mod crs;
+pub mod csv;
+pub mod density;
+pub mod diff;
+pub mod ept;
+pub mod expr;
+pub mod extra_bytes;
+pub mod fields;
+pub mod footprint;
+pub mod geokeys;
+pub mod hexbin;
+pub mod input;
+pub mod json;
+pub mod kml;
+pub mod merge;
+pub mod ply;
+pub mod remote;
+pub mod repair;
+pub mod report;
+pub mod s3;
+pub mod stac;
+pub mod scan;
+pub mod stats;
+pub mod tiles;
+pub mod validate;
+pub mod watch;
+pub mod xyz;
+
+mod aio;
+mod discover;
+mod error;
+mod header;
+mod ogr;
+mod record;
+mod writer;
+
+pub use discover::{DiscoverOptions, Symlinks, glob_base, is_glob, read_file_list, scan_dir, scan_glob};
+pub use error::LasBoundsError;
+pub use record::{BoundsRecord, DuplicateKey, Flightline, HeaderCache, ReadOptions, RecordResult, StageTimes, read_bounds,
+                 read_header, read_records};
+pub use writer::{BoundsWriter, Elevation, FlightlineMode, LAYER_NAME, MultiWriter, OgrOptions, OgrWriter, output_driver};
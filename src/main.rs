@@ -1,169 +1,1464 @@
 extern crate clap;
+extern crate env_logger;
 extern crate gdal;
+extern crate indicatif;
+extern crate las_bounds;
+#[macro_use]
+extern crate log;
+extern crate toml;
 
 
-use las::reader::Read;
-use clap::App;
+use clap::{App, AppSettings, SubCommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::time::{Duration, Instant};
 use std::path::{Path, PathBuf};
-use las::Reader;
+use std::process;
 use std::result::Result;
-use std::error::Error;
-use gdal::vector::{Driver, Dataset, Layer, FieldValue, Geometry, OGRFieldType, OGRwkbGeometryType};
-use gdal::spatial_ref::SpatialRef;
-use std::fmt;
+use std::sync::Arc;
+use gdal::spatial_ref::{SpatialRef, CoordTransform};
+use las_bounds::{BoundsWriter, DiscoverOptions, Elevation, FlightlineMode, HeaderCache, LasBoundsError, MultiWriter, OgrOptions,
+                 OgrWriter, ReadOptions, RecordResult, StageTimes, Symlinks, fields};
+use las_bounds::cache::Cache;
+use las_bounds::crs::{self, Crs};
+use las_bounds::expr;
+use las_bounds::fields::{PathFormat, PathMode};
+use las_bounds::csv::{CsvOptions, CsvWriter};
+use las_bounds::density::DensityRasterWriter;
+use las_bounds::diff;
+use las_bounds::footprint::{self, FootprintOptions};
+use las_bounds::input::InputFormat;
+use las_bounds::json::{self, RunSummary, Status};
+use las_bounds::kml::{self as kml_output, KmlOptions, KmlWriter};
+use las_bounds::merge;
+use las_bounds::{remote, s3};
+use las_bounds::scan::ScanOptions;
+use las_bounds::report::ReportWriter;
+use las_bounds::stac::StacWriter;
+use las_bounds::stats::ArchiveStats;
+use las_bounds::tiles;
+use las_bounds::validate;
+use las_bounds::watch;
+use las_bounds::xyz::XyzOptions;
 
 
-enum LasBoundsError {
-    GdalError(gdal::errors::Error),
-    IOError(std::io::Error),
-    LASError(las::Error),
-    Custom(String)
+/// Exit status of a run, distinct per outcome so that scripts can branch on it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExitCode {
+    Success = 0,
+    /// Some files failed with --skip-errors, the others were written.
+    PartialFailure = 1,
+    /// Invalid arguments or options that can not be used together.
+    Usage = 2,
+    /// Output could not be created or written.
+    Output = 3,
+    /// Inputs could not be listed or read.
+    Input = 4,
+    /// Files failed a check, invalid in validate or not in CRS of --require-crs.
+    Rejected = 5
 }
 
-impl fmt::Debug for LasBoundsError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+const EXIT_CODES: &str = "EXIT CODES:
+    0    Success
+    1    Some files failed with --skip-errors, the others were written
+    2    Invalid arguments
+    3    Output could not be created or written
+    4    Inputs could not be listed or read
+    5    Files failed a check, invalid in validate or not in CRS of --require-crs";
+
+/// Error of a run with its exit code.
+struct Failure {
+    code: ExitCode,
+    error: LasBoundsError
+}
+
+/// Errors not marked with their stage are argument errors, except I/O and LAS errors of reading inputs.
+impl From<LasBoundsError> for Failure {
+    fn from(error: LasBoundsError) -> Self {
+        let code = match error {
+            LasBoundsError::IOError(_) | LasBoundsError::LASError(_) => ExitCode::Input,
+            _ => ExitCode::Usage
+        };
+        Failure { code, error }
     }
 }
 
-impl fmt::Display for LasBoundsError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::GdalError(_e) => write!(f, "GdalError: TODO"),
-            Self::IOError(e) => write!(f, "IOError: {}", e),
-            Self::LASError(e) => write!(f, "LASError: {}", e),
-            Self::Custom(s) => write!(f, "Custom: {}", s)
+impl From<String> for Failure {
+    fn from(s: String) -> Self {
+        LasBoundsError::from(s).into()
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(error: io::Error) -> Self {
+        LasBoundsError::from(error).into()
+    }
+}
+
+impl From<gdal::errors::Error> for Failure {
+    fn from(error: gdal::errors::Error) -> Self {
+        LasBoundsError::from(error).into()
+    }
+}
+
+/// Marks errors with the exit code of their stage.
+trait OrExit<T> {
+    fn or_exit(self, code: ExitCode) -> Result<T, Failure>;
+}
+
+impl<T, E: Into<LasBoundsError>> OrExit<T> for Result<T, E> {
+    fn or_exit(self, code: ExitCode) -> Result<T, Failure> {
+        self.map_err(|e| Failure { code, error: e.into() })
+    }
+}
+
+fn fail<T>(code: ExitCode, message: String) -> Result<T, Failure> {
+    Err(Failure { code, error: message.into() })
+}
+
+
+fn build_app<'a, 'b>() -> clap::App<'a, 'b> {
+
+    App::new("las-bounds")
+    .version("0.0.0")
+    .author("nemq")
+    .about("Indexes LAS/LAZ archives: bounds of files in OGR vector datasets, archive statistics, validation and comparison of indexes.")
+    .setting(AppSettings::SubcommandRequiredElseHelp)
+    .setting(AppSettings::VersionlessSubcommands)
+    .after_help(EXIT_CODES)
+    .arg(clap::Arg::from_usage("-v, --verbose...    'More detailed output, -v for info and -vv for debug messages.'")
+        .global(true))
+    .arg(clap::Arg::from_usage("-q, --quiet    'Print only errors.'")
+        .global(true))
+    .subcommand(SubCommand::with_name("index")
+        .about("Indexes LAS/LAZ files, writing bounds of each file into an OGR vector dataset (ESRI Shapefile by default).")
+        .arg(clap::Arg::from_usage("[DIRECTORY]...   'Directories containing LAS/LAZ files or EPT datasets, http(s) URLs of LAS/LAZ files, or glob patterns such as \"data/**/tile_*.las\" (quote them to avoid shell expansion).'")
+            .required_unless_one(&["files-from", "s3"]))
+        .arg(clap::Arg::from_usage("--schema [SCHEMA]    'Attribute set, pdal-tindex writes fields of PDAL tindex (location, srs, modified, created).'")
+            .possible_values(&["default", "pdal-tindex"])
+            .default_value("default"))
+        .arg(clap::Arg::from_usage("--fields [NAMES]    'Comma separated attributes to write instead of the schema, e.g. name,path,point_count,z_min,srs.'")
+            .use_delimiter(true))
+        .arg(clap::Arg::from_usage("--field [NAME=EXPR]...    'Computed attribute, e.g. \"area_ha = area / 10000\", expressions have arithmetic, comparisons, text in single quotes and cond ? a : b.'")
+            .number_of_values(1))
+        .arg(clap::Arg::from_usage("--path-mode [MODE]    'Value of the path field: absolute path, path relative to the output directory, file name or file:// URI (by default the path as found).'")
+            .possible_values(&["absolute", "relative", "filename", "uri"]))
+        .args_from_usage("--tileindex-field [NAME]    'Write only the file path in the named field, e.g. location, as gdaltindex and MapServer tile indexes.'")
+        .args_from_usage("--duplicates    'Write duplicate_of attribute with the smallest path of files having the same header bounds, point count, project GUID and --checksum. All files are read before writing.'")
+        .args_from_usage("--source-dir    'Write source_dir attribute with the input directory of each file.'")
+        .args_from_usage("--provenance    'Write system_id and software attributes with system identifier and generating software of LAS headers.'")
+        .args_from_usage("--src-crs    'Write src_crs attribute with CRS detected in each file, EPSG code or WKT name, also when all are written in one output CRS.'")
+        .args_from_usage("--waveform    'Write has_waveform attribute, 1 for files with waveform point formats (4, 5, 9, 10) or waveform packet VLRs.'")
+        .args_from_usage("--project-id    'Write project_id attribute with project ID GUID of LAS headers, unset when it is all zeros.'")
+        .arg(clap::Arg::from_usage("--checksum [ALGORITHM]    'Write checksum attribute with hex digest of the file content, for verifying copies against the index.'")
+            .possible_values(&["md5", "sha256", "xxhash"]))
+        .args_from_usage("--file-date    'Write file_date attribute with creation date of LAS headers, ISO 8601.'")
+        .arg(clap::Arg::from_usage("--file-date-mtime    'Use modification date of the file for file_date when the header date is zero.'")
+            .requires("file-date"))
+        .args_from_usage("--extra-dims    'Write extra_dims attribute with name:type of extra dimensions from the Extra Bytes VLR, e.g. Amplitude:uint16.'")
+        .arg(clap::Arg::from_usage("--s3 [URL]...    'Index LAS/LAZ objects under s3://bucket/prefix, credentials are taken from the AWS environment.'")
+            .number_of_values(1))
+        .args_from_usage("--files-from [PATH]    'Read newline separated list of LAS/LAZ paths or http(s) URLs from the file, - for stdin.'")
+        .args_from_usage("-e, --epsg [num]    'EPSG code of LAS coordinate system, used when files carry no CRS VLR.'")
+        .args_from_usage("-t, --t_srs [EPSG]    'Reproject bounds to the target EPSG code, e.g. 4326 or EPSG:4326.'")
+        .args_from_usage("-o, --output [PATH]    'Output path (defaults to DIRECTORY with extension of the output format, required for several directories), or PostGIS connection string such as \"PG:host=db dbname=lidar\".'")
+        .arg(clap::Arg::from_usage("--kml-altitude [MODE]    'Placement of KML polygons: clamped to the ground or at maximum Z of the file (height above sea level in meters).'")
+            .possible_values(&["clamp", "absolute"])
+            .default_value("clamp"))
+        .arg(clap::Arg::from_usage("--kml-line-color [RRGGBBAA]    'Outline color of KML polygons.'")
+            .default_value("ff0000ff"))
+        .arg(clap::Arg::from_usage("--kml-fill-color [RRGGBBAA]    'Fill color of KML polygons.'")
+            .default_value("ff000040"))
+        .arg(clap::Arg::from_usage("--kml-line-width [WIDTH]    'Outline width of KML polygons in pixels.'")
+            .default_value("2"))
+        .args_from_usage("--union-layer [NAME]    'Also write layer of the given name with dissolved union of all footprints, the project outline.'")
+        .args_from_usage("--detect-gaps    'Also write gaps layer with holes in the coverage, parts of its convex hull or --aoi not covered by any file.'")
+        .args_from_usage("--aoi [PATH]    'Vector dataset with area of interest for --detect-gaps, in the output CRS.'")
+        .args_from_usage("--detect-overlaps    'Also write overlaps layer with intersections of footprint pairs, their file names and overlap area.'")
+        .args_from_usage("--hexbin [SIZE]    'Also write hexbin layer with point counts and density in hexagons of the side length in input CRS units. Appending replaces it with counts of files written in the run.'")
+        .arg(clap::Arg::from_usage("--per-flightline [MODE]    'Also write flightlines layer with footprint of points of each point source ID, per file or dissolved across files of the run.'")
+            .possible_values(&["file", "survey"]))
+        .args_from_usage("--density-raster [PATH]    'Also write GeoTIFF with number of points per --cell across all files read in the run, in CRS of the files.'")
+        .args_from_usage("--report [PATH]    'Also write single HTML page with map of footprints colored by density, sortable table of attributes and summary statistics. The map loads Leaflet and tiles from the web, offline only table and statistics are shown.'")
+        .args_from_usage("--min-density [DENSITY]    'Write density_ok attribute, 0 for files with fewer points per unit of area (highlighted in KML).'")
+        .args_from_usage("--max-density [DENSITY]    'Write density_ok attribute, 0 for files with more points per unit of area (highlighted in KML).'")
+        .args_from_usage("--min-points [N]    'Write suspect attribute, 1 for files with no points or fewer than N, and list them after the run.'")
+        .args_from_usage("--layer-per-dir    'Write files of each subdirectory of the input directory into a layer named after it, files directly in the input directory into --layer.'")
+        .args_from_usage("--require-crs [EPSG]    'Fail when any file is in other CRS than the EPSG code, listing such files (with --skip-errors they are left out).'")
+        .args_from_usage("--buffer [DISTANCE]    'Expand footprints by the distance in output CRS units, e.g. to include points on tile edges in clipping masks.'")
+        .args_from_usage("--3d    'Write 2.5D polygons (PolygonZ), with Z of vertices from header Z range of the file.'")
+        .arg(clap::Arg::from_usage("--z-value [STAT]    'Z of vertices of --3d polygons.'")
+            .possible_values(&["min", "max", "mid"])
+            .default_value("min"))
+        .args_from_usage("--split-by-crs    'Write files in each CRS into a layer named after it, e.g. bounds_epsg_2180, instead of failing when files are in several CRSs.'")
+        .args_from_usage("--layer [NAME]    'Name of the layer (bounds by default), added to existing GeoPackage or PostGIS output having other layers, so several indexes can share one dataset.'")
+        .arg(clap::Arg::from_usage("-f, --format [FORMAT]    'Output format: SHP, GPKG, GeoJSON, CSV (attributes with WKT geometry column), GeoParquet, FlatGeobuf (with spatial index), KML, KMZ or any OGR driver name.'")
+            .default_value("SHP"))
+        .arg(clap::Arg::from_usage("--dsco [NAME=VALUE]...    'Dataset creation option passed to the OGR driver.'")
+            .number_of_values(1))
+        .arg(clap::Arg::from_usage("--lco [NAME=VALUE]...    'Layer creation option passed to the OGR driver.'")
+            .number_of_values(1))
+        .arg(clap::Arg::from_usage("--transaction-size [N]    'Features written per transaction, for drivers supporting them (GPKG, PostGIS). 0 commits each feature.'")
+            .default_value("10000"))
+        .arg(clap::Arg::from_usage("--footprint [MODE]    'Footprint geometry: header bounding box, convex hull, concave hull (alpha shape) or occupancy grid of points.'")
+            .possible_values(&["bbox", "hull", "concave", "grid"])
+            .default_value("bbox"))
+        .arg(clap::Arg::from_usage("--alpha [DIST]    'Maximum circumradius of triangles forming concave footprint, in LAS coordinate units.'")
+            .required_if("footprint", "concave"))
+        .arg(clap::Arg::from_usage("--cell [SIZE]    'Cell size of occupancy grid footprint and --density-raster, in LAS coordinate units.'")
+            .required_if("footprint", "grid"))
+        .arg(clap::Arg::from_usage("--closing [CELLS]    'Fill gaps of occupancy grid up to the given number of cells.'")
+            .default_value("0"))
+        .arg(clap::Arg::from_usage("--classes [CLASS]...    'Compute footprints from points of the classification code only, e.g. 2 for ground, repeat for more classes. Files without such points have empty footprints.'")
+            .number_of_values(1))
+        .args_from_usage("--simplify [TOLERANCE]    'Simplify concave and grid footprints with Douglas-Peucker algorithm, removing vertices within the tolerance.'")
+        .arg(clap::Arg::from_usage("--thin [N]    'Use only every N-th point when scanning points.'")
+            .default_value("1"))
+        .args_from_usage("--gps-time    'Scan points for GPS time range.'")
+        .args_from_usage("--point-sources    'Scan points for minimum and maximum point source ID and number of distinct IDs, the flightlines covering the file. With --thin the IDs are sampled.'")
+        .arg(clap::Arg::from_usage("--class-stats [MODE]    'Scan points for per class counts, written as field per class or JSON field.'")
+            .possible_values(&["fields", "json"]))
+        .arg(clap::Arg::from_usage("--vlrs [MODE]    'Write vlr_count and evlr_count, with json also vlrs attribute listing user ID, record ID and description of each VLR and EVLR.'")
+            .possible_values(&["counts", "json"]))
+        .args_from_usage("--bbox-density    'Compute point density from header bounds area instead of footprint area.'")
+        .args_from_usage("--verify    'Scan all points to verify header bounds, writing bounds_ok and actual bounds of points.'")
+        .args_from_usage("--fix-headers    'Rewrite header bounds of LAS files in place when they do not match bounds of points, implies --verify.'")
+        .args_from_usage("--backup    'Copy files to .bak before --fix-headers changes them.'")
+        .args_from_usage("--verify-returns    'Scan all points to verify header point counts by return.'")
+        .arg(clap::Arg::from_usage("-j, --threads [N]    'Number of files read in parallel, 0 for number of CPUs.'")
+            .default_value("0"))
+        .args_from_usage("--io-concurrency [N]    'Number of header reads in flight, independent of --threads, for high-latency storage such as NFS or SMB mounts. Used for headers of local LAS/LAZ files.'")
+        .args_from_usage("--stac [DIR]    'Write static STAC catalog into the directory, Item with point cloud extension for each file and Collection of them.'")
+        .arg(clap::Arg::from_usage("--stac-only    'Write only STAC Items, no vector dataset.'")
+            .requires("stac"))
+        .arg(clap::Arg::from_usage("--skip-indexed    'With --append, do not read files already in the output at all, to resume interrupted runs.'")
+            .requires("append"))
+        .args_from_usage("--cache [PATH]    'JSON file caching point scan results by path, size and modification time, unchanged files are not scanned again.'")
+        .args_from_usage("--config [PATH]    'TOML file with defaults of epsg, t_srs, format, schema, fields, exclude and threads options (las-bounds.toml in the working directory if present).'")
+        .args_from_usage("--dry-run    'Discover files, read their headers and print what would be written, without creating any output.'")
+        .args_from_usage("--append    'Append to existing output dataset, files already in the index are skipped.'")
+        .args_from_usage("--overwrite    'Replace existing output dataset.'")
+        .arg(clap::Arg::from_usage("--log-format [FORMAT]    'Per file log format, json emits an object per processed file.'")
+            .possible_values(&["text", "json"])
+            .default_value("text"))
+        .args_from_usage("--log-file [PATH]    'Write JSON log to the file instead of stderr.'")
+        .arg(clap::Arg::from_usage("--json    'Print JSON document with files written, skipped and failed, output path, union of bounds and duration to stdout at the end, nothing else is printed there.'")
+            .conflicts_with("dry-run"))
+        .arg(clap::Arg::from_usage("--ndjson    'Print JSON line per file to stdout as it is processed, with path, status, duration, bounds and point count or error.'")
+            .conflicts_with_all(&["dry-run", "json"]))
+        .args_from_usage("--timing    'Print time spent discovering files, reading headers, scanning points, computing footprints and writing to stderr at the end.'")
+        .args_from_usage("--no-progress    'Print a line per file instead of progress bar.'")
+        .args_from_usage("--skip-errors    'Continue with remaining files when a file fails, report failures at the end.'")
+        .args_from_usage("-r, --recursive    'Scan subdirectories recursively.'")
+        .args_from_usage("--max-depth [num]    'Maximum depth of recursive scan, 1 being DIRECTORY itself (implies --recursive).'")
+        .arg(clap::Arg::from_usage("--follow-symlinks    'Follow links to directories, skipping cycles and files linked more than once.'")
+            .conflicts_with("no-follow-symlinks"))
+        .args_from_usage("--no-follow-symlinks    'Ignore symbolic links, by default links to files are indexed but links to directories are not followed.'")
+        .args_from_usage("--xyz    'Index ASCII point files (.xyz, .txt, .csv) too.'")
+        .args_from_usage("--ply    'Index PLY point files (.ply) too.'")
+        .arg(clap::Arg::from_usage("--xyz-columns [X,Y,Z]    'Column numbers of coordinates in ASCII point files, counted from 1.'")
+            .default_value("1,2,3"))
+        .args_from_usage("--xyz-delimiter [CHAR]    'Column delimiter of ASCII point files, any whitespace, comma or semicolon by default.'")
+        .arg(clap::Arg::from_usage("--xyz-skip [LINES]    'Number of header lines skipped in ASCII point files.'")
+            .default_value("0"))
+        .arg(clap::Arg::from_usage("--exclude [GLOB]...    'Skip files and directories matching the pattern by name or path relative to DIRECTORY, e.g. \"*_old\".'")
+            .number_of_values(1)))
+    .subcommand(SubCommand::with_name("watch")
+        .about("Indexes DIRECTORY and keeps the output up to date as LAS/LAZ files arrive, change or are deleted.")
+        .args_from_usage("<DIRECTORY>    'Directory to watch.'")
+        .args_from_usage("-o, --output <PATH>    'Output dataset, created or appended to.'")
+        .arg(clap::Arg::from_usage("-f, --format [FORMAT]    'Output format supporting updates: GPKG, SHP or OGR driver name.'")
+            .default_value("GPKG"))
+        .args_from_usage("-e, --epsg [num]    'EPSG code of LAS coordinate system, used when files carry no CRS VLR.'")
+        .args_from_usage("-r, --recursive    'Watch subdirectories too.'"))
+    .subcommand(SubCommand::with_name("tiles")
+        .about("Writes regular grid of tiles covering the extent of LAS/LAZ files in DIRECTORY, with files intersecting each tile.")
+        .args_from_usage("<DIRECTORY>    'Directory containing LAS/LAZ files.'")
+        .args_from_usage("--size <SIZE>    'Tile size in CRS units, tiles are aligned to its multiples.'")
+        .args_from_usage("-o, --output <PATH>    'Output dataset with tiles layer.'")
+        .arg(clap::Arg::from_usage("-f, --format [FORMAT]    'Output format: GPKG, SHP or OGR driver name.'")
+            .default_value("GPKG"))
+        .args_from_usage("-e, --epsg [num]    'EPSG code of LAS coordinate system, used when files carry no CRS VLR.'")
+        .args_from_usage("-r, --recursive    'Include subdirectories.'")
+        .args_from_usage("--overwrite    'Replace existing output.'"))
+    .subcommand(SubCommand::with_name("diff")
+        .about("Reports files added, removed and changed (bounds or point count) between two indexes, e.g. of delivery updates.")
+        .args_from_usage("<OLD>    'Index of the earlier run.'")
+        .args_from_usage("<NEW>    'Index of the later run.'")
+        .args_from_usage("-l, --layer [NAME]    'Bounds layer in both indexes, bounds or the only layer by default.'")
+        .arg(clap::Arg::from_usage("--tolerance [DIST]    'Bounds differing by at most the distance in CRS units are the same.'")
+            .default_value("0"))
+        .args_from_usage("-o, --output [PATH]    'Also write the differences as JSON file.'"))
+    .subcommand(SubCommand::with_name("merge")
+        .about("Combines bounds layers of several indexes into one, with union of their fields and one feature per path.")
+        .args_from_usage("<INPUT>...    'Indexes to merge, of files with the same path the first one is kept.'")
+        .args_from_usage("-o, --output <PATH>    'Output dataset with bounds layer.'")
+        .arg(clap::Arg::from_usage("-f, --format [FORMAT]    'Output format: GPKG, SHP or OGR driver name.'")
+            .default_value("GPKG"))
+        .args_from_usage("-l, --layer [NAME]    'Bounds layer in the inputs, bounds or the only layer by default.'")
+        .args_from_usage("--t_srs [EPSG]    'Reproject footprints to the EPSG code, by default to CRS of the first input.'")
+        .args_from_usage("--overwrite    'Replace existing output.'"))
+    .subcommand(SubCommand::with_name("validate")
+        .about("Checks LAS/LAZ files in DIRECTORY for spec violations: point format, point count against file size, CRS VLR required by LAS 1.4 and zero scale factors.")
+        .args_from_usage("<DIRECTORY>    'Directory containing LAS/LAZ files.'")
+        .args_from_usage("-o, --output [PATH]    'Also write validation layer with bounding box, valid flag and violations of each file.'")
+        .arg(clap::Arg::from_usage("-f, --format [FORMAT]    'Output format: GPKG, SHP or OGR driver name.'")
+            .default_value("GPKG"))
+        .args_from_usage("-r, --recursive    'Include subdirectories.'")
+        .args_from_usage("--overwrite    'Replace existing output.'"))
+    .subcommand(SubCommand::with_name("stats")
+        .about("Prints summary of LAS/LAZ files in DIRECTORY: total points and area, density range, CRSs, LAS versions and extent.")
+        .args_from_usage("<DIRECTORY>    'Directory containing LAS/LAZ files.'")
+        .args_from_usage("-o, --output [PATH]    'Also write the summary as JSON file.'")
+        .args_from_usage("-r, --recursive    'Include subdirectories.'"))
+}
+
+/// Returns CRS of the first file that declares one.
+fn detect_crs(paths: &[PathBuf]) -> Result<Option<Crs>, LasBoundsError> {
+
+    for p in paths.iter().filter(|p| InputFormat::of(p).has_header()) {
+        if let Some(crs) = crs::detect_crs(&las_bounds::read_header(p)?) {
+            debug!("CRS {} detected in {}", crs, p.to_string_lossy());
+            return Ok(Some(crs));
         }
     }
+
+    Ok(None)
+}
+
+/// CRS detected in each file whose header was read into `headers`, see [`HeaderCache::read_all`].
+fn file_crss(paths: &[PathBuf], headers: &HeaderCache) -> Vec<(PathBuf, Option<Crs>)> {
+
+    paths.iter()
+        .filter_map(|p| headers.with(p, crs::detect_crs).map(|crs| (p.clone(), crs)))
+        .collect()
+}
+
+/// CRSs detected in files with their number and first file, in order of first occurrence.
+fn detect_crss(file_crss: &[(PathBuf, Option<Crs>)]) -> Vec<(Crs, usize, PathBuf)> {
+
+    let mut crss: Vec<(Crs, usize, PathBuf)> = Vec::new();
+    for (p, crs) in file_crss {
+        let crs = match crs {
+            Some(crs) => crs.clone(),
+            None => continue
+        };
+        match crss.iter_mut().find(|(c, _, _)| *c == crs) {
+            Some((_, count, _)) => *count += 1,
+            None => {
+                debug!("CRS {} detected in {}", crs, p.to_string_lossy());
+                crss.push((crs, 1, p.clone()));
+            }
+        }
+    }
+
+    crss
+}
+
+/// Files whose CRS differs from the required EPSG code, with the detected CRS, see [`file_crss`]. Files
+/// without CRS are taken to be in the `default` EPSG code if given.
+fn crs_mismatches(file_crss: &[(PathBuf, Option<Crs>)], required: u32, default: Option<u32>)
+    -> Result<Vec<(PathBuf, Option<Crs>)>, LasBoundsError> {
+
+    let required_srs = SpatialRef::from_epsg(required)?;
+    let mut same = HashMap::new();
+    let mut mismatches = Vec::new();
+    for (p, crs) in file_crss {
+        let ok = match crs {
+            Some(Crs::Epsg(code)) => *code == required,
+            Some(crs) => match same.get(crs) {
+                Some(&ok) => ok,
+                None => *same.entry(crs.clone()).or_insert(crs.is_same(&required_srs)?)
+            },
+            None => default == Some(required)
+        };
+        if !ok {
+            mismatches.push((p.clone(), crs.clone()));
+        }
+    }
+
+    Ok(mismatches)
 }
 
+/// Prints what would be written without creating the output, after reading headers of all files.
+fn print_dry_run(paths: &[PathBuf], fields: &[fields::Field], driver_name: &str, out_path: &Path, layer_name: &str,
+                 srs: Option<&SpatialRef>) -> Result<(), LasBoundsError> {
+
+    let mut points = 0;
+    let mut failed = 0;
+    for p in paths.iter().filter(|p| remote::is_url(p) || InputFormat::of(p).has_header()) {
+        match las_bounds::read_header(p) {
+            Ok(header) => points += header.number_of_points(),
+            Err(e) => {
+                println!("Unreadable: {}: {}", p.to_string_lossy(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("Files: {} ({} unreadable), {} points in headers", paths.len(), failed, points);
+    println!("Output: {} ({})", out_path.to_string_lossy(), driver_name);
+    println!("Layer: {}", layer_name);
+    println!("CRS: {}", match srs {
+        Some(srs) => srs.to_proj4()?,
+        None => "unknown".to_string()
+    });
+    println!("Fields:");
+    for field in fields {
+        println!("  {} ({:?})", field.name, field.field_type);
+    }
+
+    Ok(())
+}
+
+fn parse_epsg(s: &str) -> Result<u32, LasBoundsError> {
+
+    let code = s.trim_start_matches("EPSG:").trim_start_matches("epsg:");
+    code.parse::<u32>().map_err(|_| format!("Invalid EPSG code: {}", s).into())
+}
+
+fn parse_density(s: Option<&str>) -> Result<Option<f64>, LasBoundsError> {
+
+    match s {
+        Some(s) => Ok(Some(s.parse::<f64>().ok().filter(|&d| d >= 0.0).ok_or(format!("Invalid density: {}", s))?)),
+        None => Ok(None)
+    }
+}
+
+fn parse_thin(s: &str) -> Result<usize, LasBoundsError> {
+
+    match s.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Invalid thinning factor: {}", s).into())
+    }
+}
+
+fn parse_classes(values: &[String]) -> Result<Vec<u8>, LasBoundsError> {
+
+    values.iter()
+        .map(|s| s.parse::<u8>().map_err(|_| format!("Invalid classification code: {}", s).into()))
+        .collect()
+}
+
+fn parse_xyz_options(matches: &clap::ArgMatches) -> Result<XyzOptions, LasBoundsError> {
+
+    let columns_val = matches.value_of("xyz-columns").unwrap();
+    let columns: Vec<usize> = columns_val.split(',')
+                                         .map(|s| s.trim().parse::<usize>().ok().filter(|&n| n > 0).map(|n| n - 1))
+                                         .collect::<Option<_>>()
+                                         .filter(|columns: &Vec<usize>| columns.len() == 3)
+                                         .ok_or_else(|| format!("Invalid XYZ columns: {}", columns_val))?;
+
+    let delimiter = match matches.value_of("xyz-delimiter") {
+        None => None,
+        Some("\\t") => Some('\t'),
+        Some(s) if s.chars().count() == 1 => s.chars().next(),
+        Some(s) => return Err(format!("Invalid XYZ delimiter, expected single character: {}", s).into())
+    };
+
+    Ok(XyzOptions {
+        columns: (columns[0], columns[1], columns[2]),
+        delimiter,
+        skip_lines: matches.value_of("xyz-skip").unwrap()
+                           .parse::<usize>().map_err(|_| "Invalid number of XYZ header lines".to_string())?
+    })
+}
+
+fn init_logger(matches: &clap::ArgMatches) {
+
+    let level = match (matches.is_present("quiet"), matches.occurrences_of("verbose")) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, _) => log::LevelFilter::Debug
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .init();
+}
+
+/// Per file progress output, progress bar or plain lines for CI logs.
+enum Progress {
+    Bar(ProgressBar, Instant),
+    Lines,
+    Hidden
+}
+
+impl Progress {
+
+    fn new(total_bytes: u64, plain: bool, quiet: bool) -> Self {
+
+        if quiet {
+            return Progress::Hidden;
+        }
+        if plain {
+            return Progress::Lines;
+        }
+
+        let bar = ProgressBar::new(total_bytes);
+        bar.set_style(ProgressStyle::default_bar()
+                          .template("{elapsed_precise} [{bar:30}] {bytes}/{total_bytes} {binary_bytes_per_sec} ETA {eta} {msg}")
+                          .progress_chars("=> "));
+        Progress::Bar(bar, Instant::now())
+    }
+
+    /// Reports start of processing of the i-th file.
+    fn file(&self, i: usize, total: usize, path: &Path, size: u64) {
+
+        match self {
+            Progress::Bar(bar, start) => {
+                let elapsed = start.elapsed().as_secs_f64();
+                let rate = if elapsed > 0.0 { i as f64 / elapsed } else { 0.0 };
+                let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                bar.set_message(&format!("[{}/{}] {:.1} files/s {}", i + 1, total, rate, name));
+                bar.inc(size);
+            },
+            Progress::Lines => println!("[{}/{}] {}", i + 1, total, path.to_string_lossy()),
+            Progress::Hidden => ()
+        }
+    }
+
+    fn println(&self, msg: &str) {
 
-impl Error for LasBoundsError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            Self::GdalError(_) => None,
-            Self::IOError(e) => Some(e),
-            Self::LASError(e) => Some(e),
-            Self::Custom(_) => None
+            Progress::Bar(bar, _) => bar.println(msg),
+            Progress::Lines => println!("{}", msg),
+            Progress::Hidden => ()
+        }
+    }
+
+    fn finish(&self) {
+
+        if let Progress::Bar(bar, _) = self {
+            bar.finish();
         }
     }
 }
 
-impl From<std::io::Error> for LasBoundsError {
-    fn from(error: std::io::Error) -> Self {
-        Self::IOError(error)
+/// Prints the result document of --json, bounds of files in several CRSs are left out.
+fn print_summary(mut summary: RunSummary, start: Instant, mixed_crs: bool) {
+
+    summary.duration = start.elapsed();
+    if mixed_crs {
+        summary.bounds = None;
     }
+    println!("{:#}", summary.to_json());
 }
 
-impl From<gdal::errors::Error> for LasBoundsError {
-    fn from(error: gdal::errors::Error) -> Self {
-        Self::GdalError(error)
+/// Time spent on stages of the run for --timing.
+#[derive(Default)]
+struct Timing {
+    /// Listing inputs and detecting their CRSs.
+    discovery: Duration,
+    /// Reading stages of all files, summed over reading threads.
+    reads: StageTimes,
+    /// Writing records and completing the outputs.
+    write: Duration
+}
+
+impl Timing {
+
+    fn add(&mut self, times: &StageTimes) {
+
+        self.reads.header += times.header;
+        self.reads.scan += times.scan;
+        self.reads.geometry += times.geometry;
+    }
+
+    /// Prints the breakdown to stderr, so it does not mix with --json and --ndjson output.
+    fn print(&self, total: Duration) {
+
+        eprintln!("Timing (header read, point scan and geometry summed over reading threads):");
+        let stages = [("discovery", self.discovery), ("header read", self.reads.header), ("point scan", self.reads.scan),
+                      ("geometry", self.reads.geometry), ("write", self.write), ("total", total)];
+        for (stage, duration) in &stages {
+            eprintln!("  {:<12} {:>10.3} s", stage, duration.as_secs_f64());
+        }
     }
 }
 
-impl From<las::Error> for LasBoundsError {
-    fn from(error: las::Error) -> Self {
-        Self::LASError(error)
+/// Destination of JSON log lines, `None` when JSON logging is disabled.
+fn json_log(matches: &clap::ArgMatches) -> Result<Option<Box<dyn Write>>, LasBoundsError> {
+
+    if matches.value_of("log-format") != Some("json") {
+        return Ok(None);
     }
+
+    let sink: Box<dyn Write> = match matches.value_of("log-file") {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stderr())
+    };
+    Ok(Some(sink))
 }
 
-impl From<String> for LasBoundsError {
-    fn from(s: String) -> Self {
-        Self::Custom(s)
+/// Paths listed by --files-from, read from stdin for `-`.
+fn files_from(list: &str) -> Result<Vec<PathBuf>, LasBoundsError> {
+
+    if list == "-" {
+        let stdin = io::stdin();
+        let paths = las_bounds::read_file_list(stdin.lock())?;
+        return Ok(paths);
     }
+
+    let file = File::open(list).map_err(|e| format!("Could not open file list {}: {}", list, e))?;
+    las_bounds::read_file_list(BufReader::new(file))
 }
 
-fn build_app<'a, 'b>() -> clap::App<'a, 'b> {
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+}
 
-    App::new("las-bounds")
-    .version("0.0.0")
-    .author("nemq")
-    .about("Generates bounds of LAS files and saves them in ESRI Shapefiles.")
-    .args_from_usage("<DIRECTORY>   'Directory containing LAS files.'")
-    .args_from_usage("-e, --epsg <num>    'EPSG code of LAS coordinate system.")
+fn values_of(matches: &clap::ArgMatches, name: &str) -> Vec<String> {
+
+    matches.values_of(name)
+           .map(|values| values.map(String::from).collect())
+           .unwrap_or_default()
+}
+
+/// Configuration file looked up in the working directory when there is no --config.
+const DEFAULT_CONFIG: &str = "las-bounds.toml";
+/// Options that may be set in the configuration file, by their long names.
+const CONFIG_KEYS: &[&str] = &["epsg", "t_srs", "format", "schema", "fields", "exclude", "threads"];
+
+/// Defaults of options from TOML configuration file, used for options not given on the command line.
+struct Config {
+    table: toml::value::Table
+}
+
+fn toml_string(value: &toml::Value) -> String {
+
+    match value {
+        toml::Value::String(s) => s.clone(),
+        value => value.to_string()
+    }
 }
 
+impl Config {
 
+    fn load(matches: &clap::ArgMatches) -> Result<Self, LasBoundsError> {
+
+        let path = match matches.value_of("config") {
+            Some(path) => PathBuf::from(path),
+            None if Path::new(DEFAULT_CONFIG).is_file() => PathBuf::from(DEFAULT_CONFIG),
+            None => return Ok(Config { table: toml::value::Table::new() })
+        };
+        let text = fs::read_to_string(&path).map_err(|e| format!("Could not read config {}: {}", path.to_string_lossy(), e))?;
+        let table: toml::value::Table = toml::from_str(&text)
+            .map_err(|e| format!("Invalid config {}: {}", path.to_string_lossy(), e))?;
+        info!("Using config {}", path.to_string_lossy());
+        for key in table.keys().filter(|key| !CONFIG_KEYS.contains(&key.as_str())) {
+            warn!("Unknown option in config {}: {}", path.to_string_lossy(), key);
+        }
 
-fn list_las(dir: &Path) -> Result<Vec<PathBuf>, LasBoundsError> {
+        Ok(Config { table })
+    }
 
-    let mut vec = Vec::new();
-    for path in dir.read_dir()?
-                   .filter_map(|entry| entry.ok().map(|entry| entry.path()))
-                   .filter(|path| path.extension().and_then(|ext| ext.to_str())
-                   .filter(|&ext| ext == "las").is_some()) {
+    /// Value given on the command line, then in the config, then the default of the option.
+    fn value_of(&self, matches: &clap::ArgMatches, name: &str) -> Option<String> {
 
-        vec.push(path);
+        match self.table.get(name) {
+            Some(value) if matches.occurrences_of(name) == 0 => Some(toml_string(value)),
+            _ => matches.value_of(name).map(String::from)
+        }
     }
 
-    Ok(vec)
+    /// Values given on the command line, or in the config as array or single value.
+    fn values_of(&self, matches: &clap::ArgMatches, name: &str) -> Vec<String> {
+
+        match self.table.get(name) {
+            Some(toml::Value::Array(values)) if matches.occurrences_of(name) == 0 => values.iter().map(toml_string).collect(),
+            Some(value) if matches.occurrences_of(name) == 0 => vec![toml_string(value)],
+            _ => values_of(matches, name)
+        }
+    }
 }
 
-fn read_bounds(las: &Path) -> Result<las::Bounds, LasBoundsError> {
+/// Writes tiling scheme covering header bounds of files in the directory.
+fn run_tiles(matches: &clap::ArgMatches) -> Result<(), Failure> {
+
+    let size = matches.value_of("size").unwrap();
+    let size = size.parse::<f64>().ok().filter(|&size| size > 0.0).ok_or(format!("Invalid tile size: {}", size))?;
+    let discover_options = DiscoverOptions {
+        max_depth: if matches.is_present("recursive") { std::usize::MAX } else { 1 },
+        ..Default::default()
+    };
+    let paths = las_bounds::scan_dir(Path::new(matches.value_of("DIRECTORY").unwrap()), &discover_options)
+        .or_exit(ExitCode::Input)?;
+
+    let mut files = Vec::new();
+    for p in paths.iter().filter(|p| InputFormat::of(p).has_header()) {
+        match las_bounds::read_header(p) {
+            Ok(header) => files.push((p.to_string_lossy().into_owned(), header.bounds())),
+            Err(e) => warn!("Could not read {}: {}", p.to_string_lossy(), e)
+        }
+    }
+    let srs = match (detect_crs(&paths)?, matches.value_of("epsg")) {
+        (Some(crs), _) => Some(crs.to_spatial_ref()?),
+        (None, Some(epsg)) => Some(SpatialRef::from_epsg(parse_epsg(epsg)?)?),
+        (None, None) => None
+    };
 
-    let reader = Reader::from_path(las)?;
-    let header = reader.header();
-    Ok(header.bounds())
+    let grid = tiles::tile_grid(&files, size)?;
+    info!("{} tiles cover {} files", grid.len(), files.len());
+    let driver_name = las_bounds::output_driver(matches.value_of("format").unwrap())?.0;
+    tiles::write_tiles(&driver_name, Path::new(matches.value_of("output").unwrap()), srs.as_ref(), &grid, size,
+                       matches.is_present("overwrite")).or_exit(ExitCode::Output)
 }
 
-fn create_shp (shp: &Path) -> Result<Dataset, LasBoundsError> {
-    
-    let driver = Driver::get("ESRI Shapefile")?;
-    let ds = driver.create(shp)?;
-    Ok(ds)
+/// Prints differences of the indexes, fails when they could not be read.
+fn run_diff(matches: &clap::ArgMatches) -> Result<(), Failure> {
+
+    let tolerance = matches.value_of("tolerance").unwrap();
+    let tolerance = tolerance.parse::<f64>().ok().filter(|&t| t >= 0.0).ok_or(format!("Invalid tolerance: {}", tolerance))?;
+    let layer = matches.value_of("layer");
+    let old = diff::read_index(Path::new(matches.value_of("OLD").unwrap()), layer).or_exit(ExitCode::Input)?;
+    let new = diff::read_index(Path::new(matches.value_of("NEW").unwrap()), layer).or_exit(ExitCode::Input)?;
+
+    let diff = diff::diff(&old, &new, tolerance);
+    println!("{}", diff.report());
+    if let Some(output) = matches.value_of("output") {
+        fs::write(output, format!("{:#}\n", diff.to_json())).or_exit(ExitCode::Output)?;
+    }
+    Ok(())
+}
+
+fn run_merge(matches: &clap::ArgMatches) -> Result<(), Failure> {
+
+    let inputs: Vec<&Path> = matches.values_of("INPUT").unwrap().map(Path::new).collect();
+    // Layer CRSs read from the inputs take coordinates in traditional order, so does the target.
+    let srs = match matches.value_of("t_srs") {
+        Some(s) => Some(Crs::Epsg(parse_epsg(s)?).to_spatial_ref()?),
+        None => None
+    };
+    let driver_name = las_bounds::output_driver(matches.value_of("format").unwrap())?.0;
+    let count = merge::merge(&inputs, matches.value_of("layer"), srs, &driver_name,
+                             Path::new(matches.value_of("output").unwrap()), matches.is_present("overwrite"))
+        .or_exit(ExitCode::Output)?;
+    info!("Merged {} features from {} indexes", count, inputs.len());
+    Ok(())
 }
 
-fn create_layer<'a>(ds: &'a mut Dataset, srs: Option<SpatialRef>) -> Result<&'a mut Layer, LasBoundsError> {
+/// Prints violations of invalid files and totals, fails when any file is invalid.
+fn run_validate(matches: &clap::ArgMatches) -> Result<(), Failure> {
+
+    let discover_options = DiscoverOptions {
+        max_depth: if matches.is_present("recursive") { std::usize::MAX } else { 1 },
+        ..Default::default()
+    };
+    let paths: Vec<PathBuf> = las_bounds::scan_dir(Path::new(matches.value_of("DIRECTORY").unwrap()), &discover_options)
+        .or_exit(ExitCode::Input)?
+        .into_iter()
+        .filter(|p| InputFormat::of(p) == InputFormat::Las)
+        .collect();
 
-    let layer = ds.create_layer_ext("bounds", srs.as_ref(), OGRwkbGeometryType::wkbPolygon)?;
+    let validations: Vec<_> = paths.iter().map(|p| validate::validate(p)).collect();
+    let invalid: Vec<_> = validations.iter().filter(|v| !v.is_valid()).collect();
+    for validation in &invalid {
+        println!("{}: {}", validation.path.to_string_lossy(), validation.violations.join("; "));
+    }
+    println!("{} files, {} valid, {} invalid", validations.len(), validations.len() - invalid.len(), invalid.len());
 
-    layer.create_defn_fields(&[
-        ("name", OGRFieldType::OFTString),
-        ("path", OGRFieldType::OFTString),
-    ])?;
+    if let Some(output) = matches.value_of("output") {
+        let headers = HeaderCache::default();
+        headers.read_all(&paths, 0, 0).or_exit(ExitCode::Input)?;
+        let srs = match detect_crss(&file_crss(&paths, &headers)).first() {
+            Some((crs, _, _)) => Some(crs.to_spatial_ref()?),
+            None => None
+        };
+        let driver_name = las_bounds::output_driver(matches.value_of("format").unwrap())?.0;
+        validate::write_validations(&driver_name, Path::new(output), srs.as_ref(), &validations,
+                                    matches.is_present("overwrite")).or_exit(ExitCode::Output)?;
+    }
 
-    Ok(layer)
+    if !invalid.is_empty() {
+        return fail(ExitCode::Rejected,
+                    format!("{} of {} files violate the LAS specification", invalid.len(), validations.len()));
+    }
+    Ok(())
 }
 
-fn write_bounds(las: &Path, layer: &mut Layer) ->Result<(), LasBoundsError> {
+/// Summarizes files from their headers, footprints are bounding boxes.
+fn run_stats(matches: &clap::ArgMatches) -> Result<(), Failure> {
 
-    let bounds = read_bounds(las)?;
-    let path = las.to_string_lossy().into_owned();
-    let filename = las.file_name().ok_or(format!("Could not get file name: {}", path))?.to_string_lossy().into_owned();
+    let discover_options = DiscoverOptions {
+        max_depth: if matches.is_present("recursive") { std::usize::MAX } else { 1 },
+        ..Default::default()
+    };
+    let paths = las_bounds::scan_dir(Path::new(matches.value_of("DIRECTORY").unwrap()), &discover_options)
+        .or_exit(ExitCode::Input)?;
 
-    layer.create_feature_fields(
-        Geometry::bbox(bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y)?,
-        &["name", "path"],
-        &[
-            FieldValue::StringValue(filename.into()),
-            FieldValue::StringValue(path.into())
-        ],
-    )?;
+    let read_options = ReadOptions::default();
+    let mut stats = ArchiveStats::default();
+    for p in &paths {
+        match las_bounds::read_bounds(p, &read_options) {
+            Ok(record) => stats.add(&record),
+            Err(e) => warn!("Could not read {}: {}", p.to_string_lossy(), e)
+        }
+    }
 
+    println!("{}", stats.report());
+    if let Some(output) = matches.value_of("output") {
+        fs::write(output, format!("{:#}\n", stats.to_json())).or_exit(ExitCode::Output)?;
+    }
     Ok(())
 }
 
-fn main() -> Result<(), LasBoundsError> {
+/// Indexes files already in the directory, then follows its changes until interrupted.
+fn run_watch(matches: &clap::ArgMatches) -> Result<(), Failure> {
+
+    // Notifications come with absolute paths, indexed paths have to match them.
+    let dir = fs::canonicalize(matches.value_of("DIRECTORY").unwrap())?;
+    let discover_options = DiscoverOptions {
+        max_depth: if matches.is_present("recursive") { std::usize::MAX } else { 1 },
+        ..Default::default()
+    };
+    let paths = las_bounds::scan_dir(&dir, &discover_options).or_exit(ExitCode::Input)?;
+
+    let mut options = OgrOptions::new(&las_bounds::output_driver(matches.value_of("format").unwrap())?.0,
+                                      matches.value_of("output").unwrap());
+    options.srs = match (detect_crs(&paths)?, matches.value_of("epsg")) {
+        (Some(crs), _) => Some(crs.to_spatial_ref()?),
+        (None, Some(epsg)) => Some(SpatialRef::from_epsg(parse_epsg(epsg)?)?),
+        (None, None) => None
+    };
+    options.append = true;
+    let read_options = ReadOptions::default();
+    let mut writer = OgrWriter::new(options, &fields::schema(&read_options)).or_exit(ExitCode::Output)?;
+
+    // Files that arrived while nothing was watching are indexed first.
+    for p in paths.iter().filter(|p| !writer.contains(&read_options.path_format.format(p))) {
+        match las_bounds::read_bounds(p, &read_options) {
+            Ok(record) => writer.write(&record).or_exit(ExitCode::Output)?,
+            Err(e) => warn!("Could not index {}: {}", p.to_string_lossy(), e)
+        }
+    }
+    writer.flush().or_exit(ExitCode::Output)?;
+
+    watch::watch(&dir, &mut writer, &read_options, &discover_options).or_exit(ExitCode::Output)
+}
+
+fn main() {
+
+    // Help and version are printed to stdout with success, argument errors exit with the usage code.
+    let matches = build_app().get_matches_safe().unwrap_or_else(|e| {
+        if e.use_stderr() {
+            eprintln!("{}", e.message);
+            process::exit(ExitCode::Usage as i32);
+        }
+        e.exit()
+    });
+
+    // Global flags are propagated to the subcommand, wherever they were given.
+    let (name, subcommand_matches) = matches.subcommand();
+    let subcommand_matches = subcommand_matches.unwrap_or(&matches);
+    init_logger(subcommand_matches);
+
+    let result = match name {
+        "index" => run_index(subcommand_matches),
+        "watch" => run_watch(subcommand_matches),
+        "tiles" => run_tiles(subcommand_matches),
+        "diff" => run_diff(subcommand_matches),
+        "merge" => run_merge(subcommand_matches),
+        "validate" => run_validate(subcommand_matches),
+        "stats" => run_stats(subcommand_matches),
+        _ => unreachable!("Unknown subcommand: {}", name)
+    };
+    let code = match result {
+        Ok(()) => ExitCode::Success,
+        Err(failure) => {
+            eprintln!("Error: {}", failure.error);
+            failure.code
+        }
+    };
+    process::exit(code as i32);
+}
+
+/// Indexes the inputs into the output dataset and the other requested outputs.
+fn run_index(matches: &clap::ArgMatches) -> Result<(), Failure> {
+
+    let start = Instant::now();
+    let config = Config::load(matches)?;
+
+    // Input directories with their patterns, base directory of a glob pattern stands for the pattern.
+    // URLs are single files.
+    let (urls, dir_vals): (Vec<&str>, Vec<&str>) = matches.values_of("DIRECTORY").into_iter().flatten()
+        .partition(|dir_val| remote::is_url(Path::new(dir_val)));
+    let inputs: Vec<(&str, PathBuf, bool)> = dir_vals.into_iter()
+        .map(|dir_val| {
+            let glob = !Path::new(dir_val).is_dir() && las_bounds::is_glob(dir_val);
+            let dir_path = if glob { las_bounds::glob_base(dir_val) } else { PathBuf::from(dir_val) };
+            (dir_val, dir_path, glob)
+        })
+        .collect();
+    let list_val = matches.value_of("files-from");
+
+    // Output is named after the only directory, or the file list when there is no directory.
+    let out_base = match (inputs.as_slice(), urls.is_empty() && !matches.is_present("s3")) {
+        ([(_, dir_path, _)], true) => Some(dir_path.clone()),
+        ([], true) => list_val.filter(|&list| list != "-").map(PathBuf::from),
+        _ => None
+    };
+    // Connection string selects the database driver.
+    let format = match matches.value_of("output") {
+        Some(output) if output.starts_with("PG:") => "PostGIS".to_string(),
+        _ => config.value_of(matches, "format").unwrap()
+    };
+    let (driver_name, extension) = las_bounds::output_driver(&format)?;
+    let out_path = match (matches.value_of("output"), extension, out_base) {
+        _ if matches.is_present("stac-only") => PathBuf::new(),
+        (Some(output), _, _) => PathBuf::from(output),
+        (None, Some(extension), Some(base)) if base.file_name().is_some() => base.with_extension(extension),
+        (None, Some(_), _) => return Err("Can not derive output name from the input, use --output".to_string().into()),
+        (None, None, _) => return Err(format!("Driver {} requires explicit --output", driver_name).into())
+    };
 
-    let app = build_app();
-    let matches = app.get_matches();
+    let max_depth = match matches.value_of("max-depth") {
+        Some(s) => s.parse::<usize>().map_err(|_| format!("Invalid max depth: {}", s))?,
+        None if matches.is_present("recursive") => std::usize::MAX,
+        None => 1
+    };
+    let symlinks = if matches.is_present("follow-symlinks") {
+        Symlinks::Follow
+    } else if matches.is_present("no-follow-symlinks") {
+        Symlinks::Skip
+    } else {
+        Symlinks::Files
+    };
+    let discover_options = DiscoverOptions {
+        max_depth,
+        exclude: config.values_of(matches, "exclude"),
+        symlinks,
+        xyz: matches.is_present("xyz"),
+        ply: matches.is_present("ply")
+    };
 
-    let dir_val = matches.value_of("DIRECTORY").unwrap();
-    let dir_path = Path::new(&dir_val);
-    let shp_path = dir_path.with_extension("shp");
+    let mut timing = Timing::default();
+    let discovery_start = Instant::now();
+
+    // Files found in several inputs are indexed once, under the first one.
+    let mut paths = Vec::new();
+    let mut sources = HashMap::new();
+    for (dir_val, dir_path, glob) in &inputs {
+        // Glob patterns control depth themselves, e.g. with `**`.
+        let found = if *glob {
+            las_bounds::scan_glob(dir_val, &discover_options).or_exit(ExitCode::Input)?
+        } else {
+            las_bounds::scan_dir(dir_path, &discover_options).or_exit(ExitCode::Input)?
+        };
+        for p in found {
+            if !sources.contains_key(&p) {
+                sources.insert(p.clone(), dir_path.clone());
+                paths.push(p);
+            }
+        }
+    }
+    paths.extend(urls.iter().map(PathBuf::from));
+    for s3_url in matches.values_of("s3").into_iter().flatten() {
+        paths.extend(s3::list_objects(s3_url).or_exit(ExitCode::Input)?);
+    }
+    if let Some(list) = list_val {
+        let mut seen: HashSet<PathBuf> = paths.iter().cloned().collect();
+        for p in files_from(list).or_exit(ExitCode::Input)? {
+            if seen.insert(p.clone()) {
+                paths.push(p);
+            }
+        }
+    }
+    debug!("Found {} files", paths.len());
+
+    // The output CRS is fixed before anything is written, so headers are read up front for CRS
+    // detection and kept for reading the bounds.
+    let threads = config.value_of(matches, "threads").unwrap()
+                         .parse::<usize>().map_err(|_| "Invalid number of threads".to_string())?;
+    let io_concurrency = match matches.value_of("io-concurrency") {
+        Some(s) => s.parse::<usize>().ok().filter(|&n| n > 0).ok_or(format!("Invalid I/O concurrency: {}", s))?,
+        None => 0
+    };
+    let headers = Arc::new(HeaderCache::default());
+    let header_start = Instant::now();
+    timing.reads.header += headers.read_all(&paths, threads, io_concurrency).or_exit(ExitCode::Input)?;
+    let header_elapsed = header_start.elapsed();
+    let mut crs_by_file = file_crss(&paths, &headers);
+
+    let epsg = match config.value_of(matches, "epsg") {
+        Some(s) => Some(parse_epsg(s)?),
+        None => None
+    };
+
+    // Deliveries in wrong CRS are rejected before anything is written, or left out with --skip-errors.
+    if let Some(required) = matches.value_of("require-crs") {
+        let required = parse_epsg(required)?;
+        let mismatches = crs_mismatches(&crs_by_file, required, epsg).or_exit(ExitCode::Input)?;
+        for (p, crs) in &mismatches {
+            warn!("{} is in {} instead of EPSG:{}", p.to_string_lossy(),
+                  crs.as_ref().map_or("unknown CRS".to_string(), |crs| crs.to_string()), required);
+        }
+        if !mismatches.is_empty() {
+            if !matches.is_present("skip-errors") {
+                return fail(ExitCode::Rejected, format!("{} files are not in EPSG:{}", mismatches.len(), required));
+            }
+            let excluded: HashSet<&PathBuf> = mismatches.iter().map(|(p, _)| p).collect();
+            paths.retain(|p| !excluded.contains(p));
+            crs_by_file.retain(|(p, _)| !excluded.contains(p));
+        }
+    }
+
+    // Files in several CRSs are not mixed in one layer, they are split into layers per CRS or reprojected.
+    let crss = detect_crss(&crs_by_file);
+    let mixed_crs = crss.len() > 1;
+    timing.discovery = discovery_start.elapsed().checked_sub(header_elapsed).unwrap_or_default();
+    let crs_list: Vec<String> = crss.iter()
+        .map(|(crs, count, first)| format!("  {}: {} files, e.g. {}", crs, count, first.to_string_lossy()))
+        .collect();
+    let text_output = matches!(driver_name.as_str(), "CSV" | "KML" | "KMZ");
+    if mixed_crs && (matches.is_present("stac") || matches.is_present("report") || text_output) {
+        let output = if matches.is_present("stac") {
+            "STAC"
+        } else if matches.is_present("report") {
+            "HTML report"
+        } else {
+            driver_name.as_str()
+        };
+        return Err(format!("Files are in several CRSs, which {} output does not support:\n{}", output, crs_list.join("\n")).into());
+    }
 
     let mut srs = None;
-    if let Some(epsg) = matches.value_of("epsg").and_then(|s| (s.parse::<u32>().ok())) {
-        srs = Some(SpatialRef::from_epsg(epsg)?);
+    if let [(crs, _, _)] = crss.as_slice() {
+        info!("Detected CRS: {}", crs);
+        srs = Some(crs.to_spatial_ref()?);
+    } else if let Some(epsg) = epsg {
+        info!("Using EPSG:{} for files without CRS", epsg);
+        srs = Some(Crs::Epsg(epsg).to_spatial_ref()?);
+    } else if !mixed_crs {
+        warn!("No CRS in files and no --epsg given, output will have no CRS");
     }
 
-    let mut ds = create_shp(&shp_path)?;
-    let mut layer = create_layer(&mut ds, srs)?;
+    // Nothing is created in dry run, STAC directory neither.
+    let dry_run = matches.is_present("dry-run");
+    let stac = match (matches.value_of("stac"), &srs) {
+        (Some(_), Some(_)) if dry_run => None,
+        (Some(dir), Some(srs)) => Some(StacWriter::new(dir, srs).or_exit(ExitCode::Output)?),
+        (Some(_), None) => return Err("STAC output requires known CRS of input files, use --epsg".to_string().into()),
+        (None, _) => None
+    };
+
+    // KML is always in WGS84, its writer transforms footprints itself.
+    let kml_srs = srs.clone();
 
-    let paths = list_las(dir_path)?;
-    for (i, p) in paths.iter().enumerate() {
-        println!("[{}/{}] {}", i + 1, paths.len(), p.to_string_lossy());
-        write_bounds(&p, &mut layer)?;
+    // GeoJSON is expected to be in WGS84, so it is the default target CRS for it.
+    let t_srs = config.value_of(matches, "t_srs");
+    let t_epsg = match &t_srs {
+        Some(s) => Some(parse_epsg(s)?),
+        None if driver_name == "GeoJSON" => Some(4326),
+        None => None
+    };
+
+    let split_by_crs = matches.is_present("split-by-crs");
+    if mixed_crs && !split_by_crs && t_epsg.is_none() {
+        return Err(format!("Files are in several CRSs, use --split-by-crs to write layer per CRS or --t_srs \
+                            to reproject them:\n{}", crs_list.join("\n")).into());
+    }
+    if mixed_crs {
+        info!("Files are in several CRSs:\n{}", crs_list.join("\n"));
+    }
+
+    let mut transform = None;
+    if let Some(t_epsg) = t_epsg {
+        match srs.take() {
+            Some(source) => {
+                let target = Crs::Epsg(t_epsg).to_spatial_ref()?;
+                debug!("Reprojecting bounds to EPSG:{}", t_epsg);
+                transform = Some(CoordTransform::new(&source, &target)?);
+                srs = Some(target);
+            },
+            // Each file is reprojected from its CRS.
+            None if mixed_crs => srs = Some(Crs::Epsg(t_epsg).to_spatial_ref()?),
+            None if t_srs.is_some() => return Err("Source CRS is unknown, can not reproject to --t_srs".to_string().into()),
+            None => ()
+        };
+    }
+
+    let footprint_options = FootprintOptions {
+        mode: matches.value_of("footprint").unwrap().parse()?,
+        alpha: match matches.value_of("alpha") {
+            Some(s) => s.parse::<f64>().map_err(|_| format!("Invalid alpha: {}", s))?,
+            None => std::f64::INFINITY
+        },
+        cell: match matches.value_of("cell") {
+            Some(s) => s.parse::<f64>().ok().filter(|&cell| cell > 0.0).ok_or(format!("Invalid cell size: {}", s))?,
+            None => 1.0
+        },
+        closing: matches.value_of("closing").unwrap()
+                        .parse::<usize>().map_err(|_| "Invalid closing".to_string())?,
+        simplify: match matches.value_of("simplify") {
+            Some(s) => s.parse::<f64>().ok().filter(|&tolerance| tolerance >= 0.0).ok_or(format!("Invalid tolerance: {}", s))?,
+            None => 0.0
+        }
+    };
+
+    let footprint_classes = parse_classes(&values_of(matches, "classes"))?;
+
+    let flightlines = match matches.value_of("per-flightline") {
+        Some(s) => Some(s.parse::<FlightlineMode>()?),
+        None => None
+    };
+
+    let hexbin = match matches.value_of("hexbin") {
+        Some(s) => Some(s.parse::<f64>().ok().filter(|&size| size > 0.0).ok_or(format!("Invalid hexagon size: {}", s))?),
+        None => None
+    };
+    // Hexagons are counted in coordinates of each file.
+    if hexbin.is_some() && mixed_crs {
+        return Err("--hexbin requires files in a single CRS".to_string().into());
+    }
+
+    // Density raster shares cell size with grid footprint.
+    let density_cell = match matches.value_of("density-raster") {
+        Some(_) if !matches.is_present("cell") => return Err("--density-raster requires --cell".to_string().into()),
+        Some(_) if mixed_crs => return Err("--density-raster requires files in a single CRS".to_string().into()),
+        Some(_) => Some(footprint_options.cell),
+        None => None
+    };
+
+    let path_mode = match matches.value_of("path-mode") {
+        Some(s) => s.parse()?,
+        None => PathMode::AsGiven
+    };
+    if path_mode == PathMode::Relative && (driver_name == "PostgreSQL" || matches.is_present("stac-only")) {
+        return Err("--path-mode relative requires output file".to_string().into());
+    }
+
+    let read_options = ReadOptions {
+        scan: ScanOptions {
+            thin: parse_thin(matches.value_of("thin").unwrap())?,
+            // Bbox of filtered points is computed from their coordinates, not taken from the header.
+            xy: footprint_options.mode.needs_points() || !footprint_classes.is_empty(),
+            hull: footprint_options.mode == footprint::Mode::Hull,
+            gps_time: matches.is_present("gps-time"),
+            classes: matches.is_present("class-stats"),
+            returns: matches.is_present("verify-returns"),
+            bounds: matches.is_present("verify") || matches.is_present("fix-headers"),
+            hexbin,
+            density_cell,
+            point_sources: matches.is_present("point-sources"),
+            flightlines: flightlines.is_some(),
+            footprint_classes
+        },
+        footprint: footprint_options,
+        class_stats: match matches.value_of("class-stats") {
+            Some(s) => Some(s.parse()?),
+            None => None
+        },
+        vlr_inventory: match matches.value_of("vlrs") {
+            Some(s) => Some(s.parse()?),
+            None => None
+        },
+        bbox_density: matches.is_present("bbox-density"),
+        source_dir: matches.is_present("source-dir"),
+        duplicates: matches.is_present("duplicates"),
+        provenance: matches.is_present("provenance"),
+        project_id: matches.is_present("project-id"),
+        waveform: matches.is_present("waveform"),
+        src_crs: matches.is_present("src-crs"),
+        extra_dims: matches.is_present("extra-dims"),
+        file_date: matches.is_present("file-date"),
+        file_date_mtime: matches.is_present("file-date-mtime"),
+        checksum: match matches.value_of("checksum") {
+            Some(s) => Some(s.parse()?),
+            None => None
+        },
+        headers: Some(headers),
+        fix_headers: matches.is_present("fix-headers"),
+        backup_headers: matches.is_present("backup"),
+        path_format: PathFormat { mode: path_mode, base: out_path.parent().map(Path::to_path_buf).unwrap_or_default() },
+        xyz: parse_xyz_options(matches)?,
+        cache: match matches.value_of("cache") {
+            Some(path) => Some(Arc::new(Cache::open(path).or_exit(ExitCode::Output)?)),
+            None => None
+        },
+        min_density: parse_density(matches.value_of("min-density"))?,
+        max_density: parse_density(matches.value_of("max-density"))?,
+        min_points: match matches.value_of("min-points") {
+            Some(s) => Some(s.parse::<u64>().map_err(|_| format!("Invalid number of points: {}", s))?),
+            None => None
+        }
+    };
+
+    if read_options.scan.is_needed() && paths.iter().any(|p| remote::is_url(p)) {
+        warn!("Only headers of remote files are read, their footprints and point statistics are not computed");
+    }
+
+    if read_options.scan.returns && read_options.scan.thin > 1 {
+        return Err("--verify-returns requires all points to be scanned, it can not be used with --thin".to_string().into());
+    }
+    if read_options.scan.bounds && read_options.scan.thin > 1 {
+        return Err("--verify and --fix-headers require all points to be scanned, they can not be used with --thin".to_string().into());
+    }
+
+    let selected = config.values_of(matches, "fields");
+    let mut fields = match (matches.value_of("tileindex-field"), config.value_of(matches, "schema").as_deref()) {
+        _ if !selected.is_empty() => fields::select_fields(&selected)?,
+        (Some(name), _) => fields::tileindex_schema(name),
+        (None, Some("pdal-tindex")) => fields::pdal_tindex_schema(),
+        _ => fields::schema(&read_options)
+    };
+    for definition in values_of(matches, "field") {
+        fields.push(expr::computed_field(&definition)?);
+    }
+    let extra_layers = ["union-layer", "detect-gaps", "detect-overlaps", "hexbin", "per-flightline"];
+    if text_output && extra_layers.iter().chain(&["layer-per-dir", "split-by-crs"]).any(|&arg| matches.is_present(arg)) {
+        return Err(format!("--union-layer, --detect-gaps, --detect-overlaps, --hexbin, --per-flightline, --layer-per-dir and --split-by-crs require \
+                            output format with several layers, e.g. GPKG, not {}", driver_name).into());
+    }
+    // Coverage layers are computed from a single bounds layer.
+    for grouping in &["layer-per-dir", "split-by-crs"] {
+        if matches.is_present(grouping) && extra_layers.iter().any(|&arg| matches.is_present(arg)) {
+            return Err(format!("--{} can not be used with --union-layer, --detect-gaps, --detect-overlaps, --hexbin and --per-flightline", grouping).into());
+        }
+    }
+    if matches.is_present("layer-per-dir") && split_by_crs {
+        return Err("--layer-per-dir can not be used with --split-by-crs".to_string().into());
+    }
+
+    let layer_name = matches.value_of("layer").unwrap_or(las_bounds::LAYER_NAME);
+    if dry_run {
+        return print_dry_run(&paths, &fields, &driver_name, &out_path, layer_name, srs.as_ref()).or_exit(ExitCode::Input);
+    }
+
+    let elevation = match (matches.is_present("3d"), matches.value_of("z-value")) {
+        (true, Some(s)) => Some(s.parse::<Elevation>()?),
+        _ => None
+    };
+    if elevation.is_some() && (driver_name == "KML" || driver_name == "KMZ") {
+        return Err("--3d is not supported for KML, use --kml-altitude absolute".to_string().into());
+    }
+    let buffer = match matches.value_of("buffer") {
+        Some(s) => Some(s.parse::<f64>().ok().filter(|d| d.is_finite()).ok_or(format!("Invalid buffer distance: {}", s))?),
+        None => None
+    };
+    // Buffered outlines are computed in 2D.
+    if buffer.is_some() && elevation.is_some() {
+        return Err("--buffer can not be used with --3d".to_string().into());
+    }
+    if buffer.is_some() && (driver_name == "KML" || driver_name == "KMZ") {
+        return Err("--buffer is not supported for KML".to_string().into());
+    }
+
+    let json_result = matches.is_present("json");
+    let ndjson = matches.is_present("ndjson");
+    let mut summary = RunSummary {
+        output: Some(out_path.to_string_lossy().into_owned()).filter(|_| !matches.is_present("stac-only")),
+        ..Default::default()
+    };
+
+    let mut writers: Vec<Box<dyn BoundsWriter>> = Vec::new();
+    if (driver_name == "KML" || driver_name == "KMZ") && !matches.is_present("stac-only") {
+        let kml_options = KmlOptions {
+            path: out_path,
+            altitude: matches.value_of("kml-altitude").unwrap().parse()?,
+            line_color: kml_output::parse_color(matches.value_of("kml-line-color").unwrap())?,
+            fill_color: kml_output::parse_color(matches.value_of("kml-fill-color").unwrap())?,
+            line_width: matches.value_of("kml-line-width").unwrap()
+                               .parse::<f64>().map_err(|_| "Invalid KML line width".to_string())?,
+            overwrite: matches.is_present("overwrite")
+        };
+        let srs = kml_srs.as_ref().ok_or_else(|| "KML output requires known CRS of input files, use --epsg".to_string())?;
+        writers.push(Box::new(KmlWriter::new(kml_options, srs, &fields).or_exit(ExitCode::Output)?));
+    } else if driver_name == "CSV" && !matches.is_present("stac-only") {
+        writers.push(Box::new(CsvWriter::new(CsvOptions {
+            path: out_path,
+            transform,
+            multi: read_options.footprint.mode.is_multi(),
+            elevation,
+            buffer,
+            append: matches.is_present("append"),
+            overwrite: matches.is_present("overwrite")
+        }, &fields).or_exit(ExitCode::Output)?));
+    } else if !matches.is_present("stac-only") {
+        writers.push(Box::new(OgrWriter::new(OgrOptions {
+            driver_name,
+            path: out_path,
+            layer_name: layer_name.to_string(),
+            dataset_options: values_of(matches, "dsco"),
+            layer_options: values_of(matches, "lco"),
+            srs,
+            transform,
+            multi: read_options.footprint.mode.is_multi(),
+            elevation,
+            buffer,
+            union_layer: matches.value_of("union-layer").map(String::from),
+            detect_gaps: matches.is_present("detect-gaps"),
+            aoi: matches.value_of("aoi").map(PathBuf::from),
+            detect_overlaps: matches.is_present("detect-overlaps"),
+            hexbin,
+            flightlines,
+            layer_per_dir: matches.is_present("layer-per-dir"),
+            split_by_crs,
+            reproject_each: (mixed_crs || split_by_crs) && t_epsg.is_some(),
+            append: matches.is_present("append"),
+            overwrite: matches.is_present("overwrite"),
+            transaction_size: matches.value_of("transaction-size").unwrap()
+                                     .parse::<usize>().map_err(|_| "Invalid transaction size".to_string())?
+        }, &fields).or_exit(ExitCode::Output)?));
+    }
+    if let (Some(path), Some(cell)) = (matches.value_of("density-raster"), density_cell) {
+        writers.push(Box::new(DensityRasterWriter::new(path, cell, kml_srs.clone(), matches.is_present("overwrite"))
+            .or_exit(ExitCode::Output)?));
+    }
+    if let Some(path) = matches.value_of("report") {
+        let srs = kml_srs.as_ref().ok_or_else(|| "HTML report requires known CRS of input files, use --epsg".to_string())?;
+        writers.push(Box::new(ReportWriter::new(path, srs, &fields, matches.is_present("overwrite")).or_exit(ExitCode::Output)?));
+    }
+    if let Some(stac) = stac {
+        writers.push(Box::new(stac));
+    }
+    let mut writer = MultiWriter::new(writers);
+
+    if io_concurrency > 0 && (read_options.scan.is_needed() || read_options.checksum.is_some()) {
+        warn!("--io-concurrency is used only for reading headers, points and checksums are read on --threads");
+    }
+
+    // Features already in the output are known from its path field, so resumed runs read only the rest.
+    if matches.is_present("skip-indexed") {
+        let before = paths.len();
+        paths.retain(|p| !writer.contains(&read_options.path_format.format(p)));
+        info!("Skipping {} files already in the index", before - paths.len());
+    }
+
+    let total = paths.len();
+    let total_bytes = paths.iter().map(|p| file_size(p)).sum();
+    // Lines of plain progress would mix with JSON on stdout.
+    let no_progress = matches.is_present("no-progress");
+    let progress = Progress::new(total_bytes, no_progress,
+                                 matches.is_present("quiet") || ((json_result || ndjson) && no_progress));
+    let duplicates = read_options.duplicates;
+    let cache = read_options.cache.clone();
+    let path_format = read_options.path_format.clone();
+    let (records, worker) = las_bounds::read_records(paths, read_options, threads, io_concurrency).or_exit(ExitCode::Input)?;
+    let mut log = json_log(matches).or_exit(ExitCode::Output)?;
+    let skip_errors = matches.is_present("skip-errors");
+    let mut failures = Vec::new();
+    let mut suspects = Vec::new();
+    // Records come in completion order, so with --duplicates all files are read before writing any
+    // and the original of each set of copies is the smallest path, the same in every run.
+    let mut originals = HashMap::new();
+    let results: Box<dyn Iterator<Item = RecordResult>> = if duplicates {
+        let results: Vec<RecordResult> = records.iter().collect();
+        for result in &results {
+            if let Ok(record) = &result.result {
+                let original = originals.entry(record.duplicate_key()).or_insert_with(|| result.path.clone());
+                if result.path < *original {
+                    *original = result.path.clone();
+                }
+            }
+        }
+        Box::new(results.into_iter())
+    } else {
+        Box::new(records.iter())
+    };
+    for (i, mut result) in results.enumerate() {
+        let p = result.path.clone();
+        if let Ok(record) = &mut result.result {
+            timing.add(&record.times);
+            record.source_dir = sources.get(&p).cloned();
+            if let Some(original) = originals.get(&record.duplicate_key()).filter(|original| **original != p) {
+                record.duplicate_of = Some(original.clone());
+            }
+        }
+        progress.file(i, total, &p, file_size(&p));
+        debug!("{} read in {:.3} s", p.to_string_lossy(), result.duration.as_secs_f64());
+
+        let outcome = if writer.contains(&path_format.format(&p)) {
+            progress.println(&format!("Skipped, already in the index: {}", p.to_string_lossy()));
+            Ok(Status::Skipped)
+        } else {
+            match &result.result {
+                Ok(record) => {
+                    if record.suspect == Some(true) {
+                        suspects.push((p.clone(), record.header.number_of_points()));
+                    }
+                    let write_start = Instant::now();
+                    let written = writer.write(record).map(|_| Status::Ok);
+                    timing.write += write_start.elapsed();
+                    written
+                },
+                Err(_) => Ok(Status::Failed)
+            }
+        };
+
+        let (status, error) = match &outcome {
+            Ok(status) => (*status, None),
+            Err(e) => (Status::Failed, Some(e))
+        };
+        if log.is_some() || ndjson {
+            let line = json::result_json(&result, status, error);
+            if let Some(log) = log.as_mut() {
+                writeln!(log, "{}", line).or_exit(ExitCode::Output)?;
+            }
+            if ndjson {
+                println!("{}", line);
+            }
+        }
+        summary.add(&result, status, error);
+
+        let (code, error) = match (outcome, result.result) {
+            (Ok(Status::Skipped), _) | (Ok(_), Ok(_)) => continue,
+            (Err(e), _) => (ExitCode::Output, e),
+            (_, Err(e)) => (ExitCode::Input, e)
+        };
+        if !skip_errors {
+            // Records written so far stay in the output, unless it would replace an existing one.
+            if let Err(e) = writer.abort() {
+                warn!("Could not close the output: {}", e);
+            }
+            // Files scanned so far need not be scanned again when the run is repeated.
+            if let Some(cache) = &cache {
+                cache.save().or_exit(ExitCode::Output)?;
+            }
+            if json_result {
+                summary.error = Some(error.to_string());
+                print_summary(summary, start, mixed_crs);
+            }
+            return Err(Failure { code, error });
+        }
+        progress.println(&format!("Failed: {}: {}", p.to_string_lossy(), error));
+        failures.push((p, error));
+    }
+
+    worker.join().map_err(|_| "Reading thread panicked".to_string()).or_exit(ExitCode::Input)?;
+    if let Some(cache) = cache {
+        cache.save().or_exit(ExitCode::Output)?;
+    }
+    progress.finish();
+
+    let finish_start = Instant::now();
+    let finished = writer.finish().or_exit(ExitCode::Output);
+    timing.write += finish_start.elapsed();
+    if matches.is_present("timing") {
+        timing.print(start.elapsed());
+    }
+    if json_result {
+        summary.error = finished.as_ref().err().map(|failure| failure.error.to_string());
+        print_summary(summary, start, mixed_crs);
+    }
+    finished?;
+
+    if !suspects.is_empty() {
+        warn!("{} files have suspiciously few points, possibly failed exports:", suspects.len());
+        for (p, count) in &suspects {
+            warn!("  {}: {} points", p.to_string_lossy(), count);
+        }
+    }
+    if !failures.is_empty() {
+        error!("{} of {} files failed:", failures.len(), total);
+        for (p, e) in &failures {
+            error!("  {}: {}", p.to_string_lossy(), e);
+        }
+        return fail(ExitCode::PartialFailure, format!("{} of {} files failed", failures.len(), total));
     }
 
     Ok(())
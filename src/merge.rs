@@ -0,0 +1,122 @@
+//! Combining bounds layers of several indexes into one, e.g. per-project indexes into a master catalog.
+
+use crate::{LAYER_NAME, LasBoundsError};
+use crate::ogr::{self, OutputDataset};
+use crate::writer;
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use gdal::vector::{Dataset, FieldValue, Geometry, OGRFieldType, OGRwkbGeometryType};
+use std::collections::HashSet;
+use std::path::Path;
+
+
+/// Feature read from an input, values by index of the merged schema.
+struct MergedFeature {
+    geometry: Geometry,
+    values: Vec<(usize, FieldValue)>
+}
+
+/// Union of fields of the inputs in order of first occurrence, with type of the first one.
+#[derive(Default)]
+struct Schema {
+    fields: Vec<(String, OGRFieldType::Type)>
+}
+
+impl Schema {
+
+    fn index(&mut self, name: &str, field_type: OGRFieldType::Type) -> usize {
+
+        match self.fields.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.fields.push((name.to_string(), field_type));
+                self.fields.len() - 1
+            }
+        }
+    }
+}
+
+/// Concatenates bounds layers of the inputs into `bounds` layer of a new dataset as MultiPolygons.
+/// Features are reprojected into `srs`, by default CRS of the first input. Of features with the same
+/// path, the first one is kept. Returns the number of written features.
+pub fn merge(inputs: &[&Path], layer_name: Option<&str>, srs: Option<SpatialRef>, driver_name: &str, path: &Path,
+             overwrite: bool) -> Result<usize, LasBoundsError> {
+
+    let mut srs = srs;
+    let mut schema = Schema::default();
+    let mut paths = HashSet::new();
+    let mut features = Vec::new();
+    for input in inputs {
+        let mut dataset = Dataset::open(input)?;
+        let index = writer::find_bounds_layer(&mut dataset, layer_name, input)?;
+        let layer = dataset.layer(index)?;
+
+        let transform = match (layer.spatial_ref().ok(), &srs) {
+            (Some(source), Some(target)) if !ogr::is_same_srs(&source, target) => {
+                info!("Reprojecting {}", input.to_string_lossy());
+                Some(CoordTransform::new(&source, target)?)
+            },
+            (Some(source), None) => {
+                srs = Some(source);
+                None
+            },
+            (None, Some(_)) => {
+                warn!("No CRS in {}, its features are assumed to be in the output CRS", input.to_string_lossy());
+                None
+            },
+            _ => None
+        };
+
+        let path_field = writer::path_field_name(layer);
+        let fields: Vec<(String, usize)> = layer.defn().fields()
+            .map(|field| {
+                let name = field.name();
+                let index = schema.index(&name, field.field_type());
+                (name, index)
+            })
+            .collect();
+
+        let mut duplicates = 0;
+        for feature in layer.features() {
+            if let Some(path_field) = &path_field {
+                if let Ok(FieldValue::StringValue(file)) = feature.field(path_field) {
+                    if !paths.insert(file) {
+                        duplicates += 1;
+                        continue;
+                    }
+                }
+            }
+
+            let mut geometry = ogr::multi_polygon(feature.geometry())?;
+            if let Some(transform) = &transform {
+                geometry = geometry.transform(transform)?;
+            }
+            let values = fields.iter()
+                .filter_map(|(name, index)| feature.field(name).ok().map(|value| (*index, value)))
+                .collect();
+            features.push(MergedFeature { geometry, values });
+        }
+        if duplicates > 0 {
+            info!("Skipped {} features of {} with paths already merged", duplicates, input.to_string_lossy());
+        }
+    }
+
+    if path.exists() {
+        if !overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite", path.to_string_lossy()).into());
+        }
+        ogr::delete_dataset(driver_name, path)?;
+    }
+    let mut ds = OutputDataset::create(driver_name, path, &[])?;
+    let layer = ds.create_layer(LAYER_NAME, srs.as_ref(), OGRwkbGeometryType::wkbMultiPolygon, &[])?;
+    let defn_fields: Vec<(&str, OGRFieldType::Type)> = schema.fields.iter().map(|(name, t)| (name.as_str(), *t)).collect();
+    layer.create_defn_fields(&defn_fields)?;
+
+    let count = features.len();
+    for feature in features {
+        let (indexes, values): (Vec<usize>, Vec<FieldValue>) = feature.values.into_iter().unzip();
+        let names: Vec<&str> = indexes.iter().map(|&index| schema.fields[index].0.as_str()).collect();
+        layer.create_feature_fields(feature.geometry, &names, &values)?;
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,424 @@
+//! Helpers over gdal-sys for OGR functionality that the gdal crate does not wrap yet
+//! (creation options, driver metadata, raster output).
+
+use crate::LasBoundsError;
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{Dataset, Geometry, Layer, OGRwkbGeometryType};
+use gdal_sys::{GDALDataType, GDALDatasetH, GDALDriverH, OGRGeometryH};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+use std::ptr;
+
+
+/// NULL terminated list of C strings as expected by GDAL `papszOptions` arguments.
+struct CslList {
+    _strings: Vec<CString>,
+    ptrs: Vec<*mut c_char>
+}
+
+impl CslList {
+
+    fn new(items: &[String]) -> Result<Self, LasBoundsError> {
+
+        let strings = items.iter()
+                           .map(|s| to_c_string(s))
+                           .collect::<Result<Vec<_>, _>>()?;
+        let mut ptrs: Vec<*mut c_char> = strings.iter().map(|s| s.as_ptr() as *mut c_char).collect();
+        ptrs.push(ptr::null_mut());
+
+        Ok(CslList { _strings: strings, ptrs })
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut *mut c_char {
+        self.ptrs.as_mut_ptr()
+    }
+}
+
+fn to_c_string(s: &str) -> Result<CString, LasBoundsError> {
+    CString::new(s).map_err(|_| format!("String contains NUL byte: {}", s).into())
+}
+
+fn last_error(method: &str) -> LasBoundsError {
+
+    let msg = unsafe { CStr::from_ptr(gdal_sys::CPLGetLastErrorMsg()) };
+    format!("{} failed: {}", method, msg.to_string_lossy()).into()
+}
+
+fn driver(name: &str) -> Result<GDALDriverH, LasBoundsError> {
+
+    let c_name = to_c_string(name)?;
+    let c_driver = unsafe { gdal_sys::GDALGetDriverByName(c_name.as_ptr()) };
+    if c_driver.is_null() {
+        return Err(format!("GDAL driver not available: {}", name).into());
+    }
+
+    Ok(c_driver)
+}
+
+/// Returns default file extension of the driver, `None` for drivers without one (e.g. databases).
+pub fn driver_extension(name: &str) -> Result<Option<String>, LasBoundsError> {
+
+    let c_driver = driver(name)?;
+    let c_key = to_c_string("DMD_EXTENSION")?;
+    let c_ext = unsafe { gdal_sys::GDALGetMetadataItem(c_driver, c_key.as_ptr(), ptr::null()) };
+    if c_ext.is_null() {
+        return Ok(None);
+    }
+
+    let ext = unsafe { CStr::from_ptr(c_ext) }.to_string_lossy().into_owned();
+    Ok(Some(ext).filter(|ext| !ext.is_empty()))
+}
+
+/// Whether the output is a database connection string, e.g. `PG:host=db dbname=lidar`, rather than a file.
+pub fn is_connection_string(path: &Path) -> bool {
+    path.to_string_lossy().starts_with("PG:")
+}
+
+/// Deletes dataset with all its files.
+pub fn delete_dataset(driver_name: &str, path: &Path) -> Result<(), LasBoundsError> {
+
+    let c_driver = driver(driver_name)?;
+    let c_path = to_c_string(&path.to_string_lossy())?;
+    let err = unsafe { gdal_sys::GDALDeleteDataset(c_driver, c_path.as_ptr()) };
+    if err != gdal_sys::CPLErr::CE_None {
+        return Err(last_error("GDALDeleteDataset"));
+    }
+
+    Ok(())
+}
+
+/// Renames dataset with all its files.
+pub fn rename_dataset(driver_name: &str, from: &Path, to: &Path) -> Result<(), LasBoundsError> {
+
+    let c_driver = driver(driver_name)?;
+    let c_from = to_c_string(&from.to_string_lossy())?;
+    let c_to = to_c_string(&to.to_string_lossy())?;
+    let err = unsafe { gdal_sys::GDALRenameDataset(c_driver, c_to.as_ptr(), c_from.as_ptr()) };
+    if err != gdal_sys::CPLErr::CE_None {
+        return Err(last_error("GDALRenameDataset"));
+    }
+
+    Ok(())
+}
+
+/// Makes the CRS take coordinates in longitude, latitude order, as GeoJSON does, instead of the axis order
+/// of its authority definition (latitude first for EPSG:4326).
+pub fn set_traditional_axis_order(srs: &SpatialRef) {
+    unsafe {
+        gdal_sys::OSRSetAxisMappingStrategy(srs.to_c_hsrs(), gdal_sys::OSRAxisMappingStrategy::OAMS_TRADITIONAL_GIS_ORDER);
+    }
+}
+
+/// Whether both CRSs are the same, ignoring differences in their definitions such as names.
+pub fn is_same_srs(srs: &SpatialRef, other: &SpatialRef) -> bool {
+    unsafe { gdal_sys::OSRIsSame(srs.to_c_hsrs(), other.to_c_hsrs()) == 1 }
+}
+
+fn owned_geometry(c_geometry: OGRGeometryH, method: &str) -> Result<Geometry, LasBoundsError> {
+
+    if c_geometry.is_null() {
+        return Err(last_error(method));
+    }
+    Ok(unsafe { Geometry::with_c_geometry(c_geometry, true) })
+}
+
+pub fn convex_hull(geometry: &Geometry) -> Result<Geometry, LasBoundsError> {
+    owned_geometry(unsafe { gdal_sys::OGR_G_ConvexHull(geometry.c_geometry()) }, "OGR_G_ConvexHull")
+}
+
+/// Area within `distance` of the geometry, with arcs approximated by 8 segments per quadrant.
+pub fn buffer(geometry: &Geometry, distance: f64) -> Result<Geometry, LasBoundsError> {
+    owned_geometry(unsafe { gdal_sys::OGR_G_Buffer(geometry.c_geometry(), distance, 8) }, "OGR_G_Buffer")
+}
+
+/// Part of `geometry` not covered by `other`.
+pub fn difference(geometry: &Geometry, other: &Geometry) -> Result<Geometry, LasBoundsError> {
+    owned_geometry(unsafe { gdal_sys::OGR_G_Difference(geometry.c_geometry(), other.c_geometry()) }, "OGR_G_Difference")
+}
+
+pub fn intersection(geometry: &Geometry, other: &Geometry) -> Result<Geometry, LasBoundsError> {
+    owned_geometry(unsafe { gdal_sys::OGR_G_Intersection(geometry.c_geometry(), other.c_geometry()) }, "OGR_G_Intersection")
+}
+
+/// Bounding box as `(min_x, min_y, max_x, max_y)`.
+pub fn envelope(geometry: &Geometry) -> (f64, f64, f64, f64) {
+
+    let mut envelope = gdal_sys::OGREnvelope { MinX: 0.0, MaxX: 0.0, MinY: 0.0, MaxY: 0.0 };
+    unsafe { gdal_sys::OGR_G_GetEnvelope(geometry.c_geometry(), &mut envelope) };
+    (envelope.MinX, envelope.MinY, envelope.MaxX, envelope.MaxY)
+}
+
+/// Handles of Polygon itself, or of polygons of MultiPolygon, owned by the geometry.
+fn c_polygons(geometry: &Geometry) -> Vec<OGRGeometryH> {
+
+    let c_geometry = unsafe { geometry.c_geometry() };
+    let flat_type = unsafe { gdal_sys::OGR_GT_Flatten(gdal_sys::OGR_G_GetGeometryType(c_geometry)) };
+    match flat_type {
+        OGRwkbGeometryType::wkbPolygon => vec![c_geometry],
+        OGRwkbGeometryType::wkbMultiPolygon | OGRwkbGeometryType::wkbGeometryCollection => {
+            let count = unsafe { gdal_sys::OGR_G_GetGeometryCount(c_geometry) };
+            (0..count).map(|i| unsafe { gdal_sys::OGR_G_GetGeometryRef(c_geometry, i) })
+                      .filter(|&c_part| unsafe {
+                          gdal_sys::OGR_GT_Flatten(gdal_sys::OGR_G_GetGeometryType(c_part)) == OGRwkbGeometryType::wkbPolygon
+                      })
+                      .collect()
+        },
+        _ => Vec::new()
+    }
+}
+
+/// Copies of polygons the geometry consists of, other parts (e.g. lines of touching rings) are dropped.
+pub fn polygons(geometry: &Geometry) -> Vec<Geometry> {
+    c_polygons(geometry).into_iter()
+                        .map(|c_part| unsafe { Geometry::with_c_geometry(gdal_sys::OGR_G_Clone(c_part), true) })
+                        .collect()
+}
+
+/// MultiPolygon of polygons the geometry consists of, dropping other parts.
+pub fn multi_polygon(geometry: &Geometry) -> Result<Geometry, LasBoundsError> {
+
+    let c_multi = unsafe { gdal_sys::OGR_G_CreateGeometry(OGRwkbGeometryType::wkbMultiPolygon) };
+    let multi = owned_geometry(c_multi, "OGR_G_CreateGeometry")?;
+    for c_part in c_polygons(geometry) {
+        let err = unsafe { gdal_sys::OGR_G_AddGeometry(c_multi, c_part) };
+        if err != gdal_sys::OGRErr::OGRERR_NONE {
+            return Err(last_error("OGR_G_AddGeometry"));
+        }
+    }
+    Ok(multi)
+}
+
+/// Union of many polygons computed at once (cascaded), much faster than adding them one by one.
+pub struct GeometryUnion {
+    c_collection: OGRGeometryH
+}
+
+impl GeometryUnion {
+
+    pub fn new() -> Self {
+        GeometryUnion { c_collection: unsafe { gdal_sys::OGR_G_CreateGeometry(OGRwkbGeometryType::wkbMultiPolygon) } }
+    }
+
+    /// Adds Polygon, or polygons of MultiPolygon.
+    pub fn add(&mut self, geometry: &Geometry) -> Result<(), LasBoundsError> {
+
+        for c_part in c_polygons(geometry) {
+            // Parts are copied into the collection.
+            let err = unsafe { gdal_sys::OGR_G_AddGeometry(self.c_collection, c_part) };
+            if err != gdal_sys::OGRErr::OGRERR_NONE {
+                return Err(last_error("OGR_G_AddGeometry"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn union(&self) -> Result<Geometry, LasBoundsError> {
+
+        owned_geometry(unsafe { gdal_sys::OGR_G_UnionCascaded(self.c_collection) }, "OGR_G_UnionCascaded")
+    }
+}
+
+impl Default for GeometryUnion {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for GeometryUnion {
+
+    fn drop(&mut self) {
+        unsafe { gdal_sys::OGR_G_DestroyGeometry(self.c_collection) };
+    }
+}
+
+pub fn delete_feature(layer: &Layer, fid: u64) -> Result<(), LasBoundsError> {
+
+    let err = unsafe { gdal_sys::OGR_L_DeleteFeature(layer.c_layer(), fid as i64) };
+    if err != gdal_sys::OGRErr::OGRERR_NONE {
+        return Err(last_error("OGR_L_DeleteFeature"));
+    }
+    Ok(())
+}
+
+pub fn layer_name(layer: &Layer) -> String {
+    unsafe { CStr::from_ptr(gdal_sys::OGR_L_GetName(layer.c_layer())) }.to_string_lossy().into_owned()
+}
+
+/// Writes pending changes of the layer, so readers of the dataset see them.
+pub fn sync_to_disk(layer: &Layer) -> Result<(), LasBoundsError> {
+
+    let err = unsafe { gdal_sys::OGR_L_SyncToDisk(layer.c_layer()) };
+    if err != gdal_sys::OGRErr::OGRERR_NONE {
+        return Err(last_error("OGR_L_SyncToDisk"));
+    }
+    Ok(())
+}
+
+/// Union of geometries of all features of the first layer, e.g. area of interest.
+pub fn read_layer_union(path: &Path) -> Result<Geometry, LasBoundsError> {
+
+    let mut dataset = Dataset::open(path)?;
+    let mut union = GeometryUnion::new();
+    for feature in dataset.layer(0)?.features() {
+        union.add(feature.geometry())?;
+    }
+    union.union()
+}
+
+/// Vector dataset created with driver specific options.
+pub struct OutputDataset {
+    c_dataset: GDALDatasetH,
+    dataset: Dataset
+}
+
+impl OutputDataset {
+
+    pub fn create(driver_name: &str, path: &Path, options: &[String]) -> Result<Self, LasBoundsError> {
+
+        let c_driver = driver(driver_name)?;
+        let c_path = to_c_string(&path.to_string_lossy())?;
+        let mut c_options = CslList::new(options)?;
+
+        let c_dataset = unsafe {
+            gdal_sys::GDALCreate(c_driver, c_path.as_ptr(), 0, 0, 0, GDALDataType::GDT_Unknown, c_options.as_mut_ptr())
+        };
+        if c_dataset.is_null() {
+            return Err(last_error("GDALCreate"));
+        }
+
+        let dataset = unsafe { Dataset::_with_c_dataset(c_dataset) };
+        Ok(OutputDataset { c_dataset, dataset })
+    }
+
+    /// Opens existing dataset for update.
+    pub fn open(path: &Path) -> Result<Self, LasBoundsError> {
+
+        let c_path = to_c_string(&path.to_string_lossy())?;
+        let flags = gdal_sys::GDAL_OF_VECTOR | gdal_sys::GDAL_OF_UPDATE;
+        let c_dataset = unsafe {
+            gdal_sys::GDALOpenEx(c_path.as_ptr(), flags, ptr::null(), ptr::null(), ptr::null())
+        };
+        if c_dataset.is_null() {
+            return Err(last_error("GDALOpenEx"));
+        }
+
+        let dataset = unsafe { Dataset::_with_c_dataset(c_dataset) };
+        Ok(OutputDataset { c_dataset, dataset })
+    }
+
+    /// Index of the layer with given name, `None` if there is no such layer.
+    pub fn layer_index(&self, name: &str) -> Result<Option<isize>, LasBoundsError> {
+
+        let c_name = to_c_string(name)?;
+        let c_layer = unsafe { gdal_sys::GDALDatasetGetLayerByName(self.c_dataset, c_name.as_ptr()) };
+        if c_layer.is_null() {
+            return Ok(None);
+        }
+
+        let index = (0..self.dataset.count())
+                        .find(|&i| unsafe { gdal_sys::GDALDatasetGetLayer(self.c_dataset, i as i32) } == c_layer);
+        Ok(index)
+    }
+
+    pub fn delete_layer(&mut self, index: isize) -> Result<(), LasBoundsError> {
+
+        let err = unsafe { gdal_sys::GDALDatasetDeleteLayer(self.c_dataset, index as i32) };
+        if err != gdal_sys::OGRErr::OGRERR_NONE {
+            return Err(last_error("GDALDatasetDeleteLayer"));
+        }
+        Ok(())
+    }
+
+    /// Starts transaction, returns `false` when the driver does not support transactions.
+    pub fn start_transaction(&mut self) -> Result<bool, LasBoundsError> {
+
+        let err = unsafe { gdal_sys::GDALDatasetStartTransaction(self.c_dataset, 0) };
+        match err {
+            gdal_sys::OGRErr::OGRERR_NONE => Ok(true),
+            gdal_sys::OGRErr::OGRERR_UNSUPPORTED_OPERATION => Ok(false),
+            _ => Err(last_error("GDALDatasetStartTransaction"))
+        }
+    }
+
+    pub fn commit_transaction(&mut self) -> Result<(), LasBoundsError> {
+
+        let err = unsafe { gdal_sys::GDALDatasetCommitTransaction(self.c_dataset) };
+        if err != gdal_sys::OGRErr::OGRERR_NONE {
+            return Err(last_error("GDALDatasetCommitTransaction"));
+        }
+        Ok(())
+    }
+
+    pub fn layer_names(&self) -> Vec<String> {
+
+        (0..self.dataset.count())
+            .map(|i| unsafe {
+                let c_layer = gdal_sys::GDALDatasetGetLayer(self.c_dataset, i as i32);
+                CStr::from_ptr(gdal_sys::OGR_L_GetName(c_layer)).to_string_lossy().into_owned()
+            })
+            .collect()
+    }
+
+    pub fn layer_count(&self) -> isize {
+        self.dataset.count()
+    }
+
+    pub fn layer(&mut self, index: isize) -> Result<&mut Layer, LasBoundsError> {
+        Ok(self.dataset.layer(index)?)
+    }
+
+    pub fn create_layer(&mut self, name: &str, srs: Option<&SpatialRef>, geometry_type: OGRwkbGeometryType::Type,
+                        options: &[String]) -> Result<&mut Layer, LasBoundsError> {
+
+        let c_name = to_c_string(name)?;
+        let c_srs = srs.map_or(ptr::null_mut(), |srs| srs.to_c_hsrs());
+        let mut c_options = CslList::new(options)?;
+
+        let c_layer = unsafe {
+            gdal_sys::GDALDatasetCreateLayer(self.c_dataset, c_name.as_ptr(), c_srs, geometry_type, c_options.as_mut_ptr())
+        };
+        if c_layer.is_null() {
+            return Err(last_error("GDALDatasetCreateLayer"));
+        }
+
+        let index = self.dataset.count() - 1;
+        Ok(self.dataset.layer(index)?)
+    }
+}
+
+/// Writes single band `UInt32` GeoTIFF, `data` in rows from north to south.
+pub fn write_raster(path: &Path, width: usize, height: usize, mut geo_transform: [f64; 6], srs: Option<&SpatialRef>,
+                    data: &[u32]) -> Result<(), LasBoundsError> {
+
+    let c_driver = driver("GTiff")?;
+    let c_path = to_c_string(&path.to_string_lossy())?;
+    let mut c_options = CslList::new(&["COMPRESS=DEFLATE".to_string(), "TILED=YES".to_string(), "BIGTIFF=IF_SAFER".to_string()])?;
+    let c_projection = to_c_string(&srs.map_or(Ok(String::new()), |srs| srs.to_wkt())?)?;
+
+    let c_dataset = unsafe {
+        gdal_sys::GDALCreate(c_driver, c_path.as_ptr(), width as i32, height as i32, 1, GDALDataType::GDT_UInt32,
+                             c_options.as_mut_ptr())
+    };
+    if c_dataset.is_null() {
+        return Err(last_error("GDALCreate"));
+    }
+
+    let result = unsafe {
+        if gdal_sys::GDALSetGeoTransform(c_dataset, geo_transform.as_mut_ptr()) != gdal_sys::CPLErr::CE_None {
+            Err(last_error("GDALSetGeoTransform"))
+        } else if srs.is_some() && gdal_sys::GDALSetProjection(c_dataset, c_projection.as_ptr()) != gdal_sys::CPLErr::CE_None {
+            Err(last_error("GDALSetProjection"))
+        } else {
+            let c_band = gdal_sys::GDALGetRasterBand(c_dataset, 1);
+            let err = gdal_sys::GDALRasterIO(c_band, gdal_sys::GDALRWFlag::GF_Write, 0, 0, width as i32, height as i32,
+                                             data.as_ptr() as *mut c_void, width as i32, height as i32,
+                                             GDALDataType::GDT_UInt32, 0, 0);
+            if err != gdal_sys::CPLErr::CE_None { Err(last_error("GDALRasterIO")) } else { Ok(()) }
+        }
+    };
+    unsafe { gdal_sys::GDALClose(c_dataset) };
+
+    result
+}
@@ -0,0 +1,286 @@
+//! PLY point files, ASCII and binary, read for vertex coordinates.
+
+use crate::LasBoundsError;
+use crate::density;
+use crate::hexbin;
+use crate::input;
+use crate::scan::{self, PointStats, ScanOptions};
+use las::{Bounds, Header};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64
+}
+
+impl ScalarType {
+
+    fn parse(s: &str) -> Option<Self> {
+
+        match s {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None
+        }
+    }
+
+    fn size(self) -> usize {
+
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8
+        }
+    }
+
+    fn decode(self, bytes: &[u8], encoding: Encoding) -> f64 {
+
+        let le = encoding != Encoding::BigEndian;
+        match self {
+            ScalarType::Int8 => f64::from(bytes[0] as i8),
+            ScalarType::UInt8 => f64::from(bytes[0]),
+            ScalarType::Int16 => {
+                let b = bytes.try_into().unwrap();
+                f64::from(if le { i16::from_le_bytes(b) } else { i16::from_be_bytes(b) })
+            },
+            ScalarType::UInt16 => {
+                let b = bytes.try_into().unwrap();
+                f64::from(if le { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+            },
+            ScalarType::Int32 => {
+                let b = bytes.try_into().unwrap();
+                f64::from(if le { i32::from_le_bytes(b) } else { i32::from_be_bytes(b) })
+            },
+            ScalarType::UInt32 => {
+                let b = bytes.try_into().unwrap();
+                f64::from(if le { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+            },
+            ScalarType::Float32 => {
+                let b = bytes.try_into().unwrap();
+                f64::from(if le { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) })
+            },
+            ScalarType::Float64 => {
+                let b = bytes.try_into().unwrap();
+                if le { f64::from_le_bytes(b) } else { f64::from_be_bytes(b) }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, ScalarType),
+    /// List with count type and item type, e.g. vertex indices of faces.
+    List(ScalarType, ScalarType)
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: u64,
+    properties: Vec<Property>
+}
+
+impl Element {
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|property| matches!(property, Property::Scalar(n, _) if n == name))
+    }
+}
+
+fn invalid(path: &Path, msg: &str) -> LasBoundsError {
+    format!("Invalid PLY file {}: {}", path.to_string_lossy(), msg).into()
+}
+
+fn read_header<R: BufRead>(reader: &mut R, path: &Path) -> Result<(Encoding, Vec<Element>), LasBoundsError> {
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut line = String::new();
+    let mut first = true;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid(path, "missing end_header"));
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if first {
+            if words != ["ply"] {
+                return Err(invalid(path, "missing ply signature"));
+            }
+            first = false;
+            continue;
+        }
+
+        match words.as_slice() {
+            ["format", format, _] => encoding = Some(match *format {
+                "ascii" => Encoding::Ascii,
+                "binary_little_endian" => Encoding::LittleEndian,
+                "binary_big_endian" => Encoding::BigEndian,
+                _ => return Err(invalid(path, "unknown format"))
+            }),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid(path, "invalid element count"))?,
+                properties: Vec::new()
+            }),
+            ["property", "list", count_type, item_type, _] => {
+                let property = Property::List(ScalarType::parse(count_type).ok_or_else(|| invalid(path, "unknown type"))?,
+                                              ScalarType::parse(item_type).ok_or_else(|| invalid(path, "unknown type"))?);
+                elements.last_mut().ok_or_else(|| invalid(path, "property outside element"))?.properties.push(property);
+            },
+            ["property", scalar_type, name] => {
+                let property = Property::Scalar(name.to_string(),
+                                                ScalarType::parse(scalar_type).ok_or_else(|| invalid(path, "unknown type"))?);
+                elements.last_mut().ok_or_else(|| invalid(path, "property outside element"))?.properties.push(property);
+            },
+            ["end_header"] => break,
+            _ => ()
+        }
+    }
+
+    let encoding = encoding.ok_or_else(|| invalid(path, "missing format"))?;
+    Ok((encoding, elements))
+}
+
+/// Reads property values of a single binary element record.
+fn read_binary<R: Read>(reader: &mut R, element: &Element, encoding: Encoding, values: &mut Vec<f64>)
+    -> Result<(), LasBoundsError> {
+
+    let mut buf = [0u8; 8];
+    values.clear();
+    for property in &element.properties {
+        match property {
+            Property::Scalar(_, scalar_type) => {
+                let bytes = &mut buf[..scalar_type.size()];
+                reader.read_exact(bytes)?;
+                values.push(scalar_type.decode(bytes, encoding));
+            },
+            Property::List(count_type, item_type) => {
+                let bytes = &mut buf[..count_type.size()];
+                reader.read_exact(bytes)?;
+                let count = count_type.decode(bytes, encoding) as u64;
+                let mut items = vec![0u8; count as usize * item_type.size()];
+                reader.read_exact(&mut items)?;
+                // Lists are skipped, their slot keeps indices of following scalars.
+                values.push(0.0);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads property values of a single ASCII element line.
+fn read_ascii<R: BufRead>(reader: &mut R, element: &Element, path: &Path, values: &mut Vec<f64>)
+    -> Result<(), LasBoundsError> {
+
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(invalid(path, "unexpected end of file"));
+    }
+
+    values.clear();
+    let mut words = line.split_whitespace();
+    let mut next = || words.next().and_then(|word| word.parse::<f64>().ok()).ok_or_else(|| invalid(path, "invalid value"));
+    for property in &element.properties {
+        match property {
+            Property::Scalar(..) => values.push(next()?),
+            Property::List(..) => {
+                let count = next()? as u64;
+                for _ in 0..count {
+                    next()?;
+                }
+                values.push(0.0);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads vertex coordinates into a header with their bounds and count. XY coordinates are
+/// collected when requested by scan options, other point statistics are not available.
+pub fn read_points(path: &Path, scan: &ScanOptions) -> Result<(Header, PointStats), LasBoundsError> {
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let (encoding, elements) = read_header(&mut reader, path)?;
+
+    let mut bounds = Bounds::default();
+    let mut count = 0u64;
+    let mut stats = PointStats::default();
+    let mut values = Vec::new();
+
+    // Elements precede vertices rarely, but have to be read through before them.
+    for element in &elements {
+        let xyz = if element.name == "vertex" {
+            match (element.index_of("x"), element.index_of("y"), element.index_of("z")) {
+                (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+                _ => return Err(invalid(path, "vertex without x, y and z"))
+            }
+        } else {
+            None
+        };
+
+        for _ in 0..element.count {
+            match encoding {
+                Encoding::Ascii => read_ascii(&mut reader, element, path, &mut values)?,
+                _ => read_binary(&mut reader, element, encoding, &mut values)?
+            }
+
+            if let Some((x, y, z)) = xyz {
+                let (x, y, z) = (values[x], values[y], values[z]);
+                input::grow(&mut bounds, x, y, z);
+
+                if scan.xy && count % scan.thin as u64 == 0 {
+                    scan::push_xy(&mut stats.xy, (x, y), scan);
+                }
+                if let Some(size) = scan.hexbin {
+                    *stats.hex_counts.entry(hexbin::hex_of(x, y, size)).or_insert(0) += 1;
+                }
+                if let Some(size) = scan.density_cell {
+                    *stats.cell_counts.entry(density::cell_of(x, y, size)).or_insert(0) += 1;
+                }
+                count += 1;
+            }
+        }
+
+        // Remaining elements (e.g. faces) are not needed.
+        if xyz.is_some() {
+            break;
+        }
+    }
+
+    if count == 0 {
+        return Err(format!("No points in {}", path.to_string_lossy()).into());
+    }
+
+    let header = input::synthetic_header(&bounds, count, Vec::new())?;
+    Ok((header, stats))
+}
@@ -0,0 +1,582 @@
+//! Reading bounds and attributes of LAS files.
+
+use crate::LasBoundsError;
+use crate::aio;
+use crate::cache::{self, Cache};
+use crate::checksum::{self, Algorithm};
+use crate::copc;
+use crate::ept;
+use crate::fields::{ClassStats, PathFormat, VlrInventory};
+use crate::footprint::{self, Footprint, FootprintOptions};
+use crate::header;
+use crate::input::{self, InputFormat};
+use crate::ply;
+use crate::remote;
+use crate::repair;
+use crate::scan::{self, PointStats, ScanOptions};
+use crate::xyz::{self, XyzOptions};
+use chrono::{DateTime, NaiveDate, Utc};
+use rayon::prelude::*;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+
+/// Bounds of a single LAS file, read independently of the output so it can be computed on worker threads.
+pub struct BoundsRecord {
+    pub path: PathBuf,
+    /// Value of the `path` field, see [`ReadOptions::path_format`].
+    pub indexed_path: String,
+    pub format: InputFormat,
+    /// LAS header, synthetic one for other formats.
+    pub header: las::Header,
+    pub footprint: Footprint,
+    /// Statistics from point records, without collected coordinates.
+    pub stats: PointStats,
+    /// Area used for point density.
+    pub area: f64,
+    /// File size, `None` when not reported by the server.
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    /// Input directory the file was found in, set by the caller.
+    pub source_dir: Option<PathBuf>,
+    /// Earlier file with the same header bounds, point count and project GUID, set by the caller.
+    pub duplicate_of: Option<PathBuf>,
+    /// Header bounds were rewritten with bounds of points.
+    pub header_fixed: bool,
+    /// Whether density is within [`ReadOptions::min_density`] and [`ReadOptions::max_density`],
+    /// `None` when no limit is given or density is unknown.
+    pub density_ok: Option<bool>,
+    /// Whether the file has no points or fewer than [`ReadOptions::min_points`], `None` without the limit.
+    pub suspect: Option<bool>,
+    /// Creation date of the header, or modification date with [`ReadOptions::file_date_mtime`].
+    pub file_date: Option<NaiveDate>,
+    /// Footprints of points by point source ID, empty unless [`ScanOptions::flightlines`].
+    pub flightlines: Vec<Flightline>,
+    /// Hex digest of the file content with [`ReadOptions::checksum`], `None` for remote files and EPT datasets.
+    pub checksum: Option<String>,
+    pub times: StageTimes
+}
+
+/// Time spent on stages of reading a file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageTimes {
+    /// Reading the header, also hashing the file for its checksum.
+    pub header: Duration,
+    /// Reading points, of ASCII and PLY files including their bounds.
+    pub scan: Duration,
+    /// Computing footprints from points.
+    pub geometry: Duration
+}
+
+/// Points of a single flightline in the file.
+#[derive(Debug, Clone)]
+pub struct Flightline {
+    pub point_source_id: u16,
+    /// Number of points, estimated from scanned points when thinned.
+    pub point_count: u64,
+    /// Footprint of the points, in bbox mode their bounding box.
+    pub footprint: Footprint
+}
+
+/// Header values identifying copies of the same file, see [`BoundsRecord::duplicate_key`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DuplicateKey {
+    /// Bit patterns of min and max coordinates.
+    bounds: [u64; 6],
+    point_count: u64,
+    guid: [u8; 16],
+    /// Content hash with [`ReadOptions::checksum`], so files with equal headers differ by content.
+    checksum: Option<String>
+}
+
+impl BoundsRecord {
+
+    /// Key equal for files with identical header bounds, point count, project GUID and checksum if any.
+    pub fn duplicate_key(&self) -> DuplicateKey {
+
+        let bounds = self.header.bounds();
+        DuplicateKey {
+            bounds: [bounds.min.x.to_bits(), bounds.min.y.to_bits(), bounds.min.z.to_bits(),
+                     bounds.max.x.to_bits(), bounds.max.y.to_bits(), bounds.max.z.to_bits()],
+            point_count: self.header.number_of_points(),
+            guid: *self.header.guid().as_bytes(),
+            checksum: self.checksum.clone()
+        }
+    }
+}
+
+/// What is read from each file.
+#[derive(Clone, Default)]
+pub struct ReadOptions {
+    pub footprint: FootprintOptions,
+    pub scan: ScanOptions,
+    pub class_stats: Option<ClassStats>,
+    /// Write VLR and EVLR counts, and their list.
+    pub vlr_inventory: Option<VlrInventory>,
+    /// Compute density from header bounds even when footprint is computed from points.
+    pub bbox_density: bool,
+    /// Write `source_dir` attribute.
+    pub source_dir: bool,
+    /// Write `duplicate_of` attribute.
+    pub duplicates: bool,
+    /// Write `system_id` and `software` attributes.
+    pub provenance: bool,
+    /// Write `project_id` attribute.
+    pub project_id: bool,
+    /// Write `has_waveform` attribute.
+    pub waveform: bool,
+    /// Write `src_crs` attribute.
+    pub src_crs: bool,
+    /// Write `extra_dims` attribute.
+    pub extra_dims: bool,
+    /// Rewrite header bounds not matching bounds of points, requires `scan.bounds`.
+    pub fix_headers: bool,
+    /// Copy files to `.bak` before fixing their headers.
+    pub backup_headers: bool,
+    /// What is written into the `path` field.
+    pub path_format: PathFormat,
+    /// Layout of ASCII point files.
+    pub xyz: XyzOptions,
+    /// Results of earlier scans, shared by reading threads.
+    pub cache: Option<Arc<Cache>>,
+    /// Headers read before indexing, taken instead of reading them again.
+    pub headers: Option<Arc<HeaderCache>>,
+    /// Lowest density required by the specification, for the `density_ok` attribute.
+    pub min_density: Option<f64>,
+    /// Highest density allowed by the specification, for the `density_ok` attribute.
+    pub max_density: Option<f64>,
+    /// Files with fewer points, or none, are marked with the `suspect` attribute.
+    pub min_points: Option<u64>,
+    /// Write `file_date` attribute.
+    pub file_date: bool,
+    /// Use modification date for `file_date` when the header has no date (zero day and year).
+    pub file_date_mtime: bool,
+    /// Write `checksum` attribute hashed with the algorithm.
+    pub checksum: Option<Algorithm>
+}
+
+impl ReadOptions {
+
+    /// Whether density of `points` over `area` is within density limits, `None` without limits or area.
+    fn density_ok(&self, points: u64, area: f64) -> Option<bool> {
+
+        if (self.min_density.is_none() && self.max_density.is_none()) || area <= 0.0 {
+            return None;
+        }
+        let density = points as f64 / area;
+        Some(self.min_density.map_or(true, |min| density >= min) && self.max_density.map_or(true, |max| density <= max))
+    }
+
+    /// Whether `points` are too few for a complete file, `None` without `min_points`.
+    fn is_suspect(&self, points: u64) -> Option<bool> {
+        self.min_points.map(|min| points < min.max(1))
+    }
+
+    /// Date of the header, falling back to the modification date when requested.
+    fn file_date(&self, header: &las::Header, modified: Option<SystemTime>) -> Option<NaiveDate> {
+
+        match (header.date(), modified) {
+            (Some(date), _) => Some(date),
+            (None, Some(modified)) if self.file_date_mtime => Some(DateTime::<Utc>::from(modified).naive_utc().date()),
+            _ => None
+        }
+    }
+}
+
+/// Reads header of LAS file or EPT dataset, fails for formats without header (see [`InputFormat::has_header`]).
+/// LAS files may be given by HTTP(S) URL.
+pub fn read_header(las: &Path) -> Result<las::Header, LasBoundsError> {
+
+    if remote::is_url(las) {
+        return Ok(remote::read_header(las)?.0);
+    }
+
+    match InputFormat::of(las) {
+        InputFormat::Las => header::read_file_header(las),
+        InputFormat::Ept => ept::read_header(las),
+        InputFormat::Xyz | InputFormat::Ply => Err(format!("Point file has no LAS header: {}", las.to_string_lossy()).into())
+    }
+}
+
+fn read_footprint(xy: &[(f64, f64)], bounds: &las::Bounds, options: &FootprintOptions) -> Footprint {
+
+    let footprint = match options.mode {
+        footprint::Mode::Bbox => None,
+        footprint::Mode::Hull => footprint::convex_hull(xy.to_vec()),
+        footprint::Mode::Concave => footprint::concave_hull(xy, options.alpha),
+        footprint::Mode::Grid => footprint::grid_footprint(xy, options.cell, options.closing)
+    };
+    // Simplification collapsing the whole footprint falls back to header bounds too.
+    let footprint = match footprint {
+        Some(footprint) if options.simplify > 0.0 && options.mode.is_multi() => {
+            Some(footprint.simplify(options.simplify)).filter(|footprint| !footprint.polygons.is_empty())
+        },
+        footprint => footprint
+    };
+
+    // Files with too few points to build a polygon fall back to header bounds.
+    footprint.unwrap_or_else(|| Footprint::bbox(bounds))
+}
+
+fn xy_bounds(xy: &[(f64, f64)]) -> las::Bounds {
+
+    let mut bounds = las::Bounds::default();
+    for &(x, y) in xy {
+        input::grow(&mut bounds, x, y, 0.0);
+    }
+    bounds
+}
+
+/// Footprint of points of [`ScanOptions::footprint_classes`], header bounds do not apply to them.
+/// Empty when the file has no such points.
+fn filtered_footprint(xy: &[(f64, f64)], options: &FootprintOptions) -> Footprint {
+
+    if xy.is_empty() {
+        return Footprint { polygons: Vec::new() };
+    }
+    read_footprint(xy, &xy_bounds(xy), options)
+}
+
+fn read_flightline(point_source_id: u16, xy: &[(f64, f64)], scan_options: &ScanOptions,
+                   options: &FootprintOptions) -> Flightline {
+
+    Flightline {
+        point_source_id,
+        point_count: xy.len() as u64 * scan_options.thin as u64,
+        footprint: read_footprint(xy, &xy_bounds(xy), options)
+    }
+}
+
+/// Header of a file with size and modification time of remote files, which come with the header.
+type CachedHeader = (las::Header, remote::RemoteMetadata);
+
+/// Headers read before indexing, e.g. to detect CRSs of the inputs, so [`read_bounds`] does not
+/// request them again. Each header is kept until it is taken.
+#[derive(Default)]
+pub struct HeaderCache {
+    headers: Mutex<HashMap<PathBuf, CachedHeader>>
+}
+
+impl HeaderCache {
+
+    /// Reads headers of the files having one, see [`read_header`], on a pool of `threads` threads
+    /// (0 for number of CPUs). With `io_concurrency` above 0, headers of local LAS files are read
+    /// asynchronously with that many files in flight, as in [`read_records`]. Returns time spent
+    /// reading them summed over the files. Unreadable headers are left to be reported when the
+    /// files are read.
+    pub fn read_all(&self, paths: &[PathBuf], threads: usize, io_concurrency: usize) -> Result<Duration, LasBoundsError> {
+
+        let (local, paths): (Vec<PathBuf>, Vec<PathBuf>) = paths.iter()
+            .filter(|p| InputFormat::of(p).has_header())
+            .cloned()
+            .partition(|p| io_concurrency > 0 && !remote::is_url(p) && InputFormat::of(p) == InputFormat::Las);
+        let (sender, receiver) = mpsc::channel();
+        let io = if local.is_empty() {
+            drop(sender);
+            None
+        } else {
+            Some(thread::spawn(move || aio::read_headers(local, io_concurrency, sender, |path, duration, result| {
+                (path, duration, result.ok().map(|(header, _)| header))
+            })))
+        };
+
+        let mut times: Vec<Duration> = thread_pool(threads)?.install(|| paths.par_iter().map(|p| {
+            let start = Instant::now();
+            let read = if remote::is_url(p) {
+                remote::read_header(p)
+            } else {
+                read_header(p).map(|header| (header, remote::RemoteMetadata::default()))
+            };
+            if let Ok(cached) = read {
+                self.headers.lock().unwrap().insert(p.to_path_buf(), cached);
+            }
+            start.elapsed()
+        }).collect());
+
+        // Receiving ends when the asynchronous reads are done, or at once without them.
+        for (path, duration, header) in receiver {
+            if let Some(header) = header {
+                self.headers.lock().unwrap().insert(path, (header, remote::RemoteMetadata::default()));
+            }
+            times.push(duration);
+        }
+        if let Some(io) = io {
+            let _ = io.join();
+        }
+
+        Ok(times.iter().sum())
+    }
+
+    /// What `f` makes of the header read already, `None` when it was not read.
+    pub fn with<T, F: FnOnce(&las::Header) -> T>(&self, path: &Path, f: F) -> Option<T> {
+        self.headers.lock().unwrap().get(path).map(|(header, _)| f(header))
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.headers.lock().unwrap().contains_key(path)
+    }
+
+    fn take(&self, path: &Path) -> Option<CachedHeader> {
+        self.headers.lock().unwrap().remove(path)
+    }
+}
+
+/// Reads bounds, footprint and statistics of a single file.
+pub fn read_bounds(las: &Path, options: &ReadOptions) -> Result<BoundsRecord, LasBoundsError> {
+
+    if remote::is_url(las) {
+        return read_remote_bounds(las, options);
+    }
+
+    let format = InputFormat::of(las);
+    let mut times = StageTimes::default();
+    let start = Instant::now();
+    // LAS files hashed for the checksum are read once, header first, through the same handle.
+    let hashed = options.checksum.is_some() && format == InputFormat::Las;
+    let cached = options.headers.as_ref().and_then(|headers| headers.take(las)).filter(|_| !hashed);
+    // LAS file stays open for the checksum.
+    let mut file = None;
+    let (mut header, mut stats) = match (format, cached) {
+        (InputFormat::Xyz, _) => xyz::read_points(las, &options.xyz, &options.scan)?,
+        (InputFormat::Ply, _) => ply::read_points(las, &options.scan)?,
+        (_, Some((header, _))) => (header, PointStats::default()),
+        (InputFormat::Las, None) => {
+            let mut las_file = File::open(las)?;
+            let header = header::read_open_header(&mut las_file)?;
+            file = Some(las_file);
+            (header, PointStats::default())
+        },
+        (InputFormat::Ept, None) => (read_header(las)?, PointStats::default())
+    };
+    match format {
+        InputFormat::Xyz | InputFormat::Ply => times.scan = start.elapsed(),
+        _ => times.header = start.elapsed()
+    }
+
+    // EPT datasets are indexed from metadata, their points are not scanned, ASCII and PLY points are read already.
+    let mut scan_options = options.scan.clone();
+    if format != InputFormat::Las {
+        scan_options = ScanOptions::default();
+    }
+
+    // COPC files carry GPS time range in the info VLR, so points are not scanned when only that is needed.
+    if scan_options.gps_time {
+        if let Some(gps_time) = copc::copc_info(&header).and_then(|info| info.gps_time) {
+            stats.gps_time = Some(gps_time);
+            scan_options.gps_time = false;
+        }
+    }
+    let options_key = cache::options_key(&options.footprint, &scan_options);
+    let mut cached_footprint = None;
+    let mut flightlines = Vec::new();
+    let mut scanned = false;
+    if scan_options.is_needed() {
+        match options.cache.as_ref().and_then(|cache| cache.get(las, &options_key)) {
+            Some(entry) => {
+                debug!("Using cached scan of {}", las.to_string_lossy());
+                stats = entry.stats;
+                cached_footprint = Some(entry.footprint);
+                flightlines = entry.flightlines;
+            },
+            None => {
+                let start = Instant::now();
+                let gps_time = stats.gps_time;
+                stats = scan::scan_points(las, &scan_options)?;
+                stats.gps_time = stats.gps_time.or(gps_time);
+                scanned = true;
+                times.scan += start.elapsed();
+            }
+        }
+    }
+
+    let mut header_fixed = false;
+    if let (true, Some(actual)) = (options.fix_headers && format == InputFormat::Las, stats.bounds) {
+        if !scan::header_bounds_match(&header, &actual) {
+            info!("Fixing header bounds of {}", las.to_string_lossy());
+            repair::write_bounds(las, &actual, options.backup_headers)?;
+            header = read_header(las)?;
+            header_fixed = true;
+        }
+    }
+
+    let start = Instant::now();
+    let footprint = match cached_footprint {
+        Some(footprint) => footprint,
+        None if !scan_options.footprint_classes.is_empty() => filtered_footprint(&stats.xy, &options.footprint),
+        None => read_footprint(&stats.xy, &header.bounds(), &options.footprint)
+    };
+    stats.xy = Vec::new();
+    if scanned {
+        flightlines = std::mem::take(&mut stats.flightline_xy).into_iter()
+            .map(|(point_source_id, xy)| read_flightline(point_source_id, &xy, &scan_options, &options.footprint))
+            .collect();
+    }
+    times.geometry = start.elapsed();
+
+    // Stored after header fix, so the entry matches the changed file.
+    if let (true, Some(cache)) = (scanned, &options.cache) {
+        cache.insert(las, &options_key, &footprint, &stats, &flightlines);
+    }
+
+    let area = if options.bbox_density { Footprint::bbox(&header.bounds()).area() } else { footprint.area() };
+
+    // Fixed header changed the content since the file was opened.
+    let start = Instant::now();
+    let checksum = match options.checksum {
+        Some(algorithm) if format != InputFormat::Ept => {
+            let mut file = match file {
+                Some(file) if !header_fixed => file,
+                _ => File::open(las)?
+            };
+            Some(checksum::checksum(&mut file, algorithm)?)
+        },
+        _ => None
+    };
+    times.header += start.elapsed();
+
+    let metadata = fs::metadata(las)?;
+    let density_ok = options.density_ok(header.number_of_points(), area);
+    let suspect = options.is_suspect(header.number_of_points());
+    let file_date = options.file_date(&header, metadata.modified().ok());
+
+    Ok(BoundsRecord {
+        path: las.to_path_buf(),
+        indexed_path: options.path_format.format(las),
+        format,
+        header,
+        footprint,
+        stats,
+        area,
+        size: Some(metadata.len()),
+        modified: metadata.modified().ok(),
+        created: metadata.created().ok(),
+        source_dir: None,
+        duplicate_of: None,
+        header_fixed,
+        density_ok,
+        suspect,
+        file_date,
+        flightlines,
+        checksum,
+        times
+    })
+}
+
+/// Reads bounds of a LAS file given by URL from its header, points are not scanned.
+fn read_remote_bounds(url: &Path, options: &ReadOptions) -> Result<BoundsRecord, LasBoundsError> {
+
+    let start = Instant::now();
+    let (header, metadata) = match options.headers.as_ref().and_then(|headers| headers.take(url)) {
+        Some(cached) => cached,
+        None => remote::read_header(url)?
+    };
+    let mut record = header_record(url, header, metadata.size, metadata.modified, None, options);
+    record.times.header = start.elapsed();
+    Ok(record)
+}
+
+/// Record of a LAS file from its header alone, footprint is the bounding box.
+fn header_record(las: &Path, header: las::Header, size: Option<u64>, modified: Option<SystemTime>,
+                 created: Option<SystemTime>, options: &ReadOptions) -> BoundsRecord {
+
+    let footprint = Footprint::bbox(&header.bounds());
+    let area = footprint.area();
+    let density_ok = options.density_ok(header.number_of_points(), area);
+    let suspect = options.is_suspect(header.number_of_points());
+    let file_date = options.file_date(&header, modified);
+
+    BoundsRecord {
+        path: las.to_path_buf(),
+        indexed_path: options.path_format.format(las),
+        format: InputFormat::Las,
+        header,
+        footprint,
+        stats: PointStats::default(),
+        area,
+        size,
+        modified,
+        created,
+        source_dir: None,
+        duplicate_of: None,
+        header_fixed: false,
+        density_ok,
+        suspect,
+        file_date,
+        flightlines: Vec::new(),
+        checksum: None,
+        times: StageTimes::default()
+    }
+}
+
+/// Outcome of reading a single file.
+pub struct RecordResult {
+    pub path: PathBuf,
+    /// Time spent reading the file.
+    pub duration: Duration,
+    pub result: Result<BoundsRecord, LasBoundsError>
+}
+
+fn thread_pool(threads: usize) -> Result<rayon::ThreadPool, LasBoundsError> {
+
+    let pool = rayon::ThreadPoolBuilder::new()
+                   .num_threads(threads)
+                   .build()
+                   .map_err(|e| format!("Could not create thread pool: {}", e))?;
+    Ok(pool)
+}
+
+/// Reads records of all files on a thread pool of `threads` threads (0 for number of CPUs).
+/// Records are delivered through the returned channel in completion order, so the output
+/// can be written on the receiving thread. With `io_concurrency` above 0 and only headers needed,
+/// headers of local LAS files are read asynchronously with that many files in flight.
+pub fn read_records(paths: Vec<PathBuf>, options: ReadOptions, threads: usize, io_concurrency: usize)
+    -> Result<(Receiver<RecordResult>, JoinHandle<()>), LasBoundsError> {
+
+    let pool = thread_pool(threads)?;
+
+    // Files are hashed on the reading threads.
+    let header_only = !options.scan.is_needed() && options.checksum.is_none();
+    let (headers, paths): (Vec<PathBuf>, Vec<PathBuf>) = if io_concurrency > 0 && header_only {
+        // Headers read already are taken on the reading threads.
+        let cached = |p: &Path| options.headers.as_ref().map_or(false, |headers| headers.contains(p));
+        paths.into_iter().partition(|p| !remote::is_url(p) && InputFormat::of(p) == InputFormat::Las && !cached(p))
+    } else {
+        (Vec::new(), paths)
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let worker = thread::spawn(move || {
+        let io = if headers.is_empty() {
+            None
+        } else {
+            let (sender, options) = (sender.clone(), options.clone());
+            Some(thread::spawn(move || aio::read_headers(headers, io_concurrency, sender, move |path, duration, result| {
+                let result = result.map(|(header, metadata)| {
+                    let mut record = header_record(&path, header, Some(metadata.len()), metadata.modified().ok(),
+                                                   metadata.created().ok(), &options);
+                    record.times.header = duration;
+                    record
+                });
+                RecordResult { path, duration, result }
+            })))
+        };
+
+        pool.install(|| paths.par_iter().for_each_with(sender, |sender, p| {
+            let start = Instant::now();
+            let result = read_bounds(p, &options);
+            // Sending fails only when the writer stopped on error, the remaining files are not needed then.
+            let _ = sender.send(RecordResult { path: p.clone(), duration: start.elapsed(), result });
+        }));
+        if let Some(io) = io {
+            let _ = io.join();
+        }
+    });
+
+    Ok((receiver, worker))
+}
@@ -0,0 +1,89 @@
+//! LAS files on HTTP(S) servers or S3, read with range requests so only headers are transferred.
+
+use crate::LasBoundsError;
+use crate::header;
+use crate::s3;
+use chrono::DateTime;
+use las::Header;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::SystemTime;
+
+
+const TIMEOUT_MS: u64 = 30_000;
+
+/// Whether the input path is an `http://`, `https://` or `s3://` URL.
+pub fn is_url(path: &Path) -> bool {
+
+    let path = path.to_string_lossy();
+    path.starts_with("http://") || path.starts_with("https://") || path.starts_with("s3://")
+}
+
+/// File metadata reported by the server.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteMetadata {
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>
+}
+
+/// Total size from `Content-Range: bytes 0-374/123456`.
+fn content_range_size(value: &str) -> Option<u64> {
+    value.rsplit('/').next()?.trim().parse().ok()
+}
+
+/// Fetches up to `len` bytes from `start`, `u64::max_value()` for the rest of the file.
+pub fn fetch_range(url: &str, start: u64, len: u64, metadata: &mut RemoteMetadata) -> Result<Vec<u8>, LasBoundsError> {
+
+    let range = if len == u64::max_value() {
+        format!("bytes={}-", start)
+    } else {
+        format!("bytes={}-{}", start, start + len.max(1) - 1)
+    };
+    let response = ureq::get(url)
+                       .set("Range", &range)
+                       .timeout_connect(TIMEOUT_MS)
+                       .timeout_read(TIMEOUT_MS)
+                       .call();
+    if let Some(e) = response.synthetic_error() {
+        return Err(format!("Request to {} failed: {}", url, e).into());
+    }
+
+    let status = response.status();
+    if status != 200 && status != 206 {
+        return Err(format!("Request to {} failed: HTTP {}", url, status).into());
+    }
+
+    if let Some(size) = response.header("Content-Range").and_then(content_range_size) {
+        metadata.size = Some(size);
+    } else if status == 200 {
+        metadata.size = response.header("Content-Length").and_then(|value| value.parse().ok());
+    }
+    if let Some(modified) = response.header("Last-Modified").and_then(|value| DateTime::parse_from_rfc2822(value).ok()) {
+        metadata.modified = Some(SystemTime::from(modified));
+    }
+
+    // Servers ignoring the range send the whole file.
+    let mut reader = response.into_reader();
+    if status == 200 {
+        io::copy(&mut reader.by_ref().take(start), &mut io::sink())?;
+    }
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Reads LAS header of the URL, fetching only the header, VLRs and EVLRs.
+pub fn read_header(url: &Path) -> Result<(Header, RemoteMetadata), LasBoundsError> {
+
+    let is_s3 = s3::is_s3_url(url);
+    let url = url.to_string_lossy();
+    let mut metadata = RemoteMetadata::default();
+    let header = header::read_header_ranges(|start, len| if is_s3 {
+        s3::fetch_range(&url, start, len, &mut metadata)
+    } else {
+        fetch_range(&url, start, len, &mut metadata)
+    })?;
+
+    Ok((header, metadata))
+}
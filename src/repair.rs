@@ -0,0 +1,39 @@
+//! In place repair of LAS headers.
+
+use crate::LasBoundsError;
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+
+/// Offset of max X in the public header block, followed by min X, max Y, min Y, max Z and min Z.
+const BOUNDS_OFFSET: u64 = 179;
+
+/// Path of the copy made before the header is changed, e.g. `tile.las.bak`.
+pub fn backup_path(path: &Path) -> PathBuf {
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Overwrites bounds in the header, other bytes of the file stay the same, so this works also
+/// for LAZ files. Existing backup is kept, as it is the older copy.
+pub fn write_bounds(path: &Path, bounds: &las::Bounds, backup: bool) -> Result<(), LasBoundsError> {
+
+    if backup {
+        let backup = backup_path(path);
+        if !backup.exists() {
+            fs::copy(path, &backup)?;
+        }
+    }
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(BOUNDS_OFFSET))?;
+    for value in &[bounds.max.x, bounds.min.x, bounds.max.y, bounds.min.y, bounds.max.z, bounds.min.z] {
+        file.write_all(&value.to_le_bytes())?;
+    }
+    file.sync_all()?;
+
+    Ok(())
+}
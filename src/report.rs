@@ -0,0 +1,225 @@
+//! HTML report for sharing QA results: Leaflet map of footprints colored by density, sortable table
+//! of attributes and summary statistics. The page is a single file with the data embedded, Leaflet
+//! and map tiles are loaded from the web when it is opened. Offline, the table and statistics are
+//! shown without the map.
+
+use crate::{BoundsRecord, BoundsWriter, LasBoundsError};
+use crate::fields::{self, Field, Value as FieldValue};
+use crate::ogr;
+use crate::stac;
+use crate::stats::ArchiveStats;
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{TITLE}}</title>
+<link rel="stylesheet" href="https://unpkg.com/leaflet@1.7.1/dist/leaflet.css">
+<script src="https://unpkg.com/leaflet@1.7.1/dist/leaflet.js"></script>
+<style>
+body { font-family: sans-serif; margin: 1em; }
+#map { height: 60vh; }
+table { border-collapse: collapse; margin-top: 1em; }
+th, td { border: 1px solid #ccc; padding: 2px 6px; text-align: left; }
+th { cursor: pointer; background: #eee; }
+tr.selected { background: #ffd; }
+.legend { background: #fff; padding: 4px 8px; }
+</style>
+</head>
+<body>
+<h1>{{TITLE}}</h1>
+<pre>{{SUMMARY}}</pre>
+<div id="map"></div>
+<table id="attributes"><thead></thead><tbody></tbody></table>
+<script>
+var data = {{DATA}};
+var densities = data.features.map(function (f) { return f.density; }).filter(function (d) { return d !== null; });
+var min = Math.min.apply(null, densities), max = Math.max.apply(null, densities);
+
+// Blue for the lowest density to red for the highest, grey when unknown.
+function color(density) {
+    if (density === null) {
+        return '#888';
+    }
+    var t = max > min ? (density - min) / (max - min) : 0.5;
+    return 'hsl(' + Math.round(240 * (1 - t)) + ', 90%, 45%)';
+}
+
+// Leaflet is loaded from the web, offline the table and statistics are shown without the map.
+var map = null, layers = [];
+if (window.L === undefined) {
+    var placeholder = document.getElementById('map');
+    placeholder.style.height = 'auto';
+    placeholder.textContent = 'The map needs Leaflet from unpkg.com, which could not be loaded.';
+} else {
+    map = L.map('map');
+    L.tileLayer('https://{s}.tile.openstreetmap.org/{z}/{x}/{y}.png', {
+        attribution: '&copy; OpenStreetMap contributors'
+    }).addTo(map);
+    var footprints = L.geoJSON(data, {
+        style: function (f) { return { color: color(f.density), weight: 1, fillOpacity: 0.4 }; },
+        onEachFeature: function (f, layer) {
+            layers[f.id] = layer;
+            layer.bindPopup(data.fields.map(function (name) {
+                return '<b>' + text(name) + '</b>: ' + text(f.properties[name]);
+            }).join('<br>'));
+        }
+    }).addTo(map);
+    if (data.features.length > 0) {
+        map.fitBounds(footprints.getBounds());
+    } else {
+        map.setView([0, 0], 1);
+    }
+    if (densities.length > 0) {
+        var legend = L.control({ position: 'bottomright' });
+        legend.onAdd = function () {
+            var div = L.DomUtil.create('div', 'legend');
+            div.innerHTML = 'Density<br><span style="color:' + color(min) + '">&#9632;</span> ' + min.toFixed(2) +
+                '<br><span style="color:' + color(max) + '">&#9632;</span> ' + max.toFixed(2);
+            return div;
+        };
+        legend.addTo(map);
+    }
+}
+
+function text(value) {
+    if (value === null || value === undefined) {
+        return '';
+    }
+    var div = document.createElement('div');
+    div.textContent = typeof value === 'number' && !Number.isInteger(value) ? value.toFixed(3) : value;
+    return div.innerHTML;
+}
+
+var rows = data.features.slice();
+var sortKey = null, ascending = true;
+
+function compare(a, b) {
+    var x = a.properties[sortKey], y = b.properties[sortKey];
+    if (x === y) { return 0; }
+    if (x === null || x === undefined) { return 1; }
+    if (y === null || y === undefined) { return -1; }
+    var order = typeof x === 'number' && typeof y === 'number' ? x - y : String(x).localeCompare(String(y));
+    return ascending ? order : -order;
+}
+
+function render() {
+    var head = '<tr>' + data.fields.map(function (name, i) {
+        var arrow = name === sortKey ? (ascending ? ' &#9650;' : ' &#9660;') : '';
+        return '<th data-index="' + i + '">' + text(name) + arrow + '</th>';
+    }).join('') + '</tr>';
+    document.querySelector('#attributes thead').innerHTML = head;
+    document.querySelector('#attributes tbody').innerHTML = rows.map(function (f) {
+        return '<tr data-id="' + f.id + '">' + data.fields.map(function (name) {
+            return '<td>' + text(f.properties[name]) + '</td>';
+        }).join('') + '</tr>';
+    }).join('');
+}
+
+document.querySelector('#attributes thead').addEventListener('click', function (e) {
+    var index = e.target.getAttribute('data-index');
+    if (index === null) { return; }
+    var key = data.fields[index];
+    ascending = key === sortKey ? !ascending : true;
+    sortKey = key;
+    rows.sort(compare);
+    render();
+});
+document.querySelector('#attributes tbody').addEventListener('click', function (e) {
+    var row = e.target.closest('tr');
+    if (row === null) { return; }
+    document.querySelectorAll('#attributes tr.selected').forEach(function (r) { r.classList.remove('selected'); });
+    row.classList.add('selected');
+    if (map === null) { return; }
+    var layer = layers[row.getAttribute('data-id')];
+    map.fitBounds(layer.getBounds());
+    layer.openPopup();
+});
+render();
+</script>
+</body>
+</html>
+"#;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn json_value(value: Option<FieldValue>) -> Value {
+
+    match value {
+        Some(FieldValue::Integer(i)) => json!(i),
+        Some(FieldValue::Real(r)) => json!(r),
+        Some(FieldValue::String(s)) => json!(s),
+        None => Value::Null
+    }
+}
+
+/// Collects footprints in WGS84 with their attributes and writes the page on finish.
+pub struct ReportWriter {
+    path: PathBuf,
+    fields: Vec<Field>,
+    /// Transformation of footprints into WGS84.
+    transform: CoordTransform,
+    features: Vec<Value>,
+    stats: ArchiveStats
+}
+
+impl ReportWriter {
+
+    /// `srs` is CRS of the input files. Fails when the report exists and overwrite was not requested.
+    pub fn new<P: Into<PathBuf>>(path: P, srs: &SpatialRef, fields: &[Field], overwrite: bool) -> Result<Self, LasBoundsError> {
+
+        let path = path.into();
+        if path.exists() && !overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite", path.to_string_lossy()).into());
+        }
+
+        let wgs84 = SpatialRef::from_epsg(4326)?;
+        ogr::set_traditional_axis_order(&wgs84);
+        let transform = CoordTransform::new(srs, &wgs84)?;
+
+        Ok(ReportWriter { path, fields: fields.to_vec(), transform, features: Vec::new(), stats: ArchiveStats::default() })
+    }
+}
+
+impl BoundsWriter for ReportWriter {
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        let (geometry, _) = stac::wgs84_geometry(&self.transform, record)?;
+        let properties: serde_json::Map<String, Value> = self.fields.iter()
+            .map(|field| (field.name.to_string(), json_value(fields::value(record, field))))
+            .collect();
+        self.features.push(json!({
+            "type": "Feature",
+            "id": self.features.len(),
+            "geometry": geometry,
+            "properties": properties,
+            "density": json_value(fields::value(record, &fields::DENSITY))
+        }));
+        self.stats.add(record);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        let title = self.path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let data = json!({
+            "type": "FeatureCollection",
+            "fields": self.fields.iter().map(|field| field.name.to_string()).collect::<Vec<_>>(),
+            "features": std::mem::take(&mut self.features)
+        });
+        // Text of the script element must not close it.
+        let page = TEMPLATE.replace("{{TITLE}}", &escape(&title))
+                           .replace("{{SUMMARY}}", &escape(&self.stats.report()))
+                           .replace("{{DATA}}", &data.to_string().replace("</", "<\\/"));
+        fs::write(&self.path, page)?;
+        Ok(())
+    }
+}
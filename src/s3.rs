@@ -0,0 +1,135 @@
+//! LAS objects in S3 buckets, listed by prefix and read with ranged GETs. Credentials come from
+//! the standard AWS chain (environment, profile files, container or instance metadata).
+
+use crate::LasBoundsError;
+use crate::remote::RemoteMetadata;
+use chrono::DateTime;
+use rusoto_core::Region;
+use rusoto_s3::{GetObjectRequest, ListObjectsV2Request, S3, S3Client};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::AsyncReadExt;
+use tokio::runtime::Runtime;
+
+
+/// Client with the runtime driving its requests, one per thread so files are read in parallel.
+struct Context {
+    runtime: Runtime,
+    client: S3Client
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = RefCell::new(None);
+}
+
+fn with_context<T, F>(f: F) -> Result<T, LasBoundsError>
+    where F: FnOnce(&mut Context) -> Result<T, LasBoundsError> {
+
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        if context.is_none() {
+            let runtime = tokio::runtime::Builder::new()
+                              .basic_scheduler()
+                              .enable_all()
+                              .build()?;
+            // Region comes from AWS_DEFAULT_REGION or AWS_REGION.
+            *context = Some(Context { runtime, client: S3Client::new(Region::default()) });
+        }
+        f(context.as_mut().unwrap())
+    })
+}
+
+/// Whether the input path is an `s3://` URL.
+pub fn is_s3_url(path: &Path) -> bool {
+    path.to_string_lossy().starts_with("s3://")
+}
+
+/// Splits `s3://bucket/key` into bucket and key.
+fn split_url(url: &str) -> Result<(String, String), LasBoundsError> {
+
+    let rest = url.strip_prefix("s3://").ok_or_else(|| format!("Invalid S3 URL: {}", url))?;
+    let (bucket, key) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => (rest, "")
+    };
+    if bucket.is_empty() {
+        return Err(format!("Invalid S3 URL, bucket is missing: {}", url).into());
+    }
+
+    Ok((bucket.to_string(), key.to_string()))
+}
+
+fn is_las_key(key: &str) -> bool {
+
+    let key = key.to_ascii_lowercase();
+    key.ends_with(".las") || key.ends_with(".laz")
+}
+
+/// Lists LAS/LAZ objects under `s3://bucket/prefix` as `s3://` URLs, sorted by key.
+pub fn list_objects(url: &str) -> Result<Vec<PathBuf>, LasBoundsError> {
+
+    let (bucket, prefix) = split_url(url)?;
+    with_context(|context| {
+        let mut urls = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: bucket.clone(),
+                prefix: Some(prefix.clone()).filter(|prefix| !prefix.is_empty()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+            let output = context.runtime.block_on(context.client.list_objects_v2(request))
+                                .map_err(|e| format!("Listing {} failed: {}", url, e))?;
+
+            urls.extend(output.contents.unwrap_or_default().into_iter()
+                              .filter_map(|object| object.key)
+                              .filter(|key| is_las_key(key))
+                              .map(|key| PathBuf::from(format!("s3://{}/{}", bucket, key))));
+
+            match output.next_continuation_token {
+                Some(token) if output.is_truncated == Some(true) => continuation_token = Some(token),
+                _ => break
+            }
+        }
+
+        urls.sort();
+        Ok(urls)
+    })
+}
+
+/// Fetches up to `len` bytes from `start`, `u64::max_value()` for the rest of the object.
+pub fn fetch_range(url: &str, start: u64, len: u64, metadata: &mut RemoteMetadata) -> Result<Vec<u8>, LasBoundsError> {
+
+    let (bucket, key) = split_url(url)?;
+    let range = if len == u64::max_value() {
+        format!("bytes={}-", start)
+    } else {
+        format!("bytes={}-{}", start, start + len.max(1) - 1)
+    };
+    let request = GetObjectRequest { bucket, key, range: Some(range), ..Default::default() };
+
+    with_context(|context| {
+        let client = &context.client;
+        let (output, bytes) = context.runtime.block_on(async {
+            let mut output = client.get_object(request).await.map_err(|e| format!("Reading {} failed: {}", url, e))?;
+            let mut bytes = Vec::new();
+            if let Some(body) = output.body.take() {
+                body.into_async_read().read_to_end(&mut bytes).await?;
+            }
+            Ok::<_, LasBoundsError>((output, bytes))
+        })?;
+
+        // `Content-Range: bytes 0-374/123456`
+        metadata.size = output.content_range.as_ref()
+                              .and_then(|range| range.rsplit('/').next())
+                              .and_then(|size| size.parse().ok())
+                              .or(metadata.size);
+        if let Some(modified) = output.last_modified.as_ref().and_then(|value| DateTime::parse_from_rfc2822(value).ok()) {
+            metadata.modified = Some(SystemTime::from(modified));
+        }
+
+        Ok(bytes)
+    })
+}
@@ -0,0 +1,171 @@
+//! Single pass over point records collecting everything requested from points.
+
+use crate::LasBoundsError;
+use crate::density::{self, CellCounts};
+use crate::footprint::{self, Point2};
+use crate::hexbin::{self, HexCounts};
+use crate::input;
+use las::Reader;
+use las::reader::Read;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+
+#[derive(Clone)]
+pub struct ScanOptions {
+    /// Only every n-th point is scanned.
+    pub thin: usize,
+    /// Collect XY coordinates for footprint computation.
+    pub xy: bool,
+    /// Keep only convex hull of the XY coordinates collected so far, enough for hull footprints.
+    pub hull: bool,
+    /// Collect GPS time range.
+    pub gps_time: bool,
+    /// Count points per classification.
+    pub classes: bool,
+    /// Count points per return number.
+    pub returns: bool,
+    /// Compute actual bounds of points.
+    pub bounds: bool,
+    /// Count points in hexagons of the side length.
+    pub hexbin: Option<f64>,
+    /// Count points in square cells of the size.
+    pub density_cell: Option<f64>,
+    /// Collect point source IDs.
+    pub point_sources: bool,
+    /// Collect XY coordinates by point source ID for footprints of flightlines.
+    pub flightlines: bool,
+    /// Classification codes of points whose XY coordinates are collected, all points when empty.
+    pub footprint_classes: Vec<u8>
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions { thin: 1, xy: false, hull: false, gps_time: false, classes: false, returns: false, bounds: false, hexbin: None,
+                      density_cell: None, point_sources: false, flightlines: false,
+                      footprint_classes: Vec::new() }
+    }
+}
+
+impl ScanOptions {
+
+    /// Whether point records have to be read at all.
+    pub fn is_needed(&self) -> bool {
+        self.xy || self.gps_time || self.classes || self.returns || self.bounds || self.hexbin.is_some() || self.density_cell.is_some()
+            || self.point_sources || self.flightlines
+    }
+}
+
+#[derive(Default)]
+pub struct PointStats {
+    pub xy: Vec<Point2>,
+    /// Minimum and maximum GPS time, `None` when the point format has no GPS time.
+    pub gps_time: Option<(f64, f64)>,
+    /// Number of scanned points by classification code, empty when not requested.
+    pub class_counts: Vec<u64>,
+    /// Number of scanned points by return number, empty when not requested.
+    pub return_counts: Vec<u64>,
+    /// Bounds of scanned points, `None` when not requested or there are no points.
+    pub bounds: Option<las::Bounds>,
+    /// Number of points by hexagon, estimated from scanned points when thinned. Empty when not requested.
+    pub hex_counts: HexCounts,
+    /// Number of points by raster cell, estimated from scanned points when thinned. Empty when not requested.
+    pub cell_counts: CellCounts,
+    /// Distinct point source IDs of scanned points, flightlines of airborne surveys. Empty when not requested.
+    pub point_sources: BTreeSet<u16>,
+    /// XY coordinates of scanned points by point source ID, empty when not requested.
+    pub flightline_xy: BTreeMap<u16, Vec<Point2>>
+}
+
+/// Collected XY coordinates are reduced to their convex hull whenever their number reaches a power
+/// of two from this on, so memory of hull footprints does not grow with the file.
+const HULL_BATCH: usize = 1 << 16;
+
+/// Adds XY coordinates of a footprint point, see [`ScanOptions::hull`].
+pub fn push_xy(xy: &mut Vec<Point2>, point: Point2, options: &ScanOptions) {
+
+    xy.push(point);
+    if options.hull && xy.len() >= HULL_BATCH && xy.len().is_power_of_two() {
+        *xy = footprint::hull_ring(std::mem::take(xy));
+    }
+}
+
+/// Whether header bounds equal bounds of points, allowing difference of one scale step since header
+/// values are not necessarily multiples of it.
+pub fn header_bounds_match(header: &las::Header, actual: &las::Bounds) -> bool {
+
+    let declared = header.bounds();
+    let scale = header.transforms();
+    let close = |a: f64, b: f64, scale: f64| (a - b).abs() <= scale;
+    close(declared.min.x, actual.min.x, scale.x.scale) && close(declared.max.x, actual.max.x, scale.x.scale) &&
+        close(declared.min.y, actual.min.y, scale.y.scale) && close(declared.max.y, actual.max.y, scale.y.scale) &&
+        close(declared.min.z, actual.min.z, scale.z.scale) && close(declared.max.z, actual.max.z, scale.z.scale)
+}
+
+fn update_range(range: &mut Option<(f64, f64)>, value: f64) {
+
+    *range = Some(match *range {
+        Some((min, max)) => (min.min(value), max.max(value)),
+        None => (value, value)
+    });
+}
+
+pub fn scan_points(las: &Path, options: &ScanOptions) -> Result<PointStats, LasBoundsError> {
+
+    let mut reader = Reader::from_path(las)?;
+    let mut stats = PointStats::default();
+    if options.classes {
+        stats.class_counts = vec![0; 256];
+    }
+    if options.returns {
+        stats.return_counts = vec![0; 16];
+    }
+    let mut bounds = las::Bounds::default();
+    let mut count = 0u64;
+
+    for (i, point) in reader.points().enumerate() {
+        let point = point?;
+        if i % options.thin != 0 {
+            continue;
+        }
+
+        let class = u8::from(point.classification);
+        let footprint_point = options.footprint_classes.is_empty() || options.footprint_classes.contains(&class);
+        if options.xy && footprint_point {
+            push_xy(&mut stats.xy, (point.x, point.y), options);
+        }
+        if options.gps_time {
+            if let Some(gps_time) = point.gps_time {
+                update_range(&mut stats.gps_time, gps_time);
+            }
+        }
+        if options.classes {
+            stats.class_counts[class as usize] += 1;
+        }
+        if options.returns {
+            stats.return_counts[(point.return_number & 0x0f) as usize] += 1;
+        }
+        if options.bounds {
+            input::grow(&mut bounds, point.x, point.y, point.z);
+        }
+        if let Some(size) = options.hexbin {
+            *stats.hex_counts.entry(hexbin::hex_of(point.x, point.y, size)).or_insert(0) += options.thin as u64;
+        }
+        if let Some(size) = options.density_cell {
+            *stats.cell_counts.entry(density::cell_of(point.x, point.y, size)).or_insert(0) += options.thin as u64;
+        }
+        if options.point_sources {
+            stats.point_sources.insert(point.point_source_id);
+        }
+        if options.flightlines && footprint_point {
+            stats.flightline_xy.entry(point.point_source_id).or_insert_with(Vec::new).push((point.x, point.y));
+        }
+        count += 1;
+    }
+
+    if options.bounds && count > 0 {
+        stats.bounds = Some(bounds);
+    }
+
+    Ok(stats)
+}
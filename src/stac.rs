@@ -0,0 +1,292 @@
+//! Static STAC catalog: Item with the point cloud extension per input file and Collection linking them.
+
+use crate::{BoundsRecord, BoundsWriter, LasBoundsError};
+use crate::fields::{self, Value as FieldValue};
+use crate::footprint::Ring;
+use crate::input::InputFormat;
+use crate::ogr;
+use crate::remote;
+use crate::writer;
+use chrono::{DateTime, SecondsFormat, Utc};
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+pub const STAC_VERSION: &str = "1.0.0-beta.2";
+const COLLECTION_FILE_NAME: &str = "collection.json";
+
+/// GPS epoch 1980-01-06T00:00:00Z as Unix time.
+const GPS_EPOCH: i64 = 315_964_800;
+/// Difference of GPS time and UTC since 2017.
+const GPS_LEAP_SECONDS: i64 = 18;
+/// Offset of adjusted standard GPS time.
+const ADJUSTED_GPS_OFFSET: f64 = 1e9;
+
+/// Writes STAC Item `<file stem>.json` for each record into a directory, and on finish
+/// `collection.json` with extent of all items.
+pub struct StacWriter {
+    dir: PathBuf,
+    collection_id: String,
+    /// Transformation of footprints into WGS84.
+    transform: CoordTransform,
+    item_ids: Vec<String>,
+    bbox: Option<[f64; 4]>,
+    interval: Option<(DateTime<Utc>, DateTime<Utc>)>
+}
+
+impl StacWriter {
+
+    /// Creates the directory if needed, `srs` is CRS of the input files.
+    pub fn new<P: Into<PathBuf>>(dir: P, srs: &SpatialRef) -> Result<Self, LasBoundsError> {
+
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let collection_id = fs::canonicalize(&dir)?.file_name()
+                                .map_or("las-bounds".to_string(), |name| name.to_string_lossy().into_owned());
+
+        let wgs84 = SpatialRef::from_epsg(4326)?;
+        ogr::set_traditional_axis_order(&wgs84);
+        let transform = CoordTransform::new(srs, &wgs84)?;
+
+        Ok(StacWriter { dir, collection_id, transform, item_ids: Vec::new(), bbox: None, interval: None })
+    }
+
+    fn extend_extent(&mut self, bbox: [f64; 4], interval: Option<(DateTime<Utc>, DateTime<Utc>)>) {
+
+        self.bbox = Some(match self.bbox {
+            Some(b) => [b[0].min(bbox[0]), b[1].min(bbox[1]), b[2].max(bbox[2]), b[3].max(bbox[3])],
+            None => bbox
+        });
+        if let Some((start, end)) = interval {
+            self.interval = Some(match self.interval {
+                Some((s, e)) => (s.min(start), e.max(end)),
+                None => (start, end)
+            });
+        }
+    }
+}
+
+fn transform_ring(transform: &CoordTransform, ring: &Ring) -> Result<Vec<Value>, LasBoundsError> {
+
+    let mut xs: Vec<f64> = ring.iter().map(|p| p.0).collect();
+    let mut ys: Vec<f64> = ring.iter().map(|p| p.1).collect();
+    let mut zs = vec![0.0; ring.len()];
+    transform.transform_coords(&mut xs, &mut ys, &mut zs)?;
+
+    Ok(xs.into_iter().zip(ys).map(|(x, y)| json!([x, y])).collect())
+}
+
+/// GeoJSON MultiPolygon geometry of the footprint transformed into WGS84 and its bounding box.
+pub(crate) fn wgs84_geometry(transform: &CoordTransform, record: &BoundsRecord) -> Result<(Value, [f64; 4]), LasBoundsError> {
+
+    let footprint = writer::densified_footprint(record);
+    let mut polygons = Vec::new();
+    for polygon in &footprint.polygons {
+        let mut rings = vec![Value::Array(transform_ring(transform, &polygon.exterior)?)];
+        for interior in &polygon.interiors {
+            rings.push(Value::Array(transform_ring(transform, interior)?));
+        }
+        polygons.push(Value::Array(rings));
+    }
+
+    let mut bbox = [std::f64::INFINITY, std::f64::INFINITY, std::f64::NEG_INFINITY, std::f64::NEG_INFINITY];
+    for polygon in &polygons {
+        for point in polygon[0].as_array().into_iter().flatten() {
+            let (x, y) = (point[0].as_f64().unwrap_or_default(), point[1].as_f64().unwrap_or_default());
+            bbox = [bbox[0].min(x), bbox[1].min(y), bbox[2].max(x), bbox[3].max(y)];
+        }
+    }
+
+    Ok((json!({ "type": "MultiPolygon", "coordinates": polygons }), bbox))
+}
+
+/// Item id, the file name without extension, or the dataset directory name for EPT.
+pub fn item_id(record: &BoundsRecord) -> String {
+
+    let path = match record.format {
+        InputFormat::Ept => record.path.parent().unwrap_or(&record.path),
+        _ => &record.path
+    };
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Dimensions of the point format, as `pc:schemas` entries.
+fn point_schemas(header: &las::Header) -> Vec<Value> {
+
+    let format = header.point_format();
+    let dim = |name: &str, size: u16, kind: &str| json!({ "name": name, "size": size, "type": kind });
+
+    let mut schemas = vec![
+        dim("X", 4, "signed"),
+        dim("Y", 4, "signed"),
+        dim("Z", 4, "signed"),
+        dim("Intensity", 2, "unsigned"),
+        dim("ReturnNumber", 1, "unsigned"),
+        dim("NumberOfReturns", 1, "unsigned"),
+        dim("ScanDirectionFlag", 1, "unsigned"),
+        dim("EdgeOfFlightLine", 1, "unsigned"),
+        dim("Classification", 1, "unsigned"),
+        dim(if format.is_extended { "ScanAngle" } else { "ScanAngleRank" }, if format.is_extended { 2 } else { 1 }, "signed"),
+        dim("UserData", 1, "unsigned"),
+        dim("PointSourceId", 2, "unsigned")
+    ];
+    if format.is_extended {
+        schemas.push(dim("ScannerChannel", 1, "unsigned"));
+    }
+    if format.has_gps_time {
+        schemas.push(dim("GpsTime", 8, "floating"));
+    }
+    if format.has_color {
+        schemas.extend(vec![dim("Red", 2, "unsigned"), dim("Green", 2, "unsigned"), dim("Blue", 2, "unsigned")]);
+    }
+    if format.has_nir {
+        schemas.push(dim("Infrared", 2, "unsigned"));
+    }
+    if format.extra_bytes > 0 {
+        schemas.push(dim("ExtraBytes", format.extra_bytes, "unsigned"));
+    }
+
+    schemas
+}
+
+fn encoding(record: &BoundsRecord) -> &'static str {
+
+    match record.format {
+        InputFormat::Las if record.header.point_format().is_compressed => "LASzip",
+        InputFormat::Las => "binary",
+        InputFormat::Ept => "ept",
+        InputFormat::Xyz => "ascii",
+        InputFormat::Ply => "ply"
+    }
+}
+
+/// Asset link of the file, absolute so the item can be moved away from the working directory.
+fn asset_href(path: &Path) -> String {
+
+    if remote::is_url(path) {
+        return path.to_string_lossy().into_owned();
+    }
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().into_owned()
+}
+
+/// UTC time of adjusted standard GPS time, ignoring leap seconds before 2017.
+fn gps_datetime(adjusted: f64) -> DateTime<Utc> {
+
+    let seconds = adjusted + ADJUSTED_GPS_OFFSET;
+    let unix = GPS_EPOCH + seconds.floor() as i64 - GPS_LEAP_SECONDS;
+    let nanos = ((seconds - seconds.floor()) * 1e9) as u32;
+    DateTime::from_utc(chrono::NaiveDateTime::from_timestamp(unix, nanos), Utc)
+}
+
+/// Acquisition interval from GPS times, known only for files with standard GPS time
+/// scanned for GPS time range. Week time does not tell the week.
+fn acquisition_interval(record: &BoundsRecord) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+
+    if record.format != InputFormat::Las || record.header.gps_time_type() != las::GpsTimeType::Standard {
+        return None;
+    }
+    let (start, end) = record.stats.gps_time?;
+    Some((gps_datetime(start), gps_datetime(end)))
+}
+
+fn format_datetime(datetime: DateTime<Utc>) -> Value {
+    json!(datetime.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+impl BoundsWriter for StacWriter {
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        let id = item_id(record);
+        let (geometry, bbox) = wgs84_geometry(&self.transform, record)?;
+
+        // Without acquisition time the modification time of the file stands for it.
+        let interval = acquisition_interval(record);
+        let datetime = match (interval, record.modified) {
+            (Some((start, _)), _) => format_datetime(start),
+            (None, Some(modified)) => format_datetime(DateTime::<Utc>::from(modified)),
+            (None, None) => Value::Null
+        };
+
+        let mut properties = json!({
+            "datetime": datetime,
+            "pc:count": record.header.number_of_points(),
+            "pc:type": "lidar",
+            "pc:encoding": encoding(record),
+            "pc:schemas": point_schemas(&record.header)
+        });
+        if let Some(FieldValue::Real(density)) = fields::value(record, &fields::DENSITY) {
+            properties["pc:density"] = json!(density);
+        }
+        if let Some((start, end)) = interval {
+            properties["start_datetime"] = format_datetime(start);
+            properties["end_datetime"] = format_datetime(end);
+        }
+
+        let item = json!({
+            "type": "Feature",
+            "stac_version": STAC_VERSION,
+            "stac_extensions": ["pointcloud"],
+            "id": id,
+            "bbox": bbox,
+            "geometry": geometry,
+            "properties": properties,
+            "collection": self.collection_id,
+            "links": [
+                { "rel": "collection", "href": format!("./{}", COLLECTION_FILE_NAME) },
+                { "rel": "parent", "href": format!("./{}", COLLECTION_FILE_NAME) },
+                { "rel": "root", "href": format!("./{}", COLLECTION_FILE_NAME) }
+            ],
+            "assets": {
+                "data": { "href": asset_href(&record.path), "roles": ["data"] }
+            }
+        });
+
+        write_json(&self.dir.join(format!("{}.json", id)), &item)?;
+        self.extend_extent(bbox, interval.or_else(|| record.modified.map(|modified| {
+            let modified = DateTime::<Utc>::from(modified);
+            (modified, modified)
+        })));
+        self.item_ids.push(id);
+        Ok(())
+    }
+
+    /// Writes the Collection with union of item extents.
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        let mut links = vec![
+            json!({ "rel": "self", "href": format!("./{}", COLLECTION_FILE_NAME) }),
+            json!({ "rel": "root", "href": format!("./{}", COLLECTION_FILE_NAME) })
+        ];
+        links.extend(self.item_ids.iter().map(|id| json!({ "rel": "item", "href": format!("./{}.json", id) })));
+
+        let interval = match self.interval {
+            Some((start, end)) => json!([[format_datetime(start), format_datetime(end)]]),
+            None => json!([[null, null]])
+        };
+        let collection = json!({
+            "type": "Collection",
+            "stac_version": STAC_VERSION,
+            "stac_extensions": ["pointcloud"],
+            "id": self.collection_id,
+            "description": format!("Point cloud tiles of {}", self.collection_id),
+            "license": "proprietary",
+            "extent": {
+                "spatial": { "bbox": [self.bbox.map_or(Value::Null, |bbox| json!(bbox))] },
+                "temporal": { "interval": interval }
+            },
+            "links": links
+        });
+
+        write_json(&self.dir.join(COLLECTION_FILE_NAME), &collection)
+    }
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<(), LasBoundsError> {
+
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Could not write {}: {}", path.to_string_lossy(), e))?;
+    fs::write(path, json)?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Archive-wide summary of indexed files: totals, density range, CRS and LAS version breakdown, extent.
+
+use crate::BoundsRecord;
+use crate::crs;
+use crate::fields::{self, Value as FieldValue};
+use crate::input;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+
+/// Summary of the records added so far.
+#[derive(Default)]
+pub struct ArchiveStats {
+    pub files: usize,
+    pub points: u64,
+    /// Sum of footprint areas in CRS units.
+    pub area: f64,
+    /// Minimum and maximum density of files with positive area.
+    pub density: Option<(f64, f64)>,
+    /// Number of files by CRS, `none` for files without CRS.
+    pub crss: BTreeMap<String, usize>,
+    /// Number of files by LAS version, e.g. `1.4`.
+    pub versions: BTreeMap<String, usize>,
+    /// Bounds of all files, `None` when there are none.
+    pub extent: Option<las::Bounds>
+}
+
+impl ArchiveStats {
+
+    pub fn add(&mut self, record: &BoundsRecord) {
+
+        self.files += 1;
+        self.points += record.header.number_of_points();
+        self.area += record.area;
+        if let Some(FieldValue::Real(density)) = fields::value(record, &fields::DENSITY) {
+            self.density = Some(match self.density {
+                Some((min, max)) => (min.min(density), max.max(density)),
+                None => (density, density)
+            });
+        }
+
+        let crs = crs::detect_crs(&record.header).map_or("none".to_string(), |crs| crs.to_string());
+        *self.crss.entry(crs).or_insert(0) += 1;
+        if let Some(FieldValue::String(version)) = fields::value(record, &fields::LAS_VERSION) {
+            *self.versions.entry(version).or_insert(0) += 1;
+        }
+
+        let bounds = record.header.bounds();
+        let extent = self.extent.get_or_insert(bounds);
+        input::grow(extent, bounds.min.x, bounds.min.y, bounds.min.z);
+        input::grow(extent, bounds.max.x, bounds.max.y, bounds.max.z);
+    }
+
+    /// Number of points per unit of area over all files, `None` when the area is 0.
+    pub fn mean_density(&self) -> Option<f64> {
+        Some(self.points as f64 / self.area).filter(|_| self.area > 0.0)
+    }
+
+    pub fn to_json(&self) -> Value {
+
+        json!({
+            "files": self.files,
+            "points": self.points,
+            "area": self.area,
+            "density": {
+                "min": self.density.map(|d| d.0),
+                "mean": self.mean_density(),
+                "max": self.density.map(|d| d.1)
+            },
+            "crs": self.crss,
+            "las_version": self.versions,
+            "extent": self.extent.map(|b| json!({
+                "min": [b.min.x, b.min.y, b.min.z],
+                "max": [b.max.x, b.max.y, b.max.z]
+            }))
+        })
+    }
+
+    /// Human readable summary, one value per line.
+    pub fn report(&self) -> String {
+
+        let optional = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.3}", v));
+        let breakdown = |counts: &BTreeMap<String, usize>| counts.iter()
+            .map(|(key, count)| format!("\n  {}: {} files", key, count))
+            .collect::<String>();
+
+        let mut report = format!("Files: {}\nPoints: {}\nArea: {:.3}\nDensity min/mean/max: {} / {} / {}",
+                                 self.files, self.points, self.area, optional(self.density.map(|d| d.0)),
+                                 optional(self.mean_density()), optional(self.density.map(|d| d.1)));
+        report += &format!("\nCRS:{}\nLAS version:{}", breakdown(&self.crss), breakdown(&self.versions));
+        if let Some(b) = self.extent {
+            report += &format!("\nExtent: {} {} {} - {} {} {}", b.min.x, b.min.y, b.min.z, b.max.x, b.max.y, b.max.z);
+        }
+        report
+    }
+}
@@ -0,0 +1,120 @@
+//! Regular tiling scheme covering an archive, the planning input for retiling.
+
+use crate::LasBoundsError;
+use crate::ogr::{self, OutputDataset};
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{FieldValue, Geometry, OGRFieldType, OGRwkbGeometryType};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+
+pub const TILES_LAYER_NAME: &str = "tiles";
+
+/// Grids with more tiles are refused, as they come from too small size for the extent.
+const MAX_TILES: i64 = 10_000_000;
+
+/// Square grid cell, column and row count from the origin in tile size units.
+pub struct Tile {
+    pub column: i64,
+    pub row: i64,
+    /// Paths of files whose bounds intersect the tile.
+    pub files: Vec<String>
+}
+
+impl Tile {
+
+    /// Identifier from column and row, e.g. `512_6201`.
+    pub fn id(&self) -> String {
+        format!("{}_{}", self.column, self.row)
+    }
+
+    /// Minimum x, y and maximum x, y.
+    pub fn bounds(&self, size: f64) -> (f64, f64, f64, f64) {
+        let (x, y) = (self.column as f64 * size, self.row as f64 * size);
+        (x, y, x + size, y + size)
+    }
+}
+
+/// Tiles of `size` aligned to its multiples, covering bounds of all files, with files whose bounds
+/// intersect each tile. Files only touching a tile edge are not in that tile.
+pub fn tile_grid(files: &[(String, las::Bounds)], size: f64) -> Result<Vec<Tile>, LasBoundsError> {
+
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Maximum coordinates on tile edges belong to the tile before.
+    let first = |min: f64| (min / size).floor() as i64;
+    let last = |max: f64, first: i64| ((max / size).ceil() as i64).saturating_sub(1).max(first);
+
+    let column_min = files.iter().map(|(_, b)| first(b.min.x)).min().unwrap();
+    let row_min = files.iter().map(|(_, b)| first(b.min.y)).min().unwrap();
+    let column_max = files.iter().map(|(_, b)| last(b.max.x, first(b.min.x))).max().unwrap();
+    let row_max = files.iter().map(|(_, b)| last(b.max.y, first(b.min.y))).max().unwrap();
+    // Tiles of garbage coordinates may be far enough apart to overflow.
+    let columns = column_max.checked_sub(column_min).and_then(|columns| columns.checked_add(1));
+    let rows = row_max.checked_sub(row_min).and_then(|rows| rows.checked_add(1));
+    match (columns, rows) {
+        (Some(columns), Some(rows)) => match columns.checked_mul(rows) {
+            Some(count) if count <= MAX_TILES => (),
+            Some(count) => return Err(format!("Grid would have {} tiles, use larger tile size", count).into()),
+            None => return Err(format!("Grid of {}x{} tiles is too large, use larger tile size", columns, rows).into())
+        },
+        _ => return Err("Grid extent is too large, check bounds of the files".to_string().into())
+    }
+
+    let mut tiles = BTreeMap::new();
+    for row in row_min..=row_max {
+        for column in column_min..=column_max {
+            tiles.insert((row, column), Tile { column, row, files: Vec::new() });
+        }
+    }
+    for (path, bounds) in files {
+        let (c0, r0) = (first(bounds.min.x), first(bounds.min.y));
+        for row in r0..=last(bounds.max.y, r0) {
+            for column in c0..=last(bounds.max.x, c0) {
+                tiles.get_mut(&(row, column)).unwrap().files.push(path.clone());
+            }
+        }
+    }
+
+    Ok(tiles.into_iter().map(|(_, tile)| tile).collect())
+}
+
+/// Writes tiles as polygons with `tile_id`, `col`, `row`, number of intersecting files and their
+/// semicolon separated paths.
+pub fn write_tiles(driver_name: &str, path: &Path, srs: Option<&SpatialRef>, tiles: &[Tile], size: f64,
+                   overwrite: bool) -> Result<(), LasBoundsError> {
+
+    if path.exists() {
+        if !overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite", path.to_string_lossy()).into());
+        }
+        ogr::delete_dataset(driver_name, path)?;
+    }
+
+    let mut ds = OutputDataset::create(driver_name, path, &[])?;
+    let layer = ds.create_layer(TILES_LAYER_NAME, srs, OGRwkbGeometryType::wkbPolygon, &[])?;
+    layer.create_defn_fields(&[
+        ("tile_id", OGRFieldType::OFTString),
+        ("col", OGRFieldType::OFTInteger64),
+        ("row", OGRFieldType::OFTInteger64),
+        ("files", OGRFieldType::OFTInteger),
+        ("paths", OGRFieldType::OFTString)
+    ])?;
+
+    for tile in tiles {
+        let (x0, y0, x1, y1) = tile.bounds(size);
+        let geometry = Geometry::from_wkt(&format!("POLYGON (({x0} {y0},{x1} {y0},{x1} {y1},{x0} {y1},{x0} {y0}))",
+                                                   x0 = x0, y0 = y0, x1 = x1, y1 = y1))?;
+        layer.create_feature_fields(geometry, &["tile_id", "col", "row", "files", "paths"], &[
+            FieldValue::StringValue(tile.id()),
+            FieldValue::Integer64Value(tile.column),
+            FieldValue::Integer64Value(tile.row),
+            FieldValue::IntegerValue(tile.files.len() as i32),
+            FieldValue::StringValue(tile.files.join(";"))
+        ])?;
+    }
+
+    Ok(())
+}
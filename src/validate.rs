@@ -0,0 +1,197 @@
+//! LAS specification conformance checks on the raw header, so files the reader rejects are reported too.
+
+use crate::LasBoundsError;
+use crate::crs;
+use crate::ogr::{self, OutputDataset};
+use gdal::spatial_ref::SpatialRef;
+use gdal::vector::{FieldValue, Geometry, OGRFieldType, OGRwkbGeometryType};
+use std::fs::{self, File};
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+
+pub const VALIDATION_LAYER_NAME: &str = "validation";
+
+/// Bits of the point format byte marking LAZ compression.
+const COMPRESSION_BITS: u8 = 0xc0;
+
+/// Checked file with the spec violations found.
+pub struct Validation {
+    pub path: PathBuf,
+    /// Header bounds as `(min_x, min_y, max_x, max_y)`, `None` when the header is unreadable.
+    pub bounds: Option<(f64, f64, f64, f64)>,
+    pub violations: Vec<String>
+}
+
+impl Validation {
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Minimum LAS minor version of point formats 0 to 10.
+fn format_min_minor(format: u8) -> Option<u8> {
+
+    match format {
+        0 | 1 => Some(0),
+        2 | 3 => Some(2),
+        4 | 5 => Some(3),
+        6..=10 => Some(4),
+        _ => None
+    }
+}
+
+/// Whether any VLR or EVLR declares CRS.
+fn has_crs_vlr(file: &mut BufReader<File>, header: &las::raw::Header) -> Result<bool, LasBoundsError> {
+
+    file.seek(SeekFrom::Start(u64::from(header.header_size)))?;
+    for _ in 0..header.number_of_variable_length_records {
+        if crs::is_crs_vlr(&las::Vlr::new(las::raw::Vlr::read_from(&mut *file, false)?)) {
+            return Ok(true);
+        }
+    }
+    if let Some(evlr) = &header.evlr {
+        file.seek(SeekFrom::Start(evlr.start_of_first_evlr))?;
+        for _ in 0..evlr.number_of_evlrs {
+            if crs::is_crs_vlr(&las::Vlr::new(las::raw::Vlr::read_from(&mut *file, true)?)) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Checks point format, point count against size of point data (of uncompressed files), CRS VLR
+/// required by LAS 1.4 and scale factors.
+pub fn validate(path: &Path) -> Validation {
+
+    let mut validation = Validation { path: path.to_path_buf(), bounds: None, violations: Vec::new() };
+    if let Err(e) = check(path, &mut validation) {
+        validation.violations.push(format!("could not be read: {}", e));
+    }
+    validation
+}
+
+fn check(path: &Path, validation: &mut Validation) -> Result<(), LasBoundsError> {
+
+    let mut file = BufReader::new(File::open(path)?);
+    let header = las::raw::Header::read_from(&mut file)?;
+    validation.bounds = Some((header.min_x, header.min_y, header.max_x, header.max_y));
+    let violations = &mut validation.violations;
+    let version = header.version;
+
+    let compressed = header.point_data_record_format & COMPRESSION_BITS != 0;
+    let format = header.point_data_record_format & !COMPRESSION_BITS;
+    match format_min_minor(format) {
+        Some(minor) if version.major == 1 && version.minor < minor => {
+            violations.push(format!("point format {} requires LAS 1.{}, file is {}.{}", format, minor, version.major, version.minor));
+        },
+        Some(_) => (),
+        None => violations.push(format!("invalid point format {}", format))
+    }
+
+    if !compressed && header.point_data_record_length > 0 {
+        let count = header.large_file.as_ref()
+                          .map_or(u64::from(header.number_of_point_records), |large| large.number_of_point_records);
+        // Writers leave the EVLR start zero when there are none, point data runs to the end then.
+        let data_end = match &header.evlr {
+            Some(evlr) if evlr.number_of_evlrs > 0 => evlr.start_of_first_evlr,
+            _ => fs::metadata(path)?.len()
+        };
+        let data_len = data_end.saturating_sub(u64::from(header.offset_to_point_data));
+        match count.checked_mul(u64::from(header.point_data_record_length)) {
+            Some(expected) if data_len != expected => {
+                violations.push(format!("point count {} needs {} bytes of point data, file has {}", count, expected, data_len));
+            },
+            Some(_) => (),
+            None => violations.push(format!("point count {} overflows size of point data", count))
+        }
+    }
+
+    if version.major == 1 && version.minor >= 4 && !has_crs_vlr(&mut file, &header)? {
+        violations.push("no CRS VLR, required by LAS 1.4".to_string());
+    }
+
+    for (axis, scale) in &[("X", header.x_scale_factor), ("Y", header.y_scale_factor), ("Z", header.z_scale_factor)] {
+        if *scale == 0.0 {
+            violations.push(format!("zero {} scale factor", axis));
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes bounding boxes of the files with `path`, `valid` (1 or 0) and semicolon separated `violations`.
+/// Files with unreadable header have empty geometry.
+pub fn write_validations(driver_name: &str, path: &Path, srs: Option<&SpatialRef>, validations: &[Validation],
+                         overwrite: bool) -> Result<(), LasBoundsError> {
+
+    if path.exists() {
+        if !overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite", path.to_string_lossy()).into());
+        }
+        ogr::delete_dataset(driver_name, path)?;
+    }
+
+    let mut ds = OutputDataset::create(driver_name, path, &[])?;
+    let layer = ds.create_layer(VALIDATION_LAYER_NAME, srs, OGRwkbGeometryType::wkbPolygon, &[])?;
+    layer.create_defn_fields(&[
+        ("path", OGRFieldType::OFTString),
+        ("valid", OGRFieldType::OFTInteger),
+        ("violations", OGRFieldType::OFTString)
+    ])?;
+
+    for validation in validations {
+        let wkt = match validation.bounds {
+            Some((x0, y0, x1, y1)) => format!("POLYGON (({x0} {y0},{x1} {y0},{x1} {y1},{x0} {y1},{x0} {y0}))",
+                                              x0 = x0, y0 = y0, x1 = x1, y1 = y1),
+            None => "POLYGON EMPTY".to_string()
+        };
+        layer.create_feature_fields(Geometry::from_wkt(&wkt)?, &["path", "valid", "violations"], &[
+            FieldValue::StringValue(validation.path.to_string_lossy().into_owned()),
+            FieldValue::IntegerValue(validation.is_valid() as i32),
+            FieldValue::StringValue(validation.violations.join("; "))
+        ])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use las::raw::header::{Evlr, LargeFile};
+
+    /// Writes uncompressed LAS 1.4 file of `count` format 6 points without EVLRs, their start zero.
+    fn write_las(name: &str, count: u64) -> PathBuf {
+
+        let raw = las::raw::Header {
+            version: las::Version::new(1, 4),
+            header_size: 375,
+            offset_to_point_data: 375,
+            point_data_record_format: 6,
+            point_data_record_length: 30,
+            number_of_point_records: count as u32,
+            evlr: Some(Evlr { start_of_first_evlr: 0, number_of_evlrs: 0 }),
+            large_file: Some(LargeFile { number_of_point_records: count, number_of_points_by_return: [0; 15] }),
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        raw.write_to(&mut bytes).unwrap();
+        bytes.resize(bytes.len() + count as usize * 30, 0);
+        let path = std::env::temp_dir().join(format!("las-bounds-{}-{}.las", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn takes_point_data_to_file_end_without_evlrs() {
+
+        let path = write_las("no-evlrs", 2);
+        let validation = validate(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(validation.violations.iter().all(|v| !v.contains("point data")), "{:?}", validation.violations);
+    }
+}
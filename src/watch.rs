@@ -0,0 +1,75 @@
+//! Incremental indexing of a directory, following filesystem notifications.
+
+use crate::{BoundsWriter, DiscoverOptions, LasBoundsError, ReadOptions, read_bounds};
+use crate::discover;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+
+/// Files are indexed only after they were not written for this long, so copies in progress are skipped.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Change of an indexed file.
+enum Change {
+    Indexed(PathBuf),
+    Removed(PathBuf)
+}
+
+fn changes(event: DebouncedEvent) -> Vec<Change> {
+
+    match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => vec![Change::Indexed(path)],
+        DebouncedEvent::Remove(path) => vec![Change::Removed(path)],
+        DebouncedEvent::Rename(from, to) => vec![Change::Removed(from), Change::Indexed(to)],
+        DebouncedEvent::Error(e, path) => {
+            warn!("Watching {} failed: {}", path.map_or(String::new(), |p| p.to_string_lossy().into_owned()), e);
+            Vec::new()
+        },
+        _ => Vec::new()
+    }
+}
+
+/// Keeps the output in sync with files in the directory until the watch fails: arrived and changed
+/// files are (re)indexed, features of deleted ones are removed. Files already in the directory have
+/// to be indexed by the caller before.
+pub fn watch(dir: &Path, writer: &mut dyn BoundsWriter, read_options: &ReadOptions, discover_options: &DiscoverOptions)
+    -> Result<(), LasBoundsError> {
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::watcher(sender, DEBOUNCE).map_err(|e| format!("Could not watch {}: {}", dir.to_string_lossy(), e))?;
+    let mode = if discover_options.max_depth > 1 { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(dir, mode).map_err(|e| format!("Could not watch {}: {}", dir.to_string_lossy(), e))?;
+    info!("Watching {}", dir.to_string_lossy());
+
+    for event in receiver {
+        for change in changes(event) {
+            match change {
+                Change::Indexed(path) if discover::is_input(&path, discover_options) && path.is_file() => {
+                    // Changed files replace their features.
+                    let indexed_path = read_options.path_format.format(&path);
+                    if writer.contains(&indexed_path) {
+                        writer.remove(&indexed_path)?;
+                    }
+                    match read_bounds(&path, read_options) {
+                        Ok(record) => {
+                            writer.write(&record)?;
+                            info!("Indexed {}", path.to_string_lossy());
+                        },
+                        // Incomplete file is indexed when it is written again.
+                        Err(e) => warn!("Could not index {}: {}", path.to_string_lossy(), e)
+                    }
+                },
+                Change::Removed(path) if writer.contains(&read_options.path_format.format(&path)) => {
+                    writer.remove(&read_options.path_format.format(&path))?;
+                    info!("Removed {}", path.to_string_lossy());
+                },
+                _ => ()
+            }
+        }
+        writer.flush()?;
+    }
+
+    Ok(())
+}
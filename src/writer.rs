@@ -0,0 +1,1084 @@
+//! Output of bounds records.
+
+use crate::{BoundsRecord, LasBoundsError};
+use crate::crs::{self, Crs};
+use crate::fields::{self, Field, FieldType, Value};
+use crate::footprint::{Footprint, Polygon};
+use crate::hexbin::{self, HexCounts};
+use crate::ogr::{self, GeometryUnion, OutputDataset};
+use gdal::spatial_ref::{CoordTransform, SpatialRef};
+use gdal::vector::{Dataset, FieldValue, Geometry, Layer, OGRFieldType, OGRwkbGeometryType};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+
+/// Destination of bounds records.
+pub trait BoundsWriter {
+
+    /// Whether a file with the `path` field value is already in the output, e.g. when appending to an existing index.
+    fn contains(&self, _path: &str) -> bool {
+        false
+    }
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError>;
+
+    /// Removes records with the `path` field value, e.g. when the file was deleted from a watched directory.
+    fn remove(&mut self, path: &str) -> Result<(), LasBoundsError> {
+        Err(format!("Output does not support removing records: {}", path).into())
+    }
+
+    /// Makes records written so far visible to readers of the output.
+    fn flush(&mut self) -> Result<(), LasBoundsError> {
+        Ok(())
+    }
+
+    /// Completes the output, called once after the last record.
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+        Ok(())
+    }
+
+    /// Stops writing after a fatal error instead of [`finish`](Self::finish). Records written so far
+    /// are kept, except in outputs that would replace existing ones, which are left untouched.
+    fn abort(&mut self) -> Result<(), LasBoundsError> {
+        self.flush()
+    }
+}
+
+/// Writes records to several outputs, e.g. vector layer and STAC items.
+pub struct MultiWriter {
+    writers: Vec<Box<dyn BoundsWriter>>
+}
+
+impl MultiWriter {
+
+    pub fn new(writers: Vec<Box<dyn BoundsWriter>>) -> Self {
+        MultiWriter { writers }
+    }
+}
+
+impl BoundsWriter for MultiWriter {
+
+    fn contains(&self, path: &str) -> bool {
+        self.writers.iter().any(|writer| writer.contains(path))
+    }
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        for writer in &mut self.writers {
+            writer.write(record)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), LasBoundsError> {
+
+        for writer in &mut self.writers {
+            writer.remove(path)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), LasBoundsError> {
+
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        for writer in &mut self.writers {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
+    fn abort(&mut self) -> Result<(), LasBoundsError> {
+
+        for writer in &mut self.writers {
+            writer.abort()?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps output format name to GDAL driver name and default file extension.
+pub fn output_driver(format: &str) -> Result<(String, Option<String>), LasBoundsError> {
+
+    match format {
+        "SHP" => Ok(("ESRI Shapefile".into(), Some("shp".into()))),
+        "GPKG" => Ok(("GPKG".into(), Some("gpkg".into()))),
+        "GeoJSON" => Ok(("GeoJSON".into(), Some("geojson".into()))),
+        // Written by CsvWriter and KmlWriter rather than the OGR drivers.
+        "CSV" => Ok(("CSV".into(), Some("csv".into()))),
+        "KML" => Ok(("KML".into(), Some("kml".into()))),
+        "KMZ" => Ok(("KMZ".into(), Some("kmz".into()))),
+        "GeoParquet" => Ok(("Parquet".into(), Some("parquet".into()))),
+        "FlatGeobuf" => Ok(("FlatGeobuf".into(), Some("fgb".into()))),
+        "PostGIS" => Ok(("PostgreSQL".into(), None)),
+        driver_name => Ok((driver_name.into(), ogr::driver_extension(driver_name)?))
+    }
+}
+
+/// Layer creation options the driver gets unless given explicitly.
+fn default_layer_options(driver_name: &str) -> &'static [&'static str] {
+
+    match driver_name {
+        // GeoParquet with per row bbox covering, so readers filter row groups without decoding geometries.
+        "Parquet" => &["GEOMETRY_ENCODING=WKB", "WRITE_COVERING_BBOX=YES", "COMPRESSION=ZSTD"],
+        // Features are sorted along Hilbert curve when the layer is closed and packed R-tree is
+        // written in front of them, so clients fetch only ranges intersecting their extent.
+        "FlatGeobuf" => &["SPATIAL_INDEX=YES"],
+        _ => &[]
+    }
+}
+
+/// Explicit options followed by driver defaults for names not given.
+fn with_default_options(driver_name: &str, options: &[String]) -> Vec<String> {
+
+    let option_name = |option: &str| option.split('=').next().unwrap_or_default().to_ascii_uppercase();
+    let mut all = options.to_vec();
+    all.extend(default_layer_options(driver_name).iter()
+                   .filter(|default| !options.iter().any(|option| option_name(option) == option_name(default)))
+                   .map(|default| default.to_string()));
+    all
+}
+
+fn ogr_field_type(field_type: FieldType) -> OGRFieldType::Type {
+
+    match field_type {
+        FieldType::Integer => OGRFieldType::OFTInteger64,
+        FieldType::Real => OGRFieldType::OFTReal,
+        FieldType::String => OGRFieldType::OFTString
+    }
+}
+
+fn ogr_field_value(value: Value) -> FieldValue {
+
+    match value {
+        Value::Integer(i) => FieldValue::Integer64Value(i),
+        Value::Real(r) => FieldValue::RealValue(r),
+        Value::String(s) => FieldValue::StringValue(s)
+    }
+}
+
+/// Names of layer fields as created by the driver, which may differ from requested ones
+/// (e.g. ESRI Shapefile truncates them to 10 characters).
+fn layer_field_names(layer: &Layer) -> Vec<String> {
+    layer.defn().fields().map(|field| field.name()).collect()
+}
+
+/// Schema fields paired with names of corresponding layer fields.
+type LayerFields = Vec<(Field, String)>;
+
+/// Name of the layer unless given in options.
+pub const LAYER_NAME: &str = "bounds";
+const GAPS_LAYER_NAME: &str = "gaps";
+const OVERLAPS_LAYER_NAME: &str = "overlaps";
+const HEXBIN_LAYER_NAME: &str = "hexbin";
+const FLIGHTLINES_LAYER_NAME: &str = "flightlines";
+
+/// Number of segments the longer envelope side is split into before reprojection.
+const DENSIFY_SEGMENTS: f64 = 20.0;
+
+/// Footprint with vertices added along its edges, so it bends properly when reprojected. Segment
+/// length comes from the footprint itself, header bounds may be zeroed or stale. Degenerate
+/// footprints are left as they are.
+pub(crate) fn densified_footprint(record: &BoundsRecord) -> Footprint {
+
+    let max_length = match record.footprint.envelope() {
+        Some((x0, y0, x1, y1)) => (x1 - x0).max(y1 - y0) / DENSIFY_SEGMENTS,
+        None => 0.0
+    };
+    if !max_length.is_finite() || max_length <= 0.0 {
+        return record.footprint.clone();
+    }
+    record.footprint.densify(max_length)
+}
+
+/// Footprint geometry expanded by `distance` in its CRS units, MultiPolygon for `multi` layers.
+pub(crate) fn buffered(geometry: &Geometry, distance: f64, multi: bool) -> Result<Geometry, LasBoundsError> {
+
+    let buffered = ogr::buffer(geometry, distance)?;
+    if multi {
+        ogr::multi_polygon(&buffered)
+    } else {
+        Ok(buffered)
+    }
+}
+
+/// Whether layer field name refers to the schema field, also when truncated by the driver.
+fn is_layer_field(layer_name: &str, field: &Field) -> bool {
+    layer_name == field.name || (layer_name.len() == 10 && field.name.starts_with(layer_name))
+}
+
+/// Whether the driver keeps several layers in one dataset, so indexes can be added next to others.
+fn is_multi_layer(driver_name: &str) -> bool {
+    matches!(driver_name, "GPKG" | "SQLite" | "OpenFileGDB" | "PostgreSQL")
+}
+
+/// Layer name for files in the CRS, e.g. `bounds_epsg_2180`.
+fn crs_layer_name(layer_name: &str, crs: &Crs) -> String {
+
+    let label = crs.to_string().to_ascii_lowercase();
+    let words: Vec<&str> = label.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty()).collect();
+    format!("{}_{}", layer_name, words.join("_"))
+}
+
+/// Name of the immediate subdirectory of the input directory the file is in, `None` for files
+/// directly in the input directory or not found in one.
+fn subdirectory(record: &BoundsRecord) -> Option<String> {
+
+    let relative = record.path.strip_prefix(record.source_dir.as_ref()?).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    components.next()?;
+    Some(first.as_os_str().to_string_lossy().into_owned())
+}
+
+/// Opens existing bounds layer, keeping only schema fields the layer already has. Single layer
+/// datasets such as Shapefiles are opened by their only layer, whatever its name.
+fn open_layer(ds: &mut OutputDataset, name: &str, fields: &[Field]) -> Result<(isize, LayerFields), LasBoundsError> {
+
+    let index = ds.layer_index(name)?.unwrap_or(0);
+    let layer = ds.layer(index)?;
+
+    let layer_fields = layer_field_names(layer).into_iter()
+        .filter_map(|name| fields.iter().find(|field| is_layer_field(&name, field)).map(|field| (field.clone(), name)))
+        .collect();
+    Ok((index, layer_fields))
+}
+
+/// Fields identifying files, of the default and pdal-tindex schema.
+const PATH_FIELDS: [&str; 2] = ["path", "location"];
+
+/// Index of the bounds layer of a dataset opened for reading: the layer of given name, or `bounds`
+/// falling back to the first one as for Shapefiles.
+pub(crate) fn find_bounds_layer(dataset: &mut Dataset, layer_name: Option<&str>, path: &Path) -> Result<isize, LasBoundsError> {
+
+    let name = layer_name.unwrap_or(LAYER_NAME);
+    for i in 0..dataset.count() {
+        if ogr::layer_name(dataset.layer(i)?) == name {
+            return Ok(i);
+        }
+    }
+    match layer_name {
+        Some(name) => Err(format!("No layer {} in {}", name, path.to_string_lossy()).into()),
+        None => Ok(0)
+    }
+}
+
+/// Name of the field with paths of indexed files, `None` when the layer has none.
+pub(crate) fn path_field_name(layer: &Layer) -> Option<String> {
+    layer_field_names(layer).into_iter().find(|name| PATH_FIELDS.contains(&name.as_str()))
+}
+
+/// Vertical position of 3D polygon vertices within Z range of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Elevation {
+    Min,
+    Max,
+    /// Middle of the range.
+    Mid
+}
+
+impl FromStr for Elevation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "min" => Ok(Elevation::Min),
+            "max" => Ok(Elevation::Max),
+            "mid" => Ok(Elevation::Mid),
+            _ => Err(format!("Unknown Z value: {}", s))
+        }
+    }
+}
+
+impl Elevation {
+
+    /// Z of the vertices from header bounds of the file.
+    pub fn z(&self, record: &BoundsRecord) -> f64 {
+
+        let bounds = record.header.bounds();
+        match self {
+            Elevation::Min => bounds.min.z,
+            Elevation::Max => bounds.max.z,
+            Elevation::Mid => (bounds.min.z + bounds.max.z) / 2.0
+        }
+    }
+}
+
+/// Extent of features of the flightlines layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlightlineMode {
+    /// Feature per flightline in each file.
+    File,
+    /// Feature per flightline, dissolved across files.
+    Survey
+}
+
+impl FromStr for FlightlineMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(FlightlineMode::File),
+            "survey" => Ok(FlightlineMode::Survey),
+            _ => Err(format!("Unknown flightline mode: {}", s))
+        }
+    }
+}
+
+/// Geometry type of bounds layers, 2.5D with Z on vertices.
+fn bounds_geometry_type(multi: bool, z: bool) -> OGRwkbGeometryType::Type {
+
+    match (multi, z) {
+        (true, true) => OGRwkbGeometryType::wkbMultiPolygon25D,
+        (true, false) => OGRwkbGeometryType::wkbMultiPolygon,
+        (false, true) => OGRwkbGeometryType::wkbPolygon25D,
+        (false, false) => OGRwkbGeometryType::wkbPolygon
+    }
+}
+
+fn create_layer(ds: &mut OutputDataset, name: &str, srs: Option<&SpatialRef>, geometry_type: OGRwkbGeometryType::Type,
+                fields: &[Field], options: &[String]) -> Result<(isize, LayerFields), LasBoundsError> {
+
+    let layer = ds.create_layer(name, srs, geometry_type, options)?;
+
+    let defn: Vec<(&str, OGRFieldType::Type)> = fields.iter()
+                                                      .map(|field| (field.name.as_ref(), ogr_field_type(field.field_type)))
+                                                      .collect();
+    layer.create_defn_fields(&defn)?;
+
+    let layer_fields = fields.iter().cloned().zip(layer_field_names(layer)).collect();
+    Ok((ds.layer_count() - 1, layer_fields))
+}
+
+/// Values of `path` field of features already in the layer.
+fn indexed_paths(layer: &Layer, layer_fields: &LayerFields) -> Result<HashSet<String>, LasBoundsError> {
+
+    let mut paths = HashSet::new();
+    if let Some((_, name)) = layer_fields.iter().find(|(field, _)| field.key == fields::PATH.key) {
+        for feature in layer.features() {
+            if let FieldValue::StringValue(path) = feature.field(name)? {
+                paths.insert(path);
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Sibling of the output path used while the output is written, e.g. `index.tmp.gpkg` for `index.gpkg`.
+fn temporary_path(path: &Path) -> PathBuf {
+
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.tmp.{}", stem, ext.to_string_lossy()),
+        None => format!("{}.tmp", stem)
+    };
+    path.with_file_name(name)
+}
+
+pub struct OgrOptions {
+    pub driver_name: String,
+    /// File path, or connection string of a database such as `PG:dbname=lidar`.
+    pub path: PathBuf,
+    /// Name of the layer, table in databases.
+    pub layer_name: String,
+    /// Dataset creation options, `NAME=VALUE`.
+    pub dataset_options: Vec<String>,
+    /// Layer creation options, `NAME=VALUE`.
+    pub layer_options: Vec<String>,
+    /// CRS of the layer.
+    pub srs: Option<SpatialRef>,
+    /// Transformation of footprints into the layer CRS.
+    pub transform: Option<CoordTransform>,
+    /// Write MultiPolygon instead of Polygon geometries.
+    pub multi: bool,
+    /// Write 2.5D polygons with Z of vertices from Z range of the file.
+    pub elevation: Option<Elevation>,
+    /// Distance footprints are expanded by after reprojection, in layer CRS units.
+    pub buffer: Option<f64>,
+    /// Name of layer with dissolved union of all footprints, written on finish.
+    pub union_layer: Option<String>,
+    /// Write `gaps` layer with holes in the coverage on finish.
+    pub detect_gaps: bool,
+    /// Vector dataset with area of interest the coverage is compared with for gaps, instead of
+    /// its convex hull. Has to be in the output CRS.
+    pub aoi: Option<PathBuf>,
+    /// Write `overlaps` layer with intersections of footprint pairs on finish.
+    pub detect_overlaps: bool,
+    /// Write `hexbin` layer with point counts of files written in this run in hexagons of the side length,
+    /// requires `ScanOptions::hexbin` of the same size.
+    pub hexbin: Option<f64>,
+    /// Write `flightlines` layer with footprints of point source IDs of files written in this run,
+    /// requires `ScanOptions::flightlines`.
+    pub flightlines: Option<FlightlineMode>,
+    /// Write files of each immediate subdirectory of their input directory into a layer named after it,
+    /// files directly in the input directory into the `layer_name` layer.
+    pub layer_per_dir: bool,
+    /// Write files with CRS into layers per CRS, named like `bounds_epsg_2180`, in that CRS unless
+    /// `reproject_each`. Files without CRS are written into the `layer_name` layer.
+    pub split_by_crs: bool,
+    /// Transform footprints of each file from CRS detected in it into `srs`, for inputs in several
+    /// CRSs. `transform` is used for files without CRS.
+    pub reproject_each: bool,
+    /// Append to existing dataset.
+    pub append: bool,
+    /// Replace existing dataset.
+    pub overwrite: bool,
+    /// Features written per transaction by drivers supporting them (GeoPackage, PostGIS), 0 to let
+    /// the driver commit each feature.
+    pub transaction_size: usize
+}
+
+impl OgrOptions {
+
+    pub fn new<P: Into<PathBuf>>(driver_name: &str, path: P) -> Self {
+        OgrOptions {
+            driver_name: driver_name.to_string(),
+            path: path.into(),
+            layer_name: LAYER_NAME.to_string(),
+            dataset_options: Vec::new(),
+            layer_options: Vec::new(),
+            srs: None,
+            transform: None,
+            multi: false,
+            elevation: None,
+            buffer: None,
+            union_layer: None,
+            detect_gaps: false,
+            aoi: None,
+            detect_overlaps: false,
+            hexbin: None,
+            flightlines: None,
+            layer_per_dir: false,
+            split_by_crs: false,
+            reproject_each: false,
+            append: false,
+            overwrite: false,
+            transaction_size: 0
+        }
+    }
+}
+
+/// Writes records as features of a layer in OGR vector dataset.
+pub struct OgrWriter {
+    ds: Option<OutputDataset>,
+    layer_index: isize,
+    layer_fields: LayerFields,
+    layer_name: String,
+    layer_per_dir: bool,
+    split_by_crs: bool,
+    /// Layers of subdirectories or CRSs by name, created as their first files are written.
+    extra_layers: HashMap<String, (isize, LayerFields)>,
+    reproject_each: bool,
+    /// Transformations from CRSs of files into the layer CRS, with `reproject_each`.
+    transforms: HashMap<Crs, CoordTransform>,
+    /// Schema and creation options of directory layers.
+    fields: Vec<Field>,
+    layer_options: Vec<String>,
+    overwrite: bool,
+    indexed: HashSet<String>,
+    transform: Option<CoordTransform>,
+    multi: bool,
+    elevation: Option<Elevation>,
+    buffer: Option<f64>,
+    srs: Option<SpatialRef>,
+    union_layer: Option<String>,
+    detect_gaps: bool,
+    aoi: Option<Geometry>,
+    detect_overlaps: bool,
+    hexbin: Option<f64>,
+    hex_counts: HexCounts,
+    flightlines: Option<FlightlineMode>,
+    /// Flightlines of written files as path, point source ID, point count and footprint in layer CRS.
+    flightline_features: Vec<(String, u16, u64, Geometry)>,
+    driver_name: String,
+    transaction_size: usize,
+    /// Number of features written in the open transaction, `None` when there is none.
+    transaction: Option<usize>,
+    /// Temporary and final path when existing dataset is replaced on finish.
+    staged: Option<(PathBuf, PathBuf)>
+}
+
+impl OgrWriter {
+
+    /// Creates the dataset, or opens it when appending. Fails when the dataset exists
+    /// and neither append nor overwrite was requested.
+    pub fn new(options: OgrOptions, fields: &[Field]) -> Result<Self, LasBoundsError> {
+
+        if ogr::is_connection_string(&options.path) {
+            let ds = OutputDataset::open(&options.path)?;
+            return Self::open_existing(ds, options, fields);
+        }
+
+        let path = &options.path;
+        let exists = path.exists();
+        // Datasets with other layers, e.g. indexes of other collections in one GeoPackage, get the layer added
+        // or replaced. Datasets with only this layer are replaced as a whole.
+        if exists && is_multi_layer(&options.driver_name) {
+            let ds = OutputDataset::open(path)?;
+            let others = ds.layer_names().iter().any(|name| *name != options.layer_name);
+            if others || !options.overwrite {
+                return Self::open_existing(ds, options, fields);
+            }
+        }
+        let append = options.append && exists;
+        let overwrite = options.overwrite && exists;
+        if exists && !append && !overwrite {
+            return Err(format!("Output already exists: {}, use --overwrite or --append", path.to_string_lossy()).into());
+        }
+        if (options.layer_per_dir || options.split_by_crs) && !is_multi_layer(&options.driver_name) {
+            return Err(format!("Layer per directory or CRS requires output format with several layers, e.g. GPKG, not {}",
+                               options.driver_name).into());
+        }
+        if append && options.driver_name == "FlatGeobuf" {
+            return Err("FlatGeobuf with spatial index can not be appended to, use --overwrite".to_string().into());
+        }
+
+        // Existing dataset is replaced only after the new one is complete.
+        let staged = if overwrite { Some((temporary_path(path), path.clone())) } else { None };
+        let write_path = staged.as_ref().map_or(path.as_path(), |(tmp, _)| tmp.as_path());
+
+        let mut ds = if append {
+            debug!("Appending to {}", path.to_string_lossy());
+            OutputDataset::open(path)?
+        } else {
+            debug!("Creating {} with {} driver, options {:?}", write_path.to_string_lossy(), options.driver_name,
+                   options.dataset_options);
+            OutputDataset::create(&options.driver_name, write_path, &options.dataset_options)?
+        };
+        let (layer_index, layer_fields) = if append {
+            open_layer(&mut ds, &options.layer_name, fields)?
+        } else {
+            let layer_options = with_default_options(&options.driver_name, &options.layer_options);
+            let geometry_type = bounds_geometry_type(options.multi, options.elevation.is_some());
+            create_layer(&mut ds, &options.layer_name, options.srs.as_ref(), geometry_type, fields, &layer_options)?
+        };
+
+        Self::with_layer(ds, layer_index, layer_fields, fields, options, staged)
+    }
+
+    /// Creates the layer in existing database or multi-layer dataset, or opens it when appending.
+    /// Replaced layers are dropped by the driver, as the other layers have to be kept.
+    fn open_existing(mut ds: OutputDataset, options: OgrOptions, fields: &[Field]) -> Result<Self, LasBoundsError> {
+
+        let exists = ds.layer_index(&options.layer_name)?.is_some();
+        if exists && !options.append && !options.overwrite {
+            return Err(format!("Layer already exists: {}, use --overwrite or --append", options.layer_name).into());
+        }
+
+        let (layer_index, layer_fields) = if exists && options.append {
+            debug!("Appending to layer {}", options.layer_name);
+            open_layer(&mut ds, &options.layer_name, fields)?
+        } else {
+            debug!("Creating layer {}", options.layer_name);
+            let mut layer_options = with_default_options(&options.driver_name, &options.layer_options);
+            if exists {
+                layer_options.push("OVERWRITE=YES".to_string());
+            }
+            let geometry_type = bounds_geometry_type(options.multi, options.elevation.is_some());
+            create_layer(&mut ds, &options.layer_name, options.srs.as_ref(), geometry_type, fields, &layer_options)?
+        };
+
+        Self::with_layer(ds, layer_index, layer_fields, fields, options, None)
+    }
+
+    fn with_layer(mut ds: OutputDataset, layer_index: isize, layer_fields: LayerFields, fields: &[Field], options: OgrOptions,
+                  staged: Option<(PathBuf, PathBuf)>) -> Result<Self, LasBoundsError> {
+
+        let mut indexed = indexed_paths(ds.layer(layer_index)?, &layer_fields)?;
+
+        // Other layers of appended dataset are taken for layers of directories or CRSs indexed before.
+        let mut extra_layers = HashMap::new();
+        if (options.layer_per_dir || options.split_by_crs) && options.append {
+            let auxiliary = [Some(options.layer_name.as_str()), options.union_layer.as_deref(),
+                             Some(GAPS_LAYER_NAME), Some(OVERLAPS_LAYER_NAME), Some(HEXBIN_LAYER_NAME),
+                             Some(FLIGHTLINES_LAYER_NAME)];
+            for name in ds.layer_names().into_iter().filter(|name| !auxiliary.contains(&Some(name.as_str()))) {
+                let (index, dir_fields) = open_layer(&mut ds, &name, fields)?;
+                indexed.extend(indexed_paths(ds.layer(index)?, &dir_fields)?);
+                extra_layers.insert(name, (index, dir_fields));
+            }
+        }
+
+        let aoi = match &options.aoi {
+            Some(path) => Some(ogr::read_layer_union(path)?),
+            None => None
+        };
+
+        Ok(OgrWriter {
+            ds: Some(ds),
+            layer_index,
+            layer_fields,
+            layer_name: options.layer_name,
+            layer_per_dir: options.layer_per_dir,
+            split_by_crs: options.split_by_crs,
+            extra_layers,
+            reproject_each: options.reproject_each,
+            transforms: HashMap::new(),
+            fields: fields.to_vec(),
+            layer_options: with_default_options(&options.driver_name, &options.layer_options),
+            overwrite: options.overwrite,
+            indexed,
+            transform: options.transform,
+            multi: options.multi,
+            elevation: options.elevation,
+            buffer: options.buffer,
+            srs: options.srs,
+            union_layer: options.union_layer,
+            detect_gaps: options.detect_gaps,
+            aoi,
+            detect_overlaps: options.detect_overlaps,
+            hexbin: options.hexbin,
+            hex_counts: HexCounts::new(),
+            flightlines: options.flightlines,
+            flightline_features: Vec::new(),
+            driver_name: options.driver_name,
+            transaction_size: options.transaction_size,
+            transaction: None,
+            staged
+        })
+    }
+
+    fn layer(&mut self) -> Result<&mut Layer, LasBoundsError> {
+
+        let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        ds.layer(self.layer_index)
+    }
+
+    /// Index and fields of the layer the record is written to, creating layer of its directory
+    /// or CRS when it is the first file there.
+    fn record_layer(&mut self, record: &BoundsRecord) -> Result<(isize, &LayerFields), LasBoundsError> {
+
+        let crs = if self.split_by_crs { crs::detect_crs(&record.header) } else { None };
+        let name = match (&crs, subdirectory(record)) {
+            (Some(crs), _) => crs_layer_name(&self.layer_name, crs),
+            (None, Some(name)) if self.layer_per_dir => name,
+            _ => return Ok((self.layer_index, &self.layer_fields))
+        };
+
+        if !self.extra_layers.contains_key(&name) {
+            let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+            let mut layer_options = self.layer_options.clone();
+            if ds.layer_index(&name)?.is_some() {
+                if !self.overwrite {
+                    return Err(format!("Layer already exists: {}, use --overwrite or --append", name).into());
+                }
+                layer_options.push("OVERWRITE=YES".to_string());
+            }
+            // Layers of CRSs are in that CRS unless all files are reprojected.
+            let layer_srs = match &crs {
+                Some(crs) if !self.reproject_each => Some(crs.to_spatial_ref()?),
+                _ => self.srs.clone()
+            };
+            debug!("Creating layer {}", name);
+            let geometry_type = bounds_geometry_type(self.multi, self.elevation.is_some());
+            let layer = create_layer(ds, &name, layer_srs.as_ref(), geometry_type, &self.fields, &layer_options)?;
+            self.extra_layers.insert(name.clone(), layer);
+        }
+
+        let (index, layer_fields) = &self.extra_layers[&name];
+        Ok((*index, layer_fields))
+    }
+
+    /// Starts transaction the next features are written in, unless one is open or the driver does
+    /// not support them.
+    fn begin_transaction(&mut self) -> Result<(), LasBoundsError> {
+
+        if self.transaction_size == 0 || self.transaction.is_some() {
+            return Ok(());
+        }
+        let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        if ds.start_transaction()? {
+            self.transaction = Some(0);
+        } else {
+            debug!("{} does not support transactions, features are committed one by one", self.driver_name);
+            self.transaction_size = 0;
+        }
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), LasBoundsError> {
+
+        if self.transaction.take().is_some() {
+            let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+            ds.commit_transaction()?;
+        }
+        Ok(())
+    }
+
+    /// Transformation of the record footprint into the layer CRS, if any.
+    fn record_transform(&mut self, record: &BoundsRecord) -> Result<Option<&CoordTransform>, LasBoundsError> {
+
+        let crs = match (self.reproject_each, &self.srs) {
+            (true, Some(_)) => crs::detect_crs(&record.header),
+            _ => None
+        };
+        let crs = match crs {
+            Some(crs) => crs,
+            None => return Ok(self.transform.as_ref())
+        };
+
+        if !self.transforms.contains_key(&crs) {
+            let target = self.srs.as_ref().unwrap();
+            debug!("Reprojecting bounds from {}", crs);
+            self.transforms.insert(crs.clone(), CoordTransform::new(&crs.to_spatial_ref()?, target)?);
+        }
+        Ok(self.transforms.get(&crs))
+    }
+
+    /// Union of all features of the bounds layer, including ones written before when appending,
+    /// with their count.
+    fn footprints_union(&mut self) -> Result<(Geometry, i64), LasBoundsError> {
+
+        let mut union = GeometryUnion::new();
+        let mut files = 0;
+        for feature in self.layer()?.features() {
+            union.add(feature.geometry())?;
+            files += 1;
+        }
+        debug!("Dissolving {} footprints", files);
+        Ok((union.union()?, files))
+    }
+
+    /// Creates the layer, replacing existing one of the same name.
+    fn replace_layer(&mut self, name: &str, geometry_type: OGRwkbGeometryType::Type,
+                     fields: &[(&str, OGRFieldType::Type)]) -> Result<&mut Layer, LasBoundsError> {
+
+        let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        if let Some(index) = ds.layer_index(name)? {
+            ds.delete_layer(index)?;
+            // Layers after the deleted one move down, the bounds layer may be one of them.
+            if index < self.layer_index {
+                self.layer_index -= 1;
+            }
+        }
+        let layer = ds.create_layer(name, self.srs.as_ref(), geometry_type, &[])?;
+        layer.create_defn_fields(fields)?;
+        Ok(layer)
+    }
+
+    /// Writes polygons of the area of interest (convex hull of the coverage by default) not covered
+    /// by any footprint.
+    fn write_gaps(&mut self, coverage: &Geometry) -> Result<(), LasBoundsError> {
+
+        let area = match self.aoi.take() {
+            Some(aoi) => aoi,
+            None => ogr::convex_hull(coverage)?
+        };
+        let gaps = ogr::polygons(&ogr::difference(&area, coverage)?);
+        debug!("Writing {} gaps", gaps.len());
+
+        let layer = self.replace_layer(GAPS_LAYER_NAME, OGRwkbGeometryType::wkbPolygon, &[("area", OGRFieldType::OFTReal)])?;
+        for gap in gaps {
+            let area = gap.area();
+            if area > 0.0 {
+                layer.create_feature_fields(gap, &["area"], &[FieldValue::RealValue(area)])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Footprints of features in the bounds layer with names of their files, or paths when the layer
+    /// has no name field.
+    fn named_footprints(&mut self) -> Result<Vec<(String, Geometry)>, LasBoundsError> {
+
+        let name_field = [&fields::NAME, &fields::PATH].iter()
+            .find_map(|field| self.layer_fields.iter().find(|(f, _)| f.key == field.key))
+            .map(|(_, name)| name.clone());
+
+        let mut footprints = Vec::new();
+        for feature in self.layer()?.features() {
+            let name = match &name_field {
+                Some(name_field) => match feature.field(name_field)? {
+                    FieldValue::StringValue(name) => name,
+                    _ => String::new()
+                },
+                None => String::new()
+            };
+            footprints.push((name, feature.geometry().clone()));
+        }
+        Ok(footprints)
+    }
+
+    /// Writes hexagons with points, with their count and density per unit of area in input CRS.
+    fn write_hexbin(&mut self, size: f64) -> Result<(), LasBoundsError> {
+
+        let hex_counts = std::mem::take(&mut self.hex_counts);
+        debug!("Writing {} hexagons", hex_counts.len());
+        let area = hexbin::hex_area(size);
+        let mut features = Vec::new();
+        for (hex, count) in hex_counts {
+            let polygon = Footprint { polygons: vec![Polygon { exterior: hexbin::hex_polygon(hex, size), interiors: Vec::new() }] };
+            let mut geometry = Geometry::from_wkt(&polygon.to_wkt())?;
+            if let Some(transform) = &self.transform {
+                geometry = geometry.transform(transform)?;
+            }
+            features.push((geometry, count));
+        }
+
+        let layer = self.replace_layer(HEXBIN_LAYER_NAME, OGRwkbGeometryType::wkbPolygon,
+                                       &[("count", OGRFieldType::OFTInteger64), ("density", OGRFieldType::OFTReal)])?;
+        for (geometry, count) in features {
+            layer.create_feature_fields(geometry, &["count", "density"],
+                                        &[FieldValue::Integer64Value(count as i64), FieldValue::RealValue(count as f64 / area)])?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes footprints of flightlines with their point source ID, point count and area, per file
+    /// with its path or dissolved across files with their number.
+    fn write_flightlines(&mut self, mode: FlightlineMode) -> Result<(), LasBoundsError> {
+
+        let features = std::mem::take(&mut self.flightline_features);
+        match mode {
+            FlightlineMode::File => {
+                debug!("Writing {} flightlines of files", features.len());
+                let layer = self.replace_layer(FLIGHTLINES_LAYER_NAME, OGRwkbGeometryType::wkbMultiPolygon, &[
+                    ("path", OGRFieldType::OFTString),
+                    ("point_source_id", OGRFieldType::OFTInteger),
+                    ("point_count", OGRFieldType::OFTInteger64),
+                    ("area", OGRFieldType::OFTReal)
+                ])?;
+                for (path, point_source_id, point_count, geometry) in features {
+                    let area = geometry.area();
+                    layer.create_feature_fields(geometry, &["path", "point_source_id", "point_count", "area"], &[
+                        FieldValue::StringValue(path),
+                        FieldValue::IntegerValue(i32::from(point_source_id)),
+                        FieldValue::Integer64Value(point_count as i64),
+                        FieldValue::RealValue(area)
+                    ])?;
+                }
+            },
+            FlightlineMode::Survey => {
+                let mut flightlines: BTreeMap<u16, (GeometryUnion, i64, u64)> = BTreeMap::new();
+                for (_, point_source_id, point_count, geometry) in &features {
+                    let (union, files, points) = flightlines.entry(*point_source_id).or_insert_with(|| (GeometryUnion::new(), 0, 0));
+                    union.add(geometry)?;
+                    *files += 1;
+                    *points += point_count;
+                }
+                debug!("Writing {} flightlines", flightlines.len());
+                let mut dissolved = Vec::new();
+                for (point_source_id, (union, files, points)) in flightlines {
+                    dissolved.push((point_source_id, files, points, ogr::multi_polygon(&union.union()?)?));
+                }
+                let layer = self.replace_layer(FLIGHTLINES_LAYER_NAME, OGRwkbGeometryType::wkbMultiPolygon, &[
+                    ("point_source_id", OGRFieldType::OFTInteger),
+                    ("files", OGRFieldType::OFTInteger64),
+                    ("point_count", OGRFieldType::OFTInteger64),
+                    ("area", OGRFieldType::OFTReal)
+                ])?;
+                for (point_source_id, files, point_count, geometry) in dissolved {
+                    let area = geometry.area();
+                    layer.create_feature_fields(geometry, &["point_source_id", "files", "point_count", "area"], &[
+                        FieldValue::IntegerValue(i32::from(point_source_id)),
+                        FieldValue::Integer64Value(files),
+                        FieldValue::Integer64Value(point_count as i64),
+                        FieldValue::RealValue(area)
+                    ])?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes intersections of footprint pairs with positive area. Pairs are found by sweeping
+    /// footprints sorted by minimum X, so only ones with overlapping envelopes are intersected.
+    fn write_overlaps(&mut self) -> Result<(), LasBoundsError> {
+
+        let mut footprints: Vec<_> = self.named_footprints()?.into_iter()
+            .map(|(name, geometry)| (ogr::envelope(&geometry), name, geometry))
+            .collect();
+        footprints.sort_by(|a, b| (a.0).0.partial_cmp(&(b.0).0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut overlaps = Vec::new();
+        for (i, (envelope, name, geometry)) in footprints.iter().enumerate() {
+            for (other_envelope, other_name, other_geometry) in &footprints[i + 1..] {
+                if other_envelope.0 >= envelope.2 {
+                    break;
+                }
+                if other_envelope.1 >= envelope.3 || other_envelope.3 <= envelope.1 {
+                    continue;
+                }
+                let overlap = ogr::multi_polygon(&ogr::intersection(geometry, other_geometry)?)?;
+                let area = overlap.area();
+                if area > 0.0 {
+                    overlaps.push((name.clone(), other_name.clone(), area, overlap));
+                }
+            }
+        }
+        debug!("Writing {} overlaps", overlaps.len());
+
+        let layer = self.replace_layer(OVERLAPS_LAYER_NAME, OGRwkbGeometryType::wkbMultiPolygon, &[
+            ("file_a", OGRFieldType::OFTString),
+            ("file_b", OGRFieldType::OFTString),
+            ("area", OGRFieldType::OFTReal)
+        ])?;
+        for (name, other_name, area, overlap) in overlaps {
+            layer.create_feature_fields(overlap, &["file_a", "file_b", "area"], &[
+                FieldValue::StringValue(name),
+                FieldValue::StringValue(other_name),
+                FieldValue::RealValue(area)
+            ])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BoundsWriter for OgrWriter {
+
+    fn contains(&self, path: &str) -> bool {
+        self.indexed.contains(path)
+    }
+
+    fn write(&mut self, record: &BoundsRecord) -> Result<(), LasBoundsError> {
+
+        let multi = self.multi;
+        let z = self.elevation.map(|elevation| elevation.z(record));
+        let write_flightlines = self.flightlines.is_some();
+        let transform = self.record_transform(record)?;
+        let footprint = if transform.is_some() { densified_footprint(record) } else { record.footprint.clone() };
+
+        let wkt = footprint.wkt(multi, z);
+        let mut geometry = Geometry::from_wkt(&wkt)?;
+        if let Some(transform) = transform {
+            geometry = geometry.transform(transform)?;
+        }
+        let mut flightlines = Vec::new();
+        if write_flightlines {
+            for flightline in &record.flightlines {
+                let mut geometry = Geometry::from_wkt(&flightline.footprint.wkt(true, None))?;
+                if let Some(transform) = transform {
+                    geometry = geometry.transform(transform)?;
+                }
+                flightlines.push((record.indexed_path.clone(), flightline.point_source_id, flightline.point_count, geometry));
+            }
+        }
+        if let Some(distance) = self.buffer {
+            geometry = buffered(&geometry, distance, multi)?;
+        }
+
+        // Unknown values are left unset.
+        let (layer_index, layer_fields) = self.record_layer(record)?;
+        let (names, values): (Vec<String>, Vec<FieldValue>) = layer_fields.iter()
+            .filter_map(|(field, name)| fields::value(record, field).map(|value| (name.clone(), ogr_field_value(value))))
+            .unzip();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        self.begin_transaction()?;
+        let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        ds.layer(layer_index)?.create_feature_fields(geometry, &names, &values)?;
+        if let Some(written) = self.transaction.as_mut() {
+            *written += 1;
+            if *written >= self.transaction_size {
+                self.commit_transaction()?;
+            }
+        }
+        self.indexed.insert(record.indexed_path.clone());
+        if self.hexbin.is_some() {
+            hexbin::merge(&mut self.hex_counts, &record.stats.hex_counts);
+        }
+        self.flightline_features.extend(flightlines);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), LasBoundsError> {
+
+        let layers: Vec<(isize, &LayerFields)> = std::iter::once((self.layer_index, &self.layer_fields))
+            .chain(self.extra_layers.values().map(|(index, layer_fields)| (*index, layer_fields)))
+            .collect();
+        let mut path_fields = Vec::new();
+        for (index, layer_fields) in layers {
+            let path_field = layer_fields.iter()
+                                 .find(|(field, _)| field.key == fields::PATH.key)
+                                 .map(|(_, name)| name.clone())
+                                 .ok_or_else(|| "Output has no path field, records can not be removed".to_string())?;
+            path_fields.push((index, path_field));
+        }
+
+        let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        for (index, path_field) in path_fields {
+            let layer = ds.layer(index)?;
+            let fids: Vec<u64> = layer.features()
+                .filter(|feature| matches!(feature.field(&path_field), Ok(FieldValue::StringValue(p)) if p == path))
+                .filter_map(|feature| feature.fid())
+                .collect();
+            for fid in fids {
+                ogr::delete_feature(layer, fid)?;
+            }
+        }
+        self.indexed.remove(path);
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), LasBoundsError> {
+
+        self.commit_transaction()?;
+        let indexes: Vec<isize> = std::iter::once(self.layer_index).chain(self.extra_layers.values().map(|(index, _)| *index)).collect();
+        let ds = self.ds.as_mut().ok_or_else(|| "Output is already finished".to_string())?;
+        for index in indexes {
+            ogr::sync_to_disk(ds.layer(index)?)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), LasBoundsError> {
+
+        // Auxiliary layers are computed from features read back from the dataset.
+        self.commit_transaction()?;
+        if self.union_layer.is_some() || self.detect_gaps {
+            let (coverage, files) = self.footprints_union()?;
+            if let Some(name) = self.union_layer.take() {
+                let area = coverage.area();
+                let layer = self.replace_layer(&name, OGRwkbGeometryType::wkbMultiPolygon,
+                                               &[("files", OGRFieldType::OFTInteger64), ("area", OGRFieldType::OFTReal)])?;
+                layer.create_feature_fields(coverage.clone(), &["files", "area"],
+                                            &[FieldValue::Integer64Value(files), FieldValue::RealValue(area)])?;
+            }
+            if self.detect_gaps {
+                self.write_gaps(&coverage)?;
+            }
+        }
+        if self.detect_overlaps {
+            self.write_overlaps()?;
+        }
+        if let Some(size) = self.hexbin {
+            self.write_hexbin(size)?;
+        }
+        if let Some(mode) = self.flightlines {
+            self.write_flightlines(mode)?;
+        }
+
+        // Dataset has to be closed before its files are moved.
+        self.ds.take();
+        if let Some((tmp, path)) = self.staged.take() {
+            ogr::delete_dataset(&self.driver_name, &path)?;
+            ogr::rename_dataset(&self.driver_name, &tmp, &path)?;
+        }
+
+        Ok(())
+    }
+
+    fn abort(&mut self) -> Result<(), LasBoundsError> {
+
+        // Features of the open transaction were reported as written.
+        self.commit_transaction()?;
+        self.ds.take();
+        if let Some((tmp, _)) = self.staged.take() {
+            ogr::delete_dataset(&self.driver_name, &tmp)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! ASCII point files with delimited coordinates per line (XYZ, CSV).
+
+use crate::LasBoundsError;
+use crate::density;
+use crate::hexbin;
+use crate::input;
+use crate::scan::{self, PointStats, ScanOptions};
+use las::{Bounds, Header};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+
+/// Layout of coordinate lines.
+#[derive(Clone)]
+pub struct XyzOptions {
+    /// Zero based indices of X, Y and Z columns.
+    pub columns: (usize, usize, usize),
+    /// Column delimiter, `None` for any run of whitespace, commas or semicolons.
+    pub delimiter: Option<char>,
+    /// Number of leading lines skipped, e.g. 1 for CSV with header row.
+    pub skip_lines: usize
+}
+
+impl Default for XyzOptions {
+    fn default() -> Self {
+        XyzOptions { columns: (0, 1, 2), delimiter: None, skip_lines: 0 }
+    }
+}
+
+fn parse_line(line: &str, options: &XyzOptions) -> Option<(f64, f64, f64)> {
+
+    let values: Vec<&str> = match options.delimiter {
+        Some(delimiter) => line.split(delimiter).map(str::trim).collect(),
+        None => line.split(|c: char| c.is_whitespace() || c == ',' || c == ';').filter(|s| !s.is_empty()).collect()
+    };
+    let value = |column: usize| values.get(column).and_then(|value| value.parse::<f64>().ok());
+
+    let (x, y, z) = options.columns;
+    Some((value(x)?, value(y)?, value(z)?))
+}
+
+/// Reads all coordinates of the file into a header with their bounds and count. XY coordinates are
+/// collected when requested by scan options, other point statistics are not available.
+pub fn read_points(path: &Path, options: &XyzOptions, scan: &ScanOptions)
+    -> Result<(Header, PointStats), LasBoundsError> {
+
+    let reader = BufReader::new(File::open(path)?);
+    let mut bounds = Bounds::default();
+    let mut count = 0u64;
+    let mut stats = PointStats::default();
+
+    for (i, line) in reader.lines().enumerate().skip(options.skip_lines) {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (x, y, z) = parse_line(line, options)
+            .ok_or_else(|| format!("Invalid coordinates at {}:{}", path.to_string_lossy(), i + 1))?;
+        input::grow(&mut bounds, x, y, z);
+
+        if scan.xy && count % scan.thin as u64 == 0 {
+            scan::push_xy(&mut stats.xy, (x, y), scan);
+        }
+        if let Some(size) = scan.hexbin {
+            *stats.hex_counts.entry(hexbin::hex_of(x, y, size)).or_insert(0) += 1;
+        }
+        if let Some(size) = scan.density_cell {
+            *stats.cell_counts.entry(density::cell_of(x, y, size)).or_insert(0) += 1;
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        return Err(format!("No points in {}", path.to_string_lossy()).into());
+    }
+
+    let header = input::synthetic_header(&bounds, count, Vec::new())?;
+    Ok((header, stats))
+}